@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use emulator::instruction::Instruction;
+
+mod parser;
+
+use parser::{Expr, Operand, Statement, Term};
+
+/// Address ROMs are assembled for, matching where `Chip8::load` places them
+pub const ORIGIN: u16 = emulator::PROGRAM_START;
+
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Error found while assembling, pointing at the offending line and column
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AssembleError {
+    /// File the error was found in, or `<input>` for in-memory source
+    pub source: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.source, self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+/// Output of a successful assembly
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Assembly {
+    /// ROM image, to be loaded at `ORIGIN`
+    pub rom: Vec<u8>,
+    /// Address of every label defined in the source
+    pub labels: HashMap<String, u16>,
+    /// Every line that emitted bytes, in address order
+    pub lines: Vec<LineMapping>,
+}
+
+impl Assembly {
+    /// Line that emitted the byte at `addr`
+    pub fn line_at(&self, addr: u16) -> Option<&LineMapping> {
+        let idx = self.lines.partition_point(|line| line.addr <= addr);
+        let line = self.lines.get(idx.checked_sub(1)?)?;
+        (addr as usize - (line.addr as usize) < line.len).then_some(line)
+    }
+
+    /// First line at or after `line` in `source` that emitted bytes
+    pub fn line_from(&self, source: &str, line: usize) -> Option<&LineMapping> {
+        self.lines
+            .iter()
+            .filter(|mapping| mapping.source == source && mapping.line >= line)
+            .min_by_key(|mapping| mapping.line)
+    }
+
+    /// Closest label at or before `addr`
+    pub fn label_before(&self, addr: u16) -> Option<(&str, u16)> {
+        self.labels
+            .iter()
+            .filter(|(_, &label)| label <= addr)
+            .max_by_key(|(name, &label)| (label, std::cmp::Reverse(name.as_str())))
+            .map(|(name, &label)| (name.as_str(), label))
+    }
+}
+
+/// Where the bytes for one line of source were placed
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct LineMapping {
+    /// File the line is in, named as in `AssembleError`
+    pub source: String,
+    pub line: usize,
+    pub addr: u16,
+    pub len: usize,
+}
+
+/// Assemble in-memory source, resolving includes against the working directory
+pub fn assemble(source: &str) -> Result<Assembly, AssembleError> {
+    let mut lines = Vec::new();
+    read_source("<input>", source, Path::new("."), 0, &mut lines)?;
+    Assembler::new(lines)?.run()
+}
+
+/// Assemble a file, resolving includes relative to the including file
+pub fn assemble_file(path: &Path) -> Result<Assembly, AssembleError> {
+    let name = path.display().to_string();
+    let text = fs::read_to_string(path).map_err(|err| AssembleError {
+        source: name.clone(),
+        line: 0,
+        column: 0,
+        message: err.to_string(),
+    })?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut lines = Vec::new();
+    read_source(&name, &text, dir, 0, &mut lines)?;
+    Assembler::new(lines)?.run()
+}
+
+/// Parsed line along with where it came from
+struct SourceLine {
+    source: String,
+    number: usize,
+    line: parser::Line,
+}
+
+impl SourceLine {
+    fn error(&self, err: (usize, String)) -> AssembleError {
+        AssembleError::at(&self.source, self.number, err)
+    }
+}
+
+/// Parse `text`, splicing in the lines of any included files
+fn read_source(
+    name: &str,
+    text: &str,
+    dir: &Path,
+    depth: usize,
+    lines: &mut Vec<SourceLine>,
+) -> Result<(), AssembleError> {
+    for (idx, text) in text.lines().enumerate() {
+        let number = idx + 1;
+        let line = parser::parse_line(text).map_err(|err| AssembleError::at(name, number, err))?;
+
+        if let Some(Statement::Include(path, col)) = &line.statement {
+            let include_err = |message: String| AssembleError::at(name, number, (*col, message));
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err(include_err("includes nested too deeply".to_string()));
+            }
+
+            let path: PathBuf = dir.join(path);
+            let text = fs::read_to_string(&path)
+                .map_err(|err| include_err(format!("{}: {}", path.display(), err)))?;
+            let dir = path.parent().unwrap_or(dir);
+
+            // Keep any label on the include line, it marks the start of the included code
+            if line.label.is_some() {
+                lines.push(SourceLine {
+                    source: name.to_string(),
+                    number,
+                    line: parser::Line {
+                        label: line.label.clone(),
+                        statement: None,
+                    },
+                });
+            }
+            read_source(&path.display().to_string(), &text, dir, depth + 1, lines)?;
+            continue;
+        }
+
+        lines.push(SourceLine {
+            source: name.to_string(),
+            number,
+            line,
+        });
+    }
+    Ok(())
+}
+
+enum Symbol {
+    Label(u16),
+    Constant(Expr),
+}
+
+struct Assembler {
+    lines: Vec<SourceLine>,
+    symbols: HashMap<String, Symbol>,
+}
+
+impl Assembler {
+    /// Deepest chain of constants referring to other constants
+    const MAX_EVAL_DEPTH: usize = 64;
+
+    /// First pass, assigning addresses to every label and collecting constants
+    fn new(lines: Vec<SourceLine>) -> Result<Self, AssembleError> {
+        let mut symbols = HashMap::new();
+        let mut addr = ORIGIN as usize;
+
+        for line in &lines {
+            let mut define = |name: &String, col: usize, symbol: Symbol| {
+                if symbols.insert(name.clone(), symbol).is_some() {
+                    return Err(line.error((col, format!("'{}' is already defined", name))));
+                }
+                Ok(())
+            };
+
+            if let Some((name, col)) = &line.line.label {
+                define(name, *col, Symbol::Label(addr as u16))?;
+            }
+            if let Some(Statement::Constant(name, expr)) = &line.line.statement {
+                define(name, 1, Symbol::Constant(expr.clone()))?;
+            }
+
+            addr += line.line.size();
+            if addr > 0x10000 {
+                return Err(line.error((1, "program does not fit in memory".to_string())));
+            }
+        }
+
+        Ok(Self { lines, symbols })
+    }
+
+    /// Second pass, emitting bytes now that every symbol is known
+    fn run(self) -> Result<Assembly, AssembleError> {
+        let mut rom = Vec::new();
+        let mut lines = Vec::new();
+
+        for line in &self.lines {
+            let start = rom.len();
+            self.emit(&line.line, &mut rom)
+                .map_err(|err| line.error(err))?;
+            if rom.len() > start {
+                lines.push(LineMapping {
+                    source: line.source.clone(),
+                    line: line.number,
+                    addr: (ORIGIN as usize + start) as u16,
+                    len: rom.len() - start,
+                });
+            }
+        }
+
+        let labels = self
+            .symbols
+            .iter()
+            .filter_map(|(name, symbol)| match symbol {
+                Symbol::Label(addr) => Some((name.clone(), *addr)),
+                Symbol::Constant(_) => None,
+            })
+            .collect();
+
+        Ok(Assembly { rom, labels, lines })
+    }
+
+    fn emit(&self, line: &parser::Line, rom: &mut Vec<u8>) -> Result<(), (usize, String)> {
+        match &line.statement {
+            Some(Statement::Instruction {
+                mnemonic,
+                operands,
+                col,
+            }) => {
+                let instr = self.encode(mnemonic, operands, *col)?;
+                rom.extend(instr.to_bytes());
+            }
+            Some(Statement::Bytes(operands)) => {
+                for operand in operands {
+                    match operand {
+                        Operand::Str(text, _) => rom.extend_from_slice(text.as_bytes()),
+                        operand => rom.push(self.value(operand, 0xFF)? as u8),
+                    }
+                }
+            }
+            Some(Statement::Words(operands)) => {
+                for operand in operands {
+                    rom.extend_from_slice(&self.value(operand, 0xFFFF)?.to_be_bytes());
+                }
+            }
+            Some(Statement::Sprite(rows)) => {
+                for row in rows {
+                    match row {
+                        Operand::Str(text, col) => rom.extend(sprite_row(text, *col)?),
+                        operand => rom.push(self.value(operand, 0xFF)? as u8),
+                    }
+                }
+            }
+            Some(Statement::Constant(..)) | Some(Statement::Include(..)) | None => {}
+        }
+        Ok(())
+    }
+
+    fn encode(
+        &self,
+        mnemonic: &str,
+        operands: &[Operand],
+        col: usize,
+    ) -> Result<Instruction, (usize, String)> {
+        use Instruction::*;
+        use Operand::Reserved;
+
+        let reserved = |operand: &Operand, name: &str| matches!(operand, Reserved(reserved, _) if reserved == name);
+
+        let instr = match (mnemonic, operands) {
+            ("CLS", []) => ClearDisplay,
+            ("RET", []) => ReturnFromSubroutine,
+            ("JP", [v0, addr]) if reserved(v0, "V0") => JumpV0PlusByte(self.value(addr, 0xFFF)?),
+            ("JP", [addr]) => Jump(self.value(addr, 0xFFF)?),
+            ("CALL", [addr]) => CallSubroutine(self.value(addr, 0xFFF)?),
+            ("SE", [x, y]) if is_register(y) => SkipRegEqReg(register(x)?, register(y)?),
+            ("SE", [x, byte]) => SkipRegEqByte(register(x)?, self.byte(byte)?),
+            ("SNE", [x, y]) if is_register(y) => SkipRegNotEqReg(register(x)?, register(y)?),
+            ("SNE", [x, byte]) => SkipRegNotEqByte(register(x)?, self.byte(byte)?),
+            ("LD", [i, Operand::Long(addr)]) if reserved(i, "I") => {
+                SetILong(self.eval_range(addr, 0xFFFF)?)
+            }
+            ("LD", [i, addr]) if reserved(i, "I") => SetI(self.value(addr, 0xFFF)?),
+            ("LD", [dt, x]) if reserved(dt, "DT") => SetDelayToReg(register(x)?),
+            ("LD", [st, x]) if reserved(st, "ST") => SetSoundToReg(register(x)?),
+            ("LD", [f, x]) if reserved(f, "F") => SetIToDigitSpriteLoc(register(x)?),
+            ("LD", [hf, x]) if reserved(hf, "HF") => SetIToBigDigitSpriteLoc(register(x)?),
+            ("LD", [r, x]) if reserved(r, "R") => StoreRegsToFlags(register(x)?),
+            ("LD", [x, r]) if reserved(r, "R") => LoadRegsFromFlags(register(x)?),
+            ("LD", [b, x]) if reserved(b, "B") => StoreNumberFromRegToI(register(x)?),
+            ("LD", [i, x]) if reserved(i, "[I]") => StoreRegsToMem(register(x)?),
+            ("LD", [x, dt]) if reserved(dt, "DT") => LoadDelayToReg(register(x)?),
+            ("LD", [x, k]) if reserved(k, "K") => LoadKeyToReg(register(x)?),
+            ("LD", [x, i]) if reserved(i, "[I]") => LoadRegsFromMem(register(x)?),
+            ("LD", [x, y]) if is_register(y) => MoveValue(register(x)?, register(y)?),
+            ("LD", [x, byte]) => SetRegToByte(register(x)?, self.byte(byte)?),
+            ("ADD", [i, x]) if reserved(i, "I") => AddRegToI(register(x)?),
+            ("ADD", [x, y]) if is_register(y) => AddRegs(register(x)?, register(y)?),
+            ("ADD", [x, byte]) => AddByteToReg(register(x)?, self.byte(byte)?),
+            ("OR", [x, y]) => OrRegs(register(x)?, register(y)?),
+            ("AND", [x, y]) => AndRegs(register(x)?, register(y)?),
+            ("XOR", [x, y]) => XorRegs(register(x)?, register(y)?),
+            ("SUB", [x, y]) => SubRegs(register(x)?, register(y)?),
+            ("SUBN", [x, y]) => ReverseSubRegs(register(x)?, register(y)?),
+            ("SHR", [x]) => ShiftRight(register(x)?, register(x)?),
+            ("SHR", [x, y]) => ShiftRight(register(x)?, register(y)?),
+            ("SHL", [x]) => ShiftLeft(register(x)?, register(x)?),
+            ("SHL", [x, y]) => ShiftLeft(register(x)?, register(y)?),
+            ("RND", [x, byte]) => SetRegToRandPlusByte(register(x)?, self.byte(byte)?),
+            ("DRW", [x, y, n]) => DrawSprite(register(x)?, register(y)?, self.value(n, 0xF)? as u8),
+            ("SKP", [x]) => SkipIfKey(register(x)?),
+            ("SKNP", [x]) => SkipIfNotKey(register(x)?),
+            ("SCD", [n]) => ScrollDown(self.value(n, 0xF)? as u8),
+            ("SCU", [n]) => ScrollUp(self.value(n, 0xF)? as u8),
+            ("SAVE", [x, y]) => StoreRegRange(register(x)?, register(y)?),
+            ("LOAD", [x, y]) => LoadRegRange(register(x)?, register(y)?),
+            ("PLANE", [n]) => SelectPlanes(self.value(n, 0xF)? as u8),
+            ("PITCH", [x]) => SetPitchToReg(register(x)?),
+            ("SCR", []) => ScrollRight,
+            ("SCL", []) => ScrollLeft,
+            ("EXIT", []) => Exit,
+            ("LOW", []) => LowResolution,
+            ("HIGH", []) => HighResolution,
+            ("AUDIO", []) => LoadAudioPattern,
+            _ => {
+                return Err((
+                    col,
+                    format!(
+                        "unknown instruction '{}' with {} operand(s)",
+                        mnemonic,
+                        operands.len()
+                    ),
+                ))
+            }
+        };
+
+        Ok(instr)
+    }
+
+    fn byte(&self, operand: &Operand) -> Result<u8, (usize, String)> {
+        Ok(self.value(operand, 0xFF)? as u8)
+    }
+
+    /// Evaluate an operand, checking it lies within `0..=max`
+    fn value(&self, operand: &Operand, max: u16) -> Result<u16, (usize, String)> {
+        let expr = match operand {
+            Operand::Expr(expr) => expr,
+            operand => return Err((operand.col(), "expected a value".to_string())),
+        };
+
+        self.eval_range(expr, max)
+    }
+
+    fn eval_range(&self, expr: &Expr, max: u16) -> Result<u16, (usize, String)> {
+        let value = self.eval(expr, 0)?;
+        if value < 0 || value > max as i64 {
+            return Err((
+                expr.col,
+                format!("value {:#X} out of range, maximum is {:#X}", value, max),
+            ));
+        }
+        Ok(value as u16)
+    }
+
+    fn eval(&self, expr: &Expr, depth: usize) -> Result<i64, (usize, String)> {
+        if depth > Self::MAX_EVAL_DEPTH {
+            return Err((expr.col, "constant refers to itself".to_string()));
+        }
+
+        let mut total = 0;
+        for (negate, term) in &expr.terms {
+            let value = match term {
+                Term::Number(num) => *num as i64,
+                Term::Symbol(name) => match self.symbols.get(name) {
+                    Some(Symbol::Label(addr)) => *addr as i64,
+                    Some(Symbol::Constant(expr)) => self.eval(expr, depth + 1)?,
+                    None => return Err((expr.col, format!("undefined symbol '{}'", name))),
+                },
+            };
+            total += if *negate { -value } else { value };
+        }
+        Ok(total)
+    }
+}
+
+fn is_register(operand: &Operand) -> bool {
+    matches!(operand, Operand::Reserved(name, _) if name.starts_with('V'))
+}
+
+fn register(operand: &Operand) -> Result<u8, (usize, String)> {
+    match operand {
+        Operand::Reserved(name, _) if name.starts_with('V') => {
+            Ok(u8::from_str_radix(&name[1..], 16).unwrap())
+        }
+        operand => Err((operand.col(), "expected a register V0-VF".to_string())),
+    }
+}
+
+/// Convert a sprite literal such as `"..####.."` into bytes, 8 pixels per byte
+fn sprite_row(text: &str, col: usize) -> Result<Vec<u8>, (usize, String)> {
+    let mut bytes = vec![0; text.len().div_ceil(8)];
+    for (idx, pixel) in text.chars().enumerate() {
+        let set = match pixel {
+            '#' | 'X' | 'x' | '1' => true,
+            '.' | ' ' | '0' => false,
+            c => return Err((col, format!("invalid sprite pixel '{}'", c))),
+        };
+        if set {
+            bytes[idx / 8] |= 0x80 >> (idx % 8);
+        }
+    }
+    Ok(bytes)
+}
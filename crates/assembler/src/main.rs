@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+pub fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let (input, output) = match args.as_slice() {
+        [_, input] => (
+            PathBuf::from(input),
+            PathBuf::from(input).with_extension("ch8"),
+        ),
+        [_, input, flag, output] if flag == "-o" => (PathBuf::from(input), PathBuf::from(output)),
+        _ => {
+            println!("usage: chip8-asm source [-o romfile]");
+            return;
+        }
+    };
+
+    let assembly = match assembler::assemble_file(&input) {
+        Ok(assembly) => assembly,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = std::fs::write(&output, assembly.rom) {
+        eprintln!("{}: {}", output.display(), err);
+        std::process::exit(1);
+    }
+}
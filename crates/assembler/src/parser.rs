@@ -0,0 +1,336 @@
+use crate::AssembleError;
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub(crate) enum TokenKind {
+    Word,
+    Str,
+    Comma,
+    Colon,
+    Plus,
+    Minus,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) text: String,
+    pub(crate) col: usize,
+}
+
+/// A term of an expression, either a number or a symbol to be resolved later
+#[derive(Debug, Clone)]
+pub(crate) enum Term {
+    Number(u32),
+    Symbol(String),
+}
+
+/// Sum of terms, e.g. `sprites + 5 - 1`
+#[derive(Debug, Clone)]
+pub(crate) struct Expr {
+    pub(crate) terms: Vec<(bool, Term)>,
+    pub(crate) col: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Operand {
+    /// Register or other reserved name, e.g. `V3`, `I`, `DT` or `[I]`
+    Reserved(String, usize),
+    Expr(Expr),
+    /// XO-CHIP 16-bit address, e.g. `LONG #1234`
+    Long(Expr),
+    Str(String, usize),
+}
+
+impl Operand {
+    pub(crate) fn col(&self) -> usize {
+        match self {
+            Self::Reserved(_, col) | Self::Str(_, col) => *col,
+            Self::Expr(expr) | Self::Long(expr) => expr.col,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Statement {
+    Instruction {
+        mnemonic: String,
+        operands: Vec<Operand>,
+        col: usize,
+    },
+    Bytes(Vec<Operand>),
+    Words(Vec<Operand>),
+    Sprite(Vec<Operand>),
+    Constant(String, Expr),
+    Include(String, usize),
+}
+
+/// A parsed source line, with any label defined at its start
+#[derive(Debug, Clone)]
+pub(crate) struct Line {
+    pub(crate) label: Option<(String, usize)>,
+    pub(crate) statement: Option<Statement>,
+}
+
+const RESERVED: [&str; 25] = [
+    "V0", "V1", "V2", "V3", "V4", "V5", "V6", "V7", "V8", "V9", "VA", "VB", "VC", "VD", "VE", "VF",
+    "I", "[I]", "DT", "ST", "K", "F", "HF", "B", "R",
+];
+
+/// Split a line into tokens, dropping any trailing comment
+pub(crate) fn tokenize(text: &str) -> Result<Vec<Token>, (usize, String)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let c = chars[idx];
+        let col = idx + 1;
+        let single = |kind| Token {
+            kind,
+            text: c.to_string(),
+            col,
+        };
+        match c {
+            ';' => break,
+            c if c.is_whitespace() => idx += 1,
+            ',' => {
+                tokens.push(single(TokenKind::Comma));
+                idx += 1;
+            }
+            ':' => {
+                tokens.push(single(TokenKind::Colon));
+                idx += 1;
+            }
+            '+' => {
+                tokens.push(single(TokenKind::Plus));
+                idx += 1;
+            }
+            '-' => {
+                tokens.push(single(TokenKind::Minus));
+                idx += 1;
+            }
+            '"' => {
+                let end = match chars[idx + 1..].iter().position(|&c| c == '"') {
+                    Some(end) => idx + 1 + end,
+                    None => return Err((col, "unterminated string".to_string())),
+                };
+                tokens.push(Token {
+                    kind: TokenKind::Str,
+                    text: chars[idx + 1..end].iter().collect(),
+                    col,
+                });
+                idx = end + 1;
+            }
+            c if is_word_char(c) => {
+                let start = idx;
+                while idx < chars.len() && is_word_char(chars[idx]) {
+                    idx += 1;
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Word,
+                    text: chars[start..idx].iter().collect(),
+                    col,
+                });
+            }
+            c => return Err((col, format!("unexpected character '{}'", c))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '#' | '$' | '%' | '[' | ']')
+}
+
+/// Parse a single line of source
+pub(crate) fn parse_line(text: &str) -> Result<Line, (usize, String)> {
+    let tokens = tokenize(text)?;
+    let mut tokens = tokens.as_slice();
+
+    let mut label = None;
+    if let [name, colon, rest @ ..] = tokens {
+        if name.kind == TokenKind::Word && colon.kind == TokenKind::Colon {
+            check_symbol(name)?;
+            label = Some((name.text.clone(), name.col));
+            tokens = rest;
+        }
+    }
+
+    let statement = match tokens {
+        [] => None,
+        [name, equ, rest @ ..]
+            if name.kind == TokenKind::Word
+                && equ.kind == TokenKind::Word
+                && equ.text.eq_ignore_ascii_case("equ") =>
+        {
+            check_symbol(name)?;
+            let expr = match parse_operands(rest, equ.col + equ.text.len())?.as_slice() {
+                [Operand::Expr(expr)] => expr.clone(),
+                [operand, ..] => return Err((operand.col(), "expected expression".to_string())),
+                [] => return Err((equ.col, "missing value for EQU".to_string())),
+            };
+            Some(Statement::Constant(name.text.clone(), expr))
+        }
+        [keyword, rest @ ..] if keyword.kind == TokenKind::Word => {
+            let operands = parse_operands(rest, keyword.col + keyword.text.len())?;
+            let mnemonic = keyword.text.to_ascii_uppercase();
+            Some(match mnemonic.as_str() {
+                "DB" => Statement::Bytes(operands),
+                "DW" => Statement::Words(operands),
+                "SPRITE" => Statement::Sprite(operands),
+                "INCLUDE" => match operands.as_slice() {
+                    [Operand::Str(path, col)] => Statement::Include(path.clone(), *col),
+                    _ => return Err((keyword.col, "expected a quoted path".to_string())),
+                },
+                _ => Statement::Instruction {
+                    mnemonic,
+                    operands,
+                    col: keyword.col,
+                },
+            })
+        }
+        [token, ..] => return Err((token.col, format!("unexpected '{}'", token.text))),
+    };
+
+    Ok(Line { label, statement })
+}
+
+fn check_symbol(token: &Token) -> Result<(), (usize, String)> {
+    let upper = token.text.to_ascii_uppercase();
+    let valid_start = token
+        .text
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.');
+    if !valid_start || RESERVED.contains(&upper.as_str()) {
+        return Err((token.col, format!("invalid symbol name '{}'", token.text)));
+    }
+    Ok(())
+}
+
+/// Parse comma separated operands, `end_col` is used to report a dangling comma
+fn parse_operands(tokens: &[Token], end_col: usize) -> Result<Vec<Operand>, (usize, String)> {
+    let mut operands = Vec::new();
+    if tokens.is_empty() {
+        return Ok(operands);
+    }
+
+    for group in tokens.split(|token| token.kind == TokenKind::Comma) {
+        let operand = match group {
+            [] => return Err((end_col, "missing operand".to_string())),
+            [token] if token.kind == TokenKind::Str => Operand::Str(token.text.clone(), token.col),
+            [token]
+                if token.kind == TokenKind::Word
+                    && RESERVED.contains(&token.text.to_ascii_uppercase().as_str()) =>
+            {
+                Operand::Reserved(token.text.to_ascii_uppercase(), token.col)
+            }
+            [long, rest @ ..] if long.text.eq_ignore_ascii_case("long") && !rest.is_empty() => {
+                Operand::Long(parse_expr(rest)?)
+            }
+            _ => Operand::Expr(parse_expr(group)?),
+        };
+        operands.push(operand);
+    }
+
+    Ok(operands)
+}
+
+fn parse_expr(tokens: &[Token]) -> Result<Expr, (usize, String)> {
+    let mut terms = Vec::new();
+    let mut negate = false;
+    let mut expect_term = true;
+
+    for token in tokens {
+        match (token.kind, expect_term) {
+            (TokenKind::Word, true) => {
+                terms.push((negate, parse_term(token)?));
+                negate = false;
+                expect_term = false;
+            }
+            (TokenKind::Minus, true) => negate = !negate,
+            (TokenKind::Minus, false) => {
+                negate = true;
+                expect_term = true;
+            }
+            (TokenKind::Plus, false) => expect_term = true,
+            _ => return Err((token.col, format!("unexpected '{}'", token.text))),
+        }
+    }
+
+    if expect_term {
+        let col = tokens.last().map_or(1, |token| token.col);
+        return Err((col, "incomplete expression".to_string()));
+    }
+
+    Ok(Expr {
+        terms,
+        col: tokens[0].col,
+    })
+}
+
+fn parse_term(token: &Token) -> Result<Term, (usize, String)> {
+    let text = token.text.as_str();
+    let lower = text.to_ascii_lowercase();
+    let number = if let Some(hex) = lower
+        .strip_prefix("0x")
+        .or_else(|| lower.strip_prefix('#'))
+        .or_else(|| lower.strip_prefix('$'))
+    {
+        u32::from_str_radix(hex, 16)
+    } else if let Some(bin) = lower.strip_prefix("0b").or_else(|| lower.strip_prefix('%')) {
+        u32::from_str_radix(bin, 2)
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        text.parse::<u32>()
+    } else {
+        check_symbol(token)?;
+        return Ok(Term::Symbol(text.to_string()));
+    };
+
+    number
+        .map(Term::Number)
+        .map_err(|_| (token.col, format!("invalid number '{}'", text)))
+}
+
+impl Line {
+    /// Number of bytes this line emits into the ROM
+    pub(crate) fn size(&self) -> usize {
+        match &self.statement {
+            Some(Statement::Instruction { operands, .. }) => {
+                if operands.iter().any(|op| matches!(op, Operand::Long(_))) {
+                    4
+                } else {
+                    2
+                }
+            }
+            Some(Statement::Bytes(operands)) => operands
+                .iter()
+                .map(|operand| match operand {
+                    Operand::Str(text, _) => text.len(),
+                    _ => 1,
+                })
+                .sum(),
+            Some(Statement::Words(operands)) => operands.len() * 2,
+            Some(Statement::Sprite(rows)) => rows
+                .iter()
+                .map(|row| match row {
+                    Operand::Str(text, _) => text.len().div_ceil(8),
+                    _ => 1,
+                })
+                .sum(),
+            Some(Statement::Constant(..)) | Some(Statement::Include(..)) | None => 0,
+        }
+    }
+}
+
+impl AssembleError {
+    pub(crate) fn at(source: &str, line: usize, (column, message): (usize, String)) -> Self {
+        Self {
+            source: source.to_string(),
+            line,
+            column,
+            message,
+        }
+    }
+}
@@ -0,0 +1,6 @@
+; Draws a digit whose sprite lives in another directory
+start:  LD I, digit
+        DRW V0, V0, 5
+        JP start
+
+digit:  INCLUDE "sprites/zero.asm"
@@ -0,0 +1,2 @@
+        CLS
+        INCLUDE "nowhere.asm"
@@ -0,0 +1,3 @@
+; Includes itself until the nesting limit is hit
+        CLS
+        INCLUDE "recursive.asm"
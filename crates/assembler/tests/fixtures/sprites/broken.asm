@@ -0,0 +1,2 @@
+        RET
+        LD V0, missing
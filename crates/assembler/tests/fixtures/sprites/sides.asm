@@ -0,0 +1 @@
+        DB #90, #90, #90
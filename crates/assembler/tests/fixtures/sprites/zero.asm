@@ -0,0 +1,4 @@
+; Top and bottom rows, with the sides included from next to this file
+        DB #F0
+        INCLUDE "sides.asm"
+        DB #F0
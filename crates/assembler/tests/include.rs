@@ -0,0 +1,56 @@
+//! `INCLUDE` resolved from files in `tests/fixtures`
+
+use std::path::{Path, PathBuf};
+
+use assembler::assemble_file;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+#[test]
+fn includes_relative_to_the_including_file() {
+    let assembly = assemble_file(&fixture("main.asm")).unwrap();
+    assert_eq!(
+        assembly.rom,
+        [0xA2, 0x06, 0xD0, 0x05, 0x12, 0x00, 0xF0, 0x90, 0x90, 0x90, 0xF0]
+    );
+    assert_eq!(assembly.labels["digit"], 0x206);
+
+    let line = |addr| {
+        let line = assembly.line_at(addr).unwrap();
+        let file = Path::new(&line.source).file_name().unwrap();
+        (file.to_str().unwrap().to_string(), line.line)
+    };
+    assert_eq!(line(0x202), ("main.asm".to_string(), 3));
+    assert_eq!(line(0x206), ("zero.asm".to_string(), 2));
+    assert_eq!(line(0x208), ("sides.asm".to_string(), 1));
+    assert_eq!(line(0x20A), ("zero.asm".to_string(), 4));
+}
+
+#[test]
+fn include_depth_is_limited() {
+    let err = assemble_file(&fixture("recursive.asm")).unwrap_err();
+    assert_eq!(err.message, "includes nested too deeply");
+    assert!(err.source.ends_with("recursive.asm"), "{}", err.source);
+    assert_eq!((err.line, err.column), (3, 17));
+}
+
+#[test]
+fn errors_point_into_the_included_file() {
+    let err = assemble_file(&fixture("broken.asm")).unwrap_err();
+    let source = Path::new(&err.source);
+    assert!(source.ends_with("sprites/broken.asm"), "{}", err.source);
+    assert_eq!((err.line, err.column), (2, 16));
+}
+
+#[test]
+fn missing_include() {
+    let err = assemble_file(&fixture("missing.asm")).unwrap_err();
+    assert!(err.source.ends_with("missing.asm"), "{}", err.source);
+    assert_eq!((err.line, err.column), (2, 17));
+    assert!(err.message.contains("nowhere.asm"), "{}", err.message);
+}
@@ -0,0 +1,121 @@
+use assembler::{assemble, ORIGIN};
+use emulator::disassembler::Syntax;
+use emulator::instruction::Instruction;
+
+/// Every opcode the decoder accepts must survive disassembly and reassembly
+#[test]
+fn every_opcode_round_trips() {
+    for opcode in 0..=u16::MAX {
+        let instr = match Instruction::try_from(opcode) {
+            Ok(instr) => instr,
+            Err(_) => continue,
+        };
+
+        let source = instr.mnemonic(Syntax::Cowgod);
+        let rom = match assemble(&source) {
+            Ok(assembly) => assembly.rom,
+            Err(err) => panic!("{:04X} `{}` failed to assemble: {}", opcode, source, err),
+        };
+        let reassembled = u16::from_be_bytes([rom[0], rom[1]]);
+
+        assert_eq!(rom.len(), 2, "`{}`", source);
+        assert_eq!(
+            Instruction::try_from(reassembled).ok(),
+            Some(instr),
+            "{:04X} `{}` reassembled to {:04X}",
+            opcode,
+            source,
+            reassembled
+        );
+    }
+}
+
+#[test]
+fn long_i_round_trips() {
+    for addr in [0x0000, 0x0FFF, 0x1234, 0xFFFF] {
+        let instr = Instruction::SetILong(addr);
+        let source = instr.mnemonic(Syntax::Cowgod);
+        let rom = assemble(&source).unwrap().rom;
+
+        assert_eq!(rom, instr.to_bytes(), "`{}`", source);
+        assert_eq!(Instruction::decode(&rom).ok(), Some(instr), "`{}`", source);
+    }
+}
+
+#[test]
+fn labels_and_constants() {
+    let assembly = assemble(
+        "
+        SPEED EQU 2
+        start:  LD V0, SPEED + 1 ; comment
+                CALL draw
+                JP start
+        draw:   LD I, sprite
+                RET
+        sprite: DB #FF, %10000001
+        ",
+    )
+    .unwrap();
+
+    assert_eq!(
+        assembly.rom,
+        vec![0x60, 0x03, 0x22, 0x06, 0x12, 0x00, 0xA2, 0x0A, 0x00, 0xEE, 0xFF, 0x81]
+    );
+    assert_eq!(assembly.labels["start"], ORIGIN);
+    assert_eq!(assembly.labels["sprite"], ORIGIN + 10);
+}
+
+#[test]
+fn data_directives() {
+    let assembly = assemble(
+        r#"
+        dw #1234, end
+        db "AB", 3
+        sprite "X......X", "XXXXXXXX########", "..11.."
+        end:
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        assembly.rom,
+        vec![0x12, 0x34, 0x02, 0x0B, b'A', b'B', 3, 0x81, 0xFF, 0xFF, 0x30]
+    );
+}
+
+#[test]
+fn errors_report_line_and_column() {
+    let err = assemble("CLS\n  LD V0, missing").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 10));
+
+    let err = assemble("LD V0, #100").unwrap_err();
+    assert_eq!((err.line, err.column), (1, 8));
+
+    let err = assemble("  FOO V0").unwrap_err();
+    assert_eq!((err.line, err.column), (1, 3));
+
+    let err = assemble("a: CLS\na: RET").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 1));
+}
+
+#[test]
+fn line_map() {
+    let assembly = assemble("start:\n  CLS\n\n  DB 1, 2, 3\n  JP start\n").unwrap();
+
+    let lines: Vec<_> = assembly
+        .lines
+        .iter()
+        .map(|line| (line.line, line.addr, line.len))
+        .collect();
+    assert_eq!(lines, vec![(2, 0x200, 2), (4, 0x202, 3), (5, 0x205, 2)]);
+
+    assert_eq!(assembly.line_at(0x204).map(|line| line.line), Some(4));
+    assert_eq!(assembly.line_at(0x206).map(|line| line.line), Some(5));
+    assert_eq!(assembly.line_at(0x207), None);
+    assert_eq!(
+        assembly.line_from("<input>", 3).map(|line| line.addr),
+        Some(0x202)
+    );
+    assert_eq!(assembly.line_from("<input>", 6), None);
+    assert_eq!(assembly.label_before(0x206), Some(("start", 0x200)));
+}
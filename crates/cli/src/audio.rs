@@ -0,0 +1,66 @@
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+
+use emulator::audio::{AudioSink, WavSink, WAV_SAMPLE_RATE};
+
+/// Plays the buzzer through an SDL audio queue, optionally recording it to a WAV file
+pub struct SdlAudioSink {
+    queue: Option<AudioQueue<i16>>,
+    recording: Option<WavSink>,
+}
+
+impl SdlAudioSink {
+    /// The queue is opened without allowing changes to the spec, so SDL converts from
+    /// this rate to the device's, and recordings match headless ones
+    const SAMPLE_RATE: u32 = WAV_SAMPLE_RATE;
+    /// Samples allowed to pile up in the queue before new ones are dropped
+    const MAX_QUEUED: u32 = Self::SAMPLE_RATE / 10;
+
+    /// Open the default output device, falling back to silence if there isn't one
+    pub fn new(audio: Option<AudioSubsystem>, record: bool) -> Self {
+        let desired = AudioSpecDesired {
+            freq: Some(Self::SAMPLE_RATE as i32),
+            channels: Some(1),
+            samples: None,
+        };
+        let queue = audio.and_then(|audio| match audio.open_queue::<i16, _>(None, &desired) {
+            Ok(queue) => {
+                queue.resume();
+                Some(queue)
+            }
+            Err(err) => {
+                eprintln!("unable to open audio device: {}", err);
+                None
+            }
+        });
+
+        Self {
+            queue,
+            recording: record.then(|| WavSink::new(Self::SAMPLE_RATE)),
+        }
+    }
+
+    /// Everything played so far, if recording was requested
+    pub fn recording(&self) -> Option<&WavSink> {
+        self.recording.as_ref()
+    }
+}
+
+impl AudioSink for SdlAudioSink {
+    fn sample_rate(&self) -> u32 {
+        Self::SAMPLE_RATE
+    }
+
+    fn queue_samples(&mut self, samples: &[i16]) {
+        if let Some(recording) = &mut self.recording {
+            recording.queue_samples(samples);
+        }
+
+        if let Some(queue) = &self.queue {
+            // `size` is in bytes
+            if queue.size() / 2 < Self::MAX_QUEUED {
+                let _ = queue.queue_audio(samples);
+            }
+        }
+    }
+}
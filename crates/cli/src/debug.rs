@@ -0,0 +1,645 @@
+use std::io::{self, BufRead, Write};
+
+use emulator::debugger::{Access, Condition, Debugger, Register, Stop, Watchpoint};
+use emulator::disassembler::{self, Syntax};
+use emulator::display::Display;
+use emulator::headless::HeadlessTarget;
+use emulator::{Chip8, CpuState};
+
+use crate::options::Options;
+
+pub const HELP: &str = "commands:
+  step [n]                  run n instructions, 1 by default, stopping at breakpoints
+  continue [frames]         run until paused, for at most 3600 frames by default
+  break [addr]              add a breakpoint, or list them
+  watch [addr [len] [r|w|rw]]  pause on writes to memory, or reads with r, or list them
+  cond [reg [value]]        pause when a register changes or takes on value, or list them
+  opbreak [pattern]         pause on opcodes such as 00E0 or DXYN, or list them
+  clear break addr | watch n | cond n | opbreak n
+  regs                      show the registers
+  mem addr [len]            dump memory, 64 bytes by default
+  disasm [addr] [n]         disassemble n instructions from addr, PC by default
+  stack                     show the return addresses on the stack
+  set reg value             set V0-VF, I or PC
+  press key, release key    hold down or let go of a hex key
+  screen                    show the screen
+  quit";
+
+/// Frames `continue` runs for when not given a count, a minute of emulated time
+const CONTINUE_FRAMES: usize = 60 * 60;
+
+/// Run `chip8 debug`, reading commands from stdin until `quit` or the end of input
+pub fn run(options: &Options) -> io::Result<()> {
+    let rom = std::fs::read(&options.rom)?;
+    let mut emu = Chip8::new(Display::new(HeadlessTarget::new()), options.quirks);
+    emu.set_memory_size(options.memory_size);
+    if let Some(speed) = options.speed {
+        emu.set_cycles_per_frame(speed);
+    }
+    if let Some(seed) = options.seed {
+        emu.set_seed(seed);
+    }
+    crate::load_rom(&mut emu, &rom, options)?;
+
+    let mut monitor = Monitor {
+        emu,
+        debugger: Debugger::new(),
+    };
+    println!("{}", monitor.disasm(monitor.emu.pc(), 1));
+
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("(chip8) ");
+        io::stdout().flush()?;
+        line.clear();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+
+        let args: Vec<&str> = line.split_whitespace().collect();
+        match args.as_slice() {
+            [] => {}
+            ["quit" | "q"] => return Ok(()),
+            [command, args @ ..] => match monitor.command(command, args) {
+                Ok(output) => println!("{}", output.trim_end()),
+                Err(err) => println!("{}", err),
+            },
+        }
+    }
+}
+
+/// Machine under the debugger, along with what pauses it
+struct Monitor {
+    emu: Chip8<HeadlessTarget>,
+    debugger: Debugger,
+}
+
+impl Monitor {
+    /// Run a command, returning what to print
+    fn command(&mut self, command: &str, args: &[&str]) -> Result<String, String> {
+        match (command, args) {
+            ("step" | "s", []) => Ok(self.step(1)),
+            ("step" | "s", [count]) => Ok(self.step(parse_number(count)? as usize)),
+            ("continue" | "c", []) => Ok(self.cont(CONTINUE_FRAMES)),
+            ("continue" | "c", [frames]) => Ok(self.cont(parse_number(frames)? as usize)),
+            ("break" | "b", []) => Ok(list(self.debugger.breakpoints().map(hex))),
+            ("break" | "b", [addr]) => {
+                self.debugger.add_breakpoint(parse_number(addr)?);
+                Ok(String::new())
+            }
+            ("watch", []) => Ok(list(self.debugger.watchpoints().iter().map(watchpoint))),
+            ("watch", [addr, rest @ ..]) => {
+                let addr = parse_number(addr)?;
+                let (len, kind) = match rest {
+                    [] => (1, "w"),
+                    [len] => (parse_number(len)?, "w"),
+                    [len, kind] => (parse_number(len)?, *kind),
+                    _ => return Err("usage: watch addr [len] [r|w|rw]".to_string()),
+                };
+                self.debugger.add_watchpoint(match kind {
+                    "r" => Watchpoint::read(addr, len),
+                    "w" => Watchpoint::write(addr, len),
+                    "rw" => Watchpoint::access(addr, len),
+                    _ => return Err(format!("unknown access {}, expected r, w or rw", kind)),
+                });
+                Ok(String::new())
+            }
+            ("cond", []) => Ok(list(self.debugger.conditions().iter().map(condition))),
+            ("cond", [reg]) => {
+                let reg = parse_register(reg)?;
+                self.debugger.add_condition(Condition::Changes(reg));
+                Ok(String::new())
+            }
+            ("cond", [reg, value]) => {
+                let reg = parse_register(reg)?;
+                let value = parse_number(value)?;
+                self.debugger.add_condition(Condition::Equals(reg, value));
+                Ok(String::new())
+            }
+            ("opbreak", []) => {
+                let patterns = self.debugger.opcode_breaks().iter();
+                Ok(list(patterns.map(|op| {
+                    format!("{:04X} mask {:04X}", op.opcode, op.mask)
+                })))
+            }
+            ("opbreak", [pattern]) => {
+                let pattern = pattern.parse().map_err(|err| format!("{}", err))?;
+                self.debugger.add_opcode_break(pattern);
+                Ok(String::new())
+            }
+            ("clear", [kind, which]) => self.clear(kind, parse_number(which)?),
+            ("regs" | "r", []) => Ok(self.regs()),
+            ("mem" | "m", [addr]) => self.mem(parse_number(addr)?, 64),
+            ("mem" | "m", [addr, len]) => self.mem(parse_number(addr)?, parse_number(len)?),
+            ("disasm" | "d", []) => Ok(self.disasm(self.emu.pc(), 10)),
+            ("disasm" | "d", [addr]) => Ok(self.disasm(parse_number(addr)?, 10)),
+            ("disasm" | "d", [addr, count]) => {
+                Ok(self.disasm(parse_number(addr)?, parse_number(count)? as usize))
+            }
+            ("stack", []) => Ok(self.stack()),
+            ("set", [reg, value]) => self.set(reg, parse_number(value)?),
+            ("press", [key]) => self.key(key, true),
+            ("release", [key]) => self.key(key, false),
+            ("screen", []) => Ok(screen(&self.emu.display)),
+            ("help" | "h", _) => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command {}, try help", command)),
+        }
+    }
+
+    /// Run `count` instructions, the first whatever it's at so that stepping leaves a
+    /// breakpoint, and the rest only until one is hit
+    fn step(&mut self, count: usize) -> String {
+        let mut status = None;
+        for done in 0..count {
+            if let Some(stop) = self.debugger.stop_at(&self.emu).filter(|_| done > 0) {
+                status = Some(describe(stop));
+                break;
+            }
+            match self.debugger.step(&mut self.emu) {
+                Ok(Some(stop)) => status = Some(describe(stop)),
+                Ok(None) => {}
+                Err(err) => status = Some(format!("fault: {}", err)),
+            }
+            if status.is_some() || self.emu.state() != CpuState::Running {
+                break;
+            }
+        }
+        self.paused(status)
+    }
+
+    fn cont(&mut self, frames: usize) -> String {
+        for _ in 0..frames {
+            let status = match self.debugger.run_frame(&mut self.emu) {
+                Ok(Some(stop)) => Some(describe(stop)),
+                Ok(None) => None,
+                Err(err) => Some(format!("fault: {}", err)),
+            };
+            if status.is_some() || self.emu.state() != CpuState::Running {
+                return self.paused(status);
+            }
+        }
+        self.paused(Some(format!("still running after {} frames", frames)))
+    }
+
+    /// Screen, reason for pausing and the next instruction, shown after running
+    fn paused(&self, status: Option<String>) -> String {
+        let mut out = screen(&self.emu.display);
+        let state = match self.emu.state() {
+            CpuState::Running => None,
+            CpuState::WaitingForKey(reg) => Some(format!("waiting for a key into V{:X}", reg)),
+            CpuState::Halted => Some("program exited".to_string()),
+        };
+        for line in status.into_iter().chain(state) {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out.push_str(&self.disasm(self.emu.pc(), 1));
+        out
+    }
+
+    fn clear(&mut self, kind: &str, which: u16) -> Result<String, String> {
+        let idx = which as usize;
+        let found = match kind {
+            "break" => self.debugger.remove_breakpoint(which),
+            "watch" => self.debugger.remove_watchpoint(idx).is_some(),
+            "cond" => self.debugger.remove_condition(idx).is_some(),
+            "opbreak" => self.debugger.remove_opcode_break(idx).is_some(),
+            _ => {
+                return Err(format!(
+                    "can't clear {}, expected break, watch, cond or opbreak",
+                    kind
+                ))
+            }
+        };
+        if !found {
+            return Err(format!("no {} {}", kind, which));
+        }
+        Ok(String::new())
+    }
+
+    fn regs(&self) -> String {
+        let registers = self.emu.registers();
+        let mut out = String::new();
+        for (row, values) in registers.chunks(8).enumerate() {
+            let cells: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(idx, value)| format!("V{:X} {:02X}", row * 8 + idx, value))
+                .collect();
+            out.push_str(&cells.join("  "));
+            out.push('\n');
+        }
+        out.push_str(&format!(
+            "PC {:03X}  I {:03X}  DT {:02X}  ST {:02X}  SP {}",
+            self.emu.pc(),
+            self.emu.i(),
+            self.emu.delay_timer(),
+            self.emu.sound_timer(),
+            self.emu.stack().len()
+        ));
+        out
+    }
+
+    fn mem(&self, addr: u16, len: u16) -> Result<String, String> {
+        let memory = self.emu.memory();
+        let start = addr as usize;
+        let end = (start + len as usize).min(memory.len());
+        if start >= memory.len() {
+            return Err(format!("{:X} is past the end of memory", addr));
+        }
+
+        let mut out = String::new();
+        for (row, bytes) in memory[start..end].chunks(16).enumerate() {
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            out.push_str(&format!("{:03X}: {}\n", start + row * 16, hex.join(" ")));
+        }
+        Ok(out)
+    }
+
+    /// Disassemble `count` instructions from `addr`, marking the PC and breakpoints
+    fn disasm(&self, addr: u16, count: usize) -> String {
+        let memory = self.emu.memory();
+        let start = (addr as usize).min(memory.len());
+        let end = (start + count * 4).min(memory.len());
+        let breakpoints: Vec<u16> = self.debugger.breakpoints().collect();
+
+        let mut out = String::new();
+        for line in disassembler::disassemble(&memory[start..end], addr)
+            .into_iter()
+            .take(count)
+        {
+            let pc = if line.addr() == self.emu.pc() {
+                '>'
+            } else {
+                ' '
+            };
+            let brk = if breakpoints.contains(&line.addr()) {
+                '*'
+            } else {
+                ' '
+            };
+            out.push_str(&format!("{}{} {}\n", pc, brk, line.render(Syntax::Cowgod)));
+        }
+        out
+    }
+
+    fn stack(&self) -> String {
+        let stack = self.emu.stack();
+        if stack.is_empty() {
+            return "stack is empty".to_string();
+        }
+        let frames = stack.iter().rev().enumerate();
+        frames
+            .map(|(depth, addr)| format!("#{} return to {:03X}\n", depth, addr))
+            .collect()
+    }
+
+    fn set(&mut self, reg: &str, value: u16) -> Result<String, String> {
+        if reg.eq_ignore_ascii_case("pc") {
+            self.emu.set_pc(value);
+            return Ok(self.disasm(value, 1));
+        }
+        match parse_register(reg)? {
+            Register::V(reg) => match u8::try_from(value) {
+                Ok(value) => self.emu.set_register(reg, value),
+                Err(_) => return Err(format!("{:X} doesn't fit in a register", value)),
+            },
+            Register::I => self.emu.set_i(value),
+            _ => return Err(format!("can't set {}", reg)),
+        }
+        Ok(String::new())
+    }
+
+    fn key(&mut self, key: &str, down: bool) -> Result<String, String> {
+        let key = match u8::from_str_radix(key, 16) {
+            Ok(key) if key < 16 => key,
+            _ => return Err(format!("invalid key {}, expected 0 to F", key)),
+        };
+        let mask = self.emu.key_mask();
+        let bit = 1 << key;
+        self.emu
+            .set_key_mask(if down { mask | bit } else { mask & !bit });
+        Ok(match self.emu.state() {
+            CpuState::WaitingForKey(reg) => format!("waiting for a key into V{:X}", reg),
+            _ => String::new(),
+        })
+    }
+}
+
+/// Draw the screen with block characters, two rows of pixels per line of text
+fn screen(display: &Display<HeadlessTarget>) -> String {
+    let (cols, rows) = display.size();
+    let pixels = display.framebuffer();
+    let lit = |x: usize, y: usize| y < rows && pixels[y * cols + x] != 0;
+
+    let border = format!("+{}+\n", "-".repeat(cols));
+    let mut out = border.clone();
+    for y in (0..rows).step_by(2) {
+        out.push('|');
+        for x in 0..cols {
+            out.push(match (lit(x, y), lit(x, y + 1)) {
+                (false, false) => ' ',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (true, true) => '█',
+            });
+        }
+        out.push_str("|\n");
+    }
+    out.push_str(&border);
+    out
+}
+
+fn describe(stop: Stop) -> String {
+    match stop {
+        Stop::Breakpoint(addr) => format!("breakpoint at {:03X}", addr),
+        Stop::Opcode { pc, opcode } => format!("opcode {:04X} at {:03X}", opcode, pc),
+        Stop::Watchpoint { pc, addr, access } => {
+            let access = match access {
+                Access::Read => "read from",
+                Access::Write => "write to",
+            };
+            format!("{} {:03X} by {:03X}", access, addr, pc)
+        }
+        Stop::Condition {
+            pc,
+            condition: cond,
+        } => format!("{} after {:03X}", condition(&cond), pc),
+    }
+}
+
+fn hex(addr: u16) -> String {
+    format!("{:03X}", addr)
+}
+
+fn watchpoint(watchpoint: &Watchpoint) -> String {
+    let kind = match (watchpoint.on_read, watchpoint.on_write) {
+        (true, true) => "rw",
+        (true, false) => "r",
+        _ => "w",
+    };
+    format!(
+        "{:03X}, {} bytes, {}",
+        watchpoint.addr, watchpoint.len, kind
+    )
+}
+
+fn condition(condition: &Condition) -> String {
+    let name = match condition.register() {
+        Register::V(reg) => format!("V{:X}", reg),
+        Register::I => "I".to_string(),
+        Register::DelayTimer => "DT".to_string(),
+        Register::SoundTimer => "ST".to_string(),
+    };
+    match condition {
+        Condition::Changes(_) => format!("{} changes", name),
+        Condition::Equals(_, value) => format!("{} == {:X}", name, value),
+    }
+}
+
+/// Numbered list, or a note that it's empty
+fn list(items: impl Iterator<Item = String>) -> String {
+    let lines: Vec<String> = items
+        .enumerate()
+        .map(|(idx, item)| format!("{}: {}", idx, item))
+        .collect();
+    if lines.is_empty() {
+        return "none".to_string();
+    }
+    lines.join("\n")
+}
+
+/// Parse a number given in hex with a `0x`, `#` or `$` prefix, or decimal otherwise
+fn parse_number(text: &str) -> Result<u16, String> {
+    let lower = text.to_ascii_lowercase();
+    let res = match lower
+        .strip_prefix("0x")
+        .or_else(|| lower.strip_prefix('#'))
+        .or_else(|| lower.strip_prefix('$'))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => lower.parse(),
+    };
+    res.map_err(|_| format!("invalid number {}", text))
+}
+
+fn parse_register(name: &str) -> Result<Register, String> {
+    let lower = name.to_ascii_lowercase();
+    match lower.as_str() {
+        "i" => Ok(Register::I),
+        "dt" => Ok(Register::DelayTimer),
+        "st" => Ok(Register::SoundTimer),
+        _ => lower
+            .strip_prefix('v')
+            .filter(|digit| digit.len() == 1)
+            .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+            .map(Register::V)
+            .ok_or_else(|| format!("unknown register {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use emulator::quirks::Quirks;
+
+    /// Stores V0 and V1 at 0x300, clears the screen, then spins at 0x20A
+    const PROGRAM: &[u8] = &[
+        0x60, 0x05, // 200: LD V0, #05
+        0x61, 0x07, // 202: LD V1, #07
+        0xA3, 0x00, // 204: LD I, #300
+        0xF1, 0x55, // 206: LD [I], V1
+        0x00, 0xE0, // 208: CLS
+        0x12, 0x0A, // 20A: JP #20A
+    ];
+
+    fn monitor(program: &[u8]) -> Monitor {
+        let mut emu = Chip8::headless(Quirks::COSMAC_VIP);
+        emu.load_bytes(program).unwrap();
+        Monitor {
+            emu,
+            debugger: Debugger::new(),
+        }
+    }
+
+    /// Run a command line, as typed at the prompt
+    fn run(monitor: &mut Monitor, line: &str) -> Result<String, String> {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        monitor.command(args[0], &args[1..])
+    }
+
+    #[test]
+    fn numbers_and_registers() {
+        for text in ["0x23A", "0X23a", "#23A", "$23a", "570"] {
+            assert_eq!(parse_number(text), Ok(0x23A), "{}", text);
+        }
+        assert_eq!(parse_number("0x"), Err("invalid number 0x".to_string()));
+        assert_eq!(
+            parse_number("70000"),
+            Err("invalid number 70000".to_string())
+        );
+
+        assert_eq!(parse_register("v3"), Ok(Register::V(3)));
+        assert_eq!(parse_register("VF"), Ok(Register::V(0xF)));
+        assert_eq!(parse_register("i"), Ok(Register::I));
+        assert_eq!(parse_register("DT"), Ok(Register::DelayTimer));
+        assert_eq!(
+            parse_register("v10"),
+            Err("unknown register v10".to_string())
+        );
+    }
+
+    #[test]
+    fn breakpoints() {
+        let mut monitor = monitor(PROGRAM);
+        assert_eq!(run(&mut monitor, "break"), Ok("none".to_string()));
+        assert_eq!(run(&mut monitor, "break 0x23A"), Ok(String::new()));
+        assert_eq!(run(&mut monitor, "b 0x208"), Ok(String::new()));
+        assert_eq!(run(&mut monitor, "break"), Ok("0: 208\n1: 23A".to_string()));
+        assert_eq!(monitor.disasm(0x208, 1), " * 208: 00E0  CLS\n");
+
+        let out = run(&mut monitor, "continue").unwrap();
+        assert!(
+            out.contains("breakpoint at 208\n>* 208: 00E0  CLS"),
+            "{}",
+            out
+        );
+        assert_eq!(run(&mut monitor, "clear break 0x208"), Ok(String::new()));
+        assert_eq!(
+            run(&mut monitor, "clear break 0x208"),
+            Err("no break 520".to_string())
+        );
+    }
+
+    #[test]
+    fn step_stops_at_breakpoints() {
+        let mut monitor = monitor(PROGRAM);
+        run(&mut monitor, "break 0x204").unwrap();
+        let out = run(&mut monitor, "step 5").unwrap();
+        assert!(out.contains("breakpoint at 204"), "{}", out);
+        assert_eq!(monitor.emu.pc(), 0x204);
+
+        // Stepping from a breakpoint runs the instruction it's on
+        run(&mut monitor, "step 2").unwrap();
+        assert_eq!(monitor.emu.pc(), 0x208);
+    }
+
+    #[test]
+    fn memory() {
+        let mut monitor = monitor(PROGRAM);
+        run(&mut monitor, "step 4").unwrap();
+        let out = run(&mut monitor, "mem 0x300 32").unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "300: 05 07 00 00 00 00 00 00 00 00 00 00 00 00 00 00",
+                "310: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00",
+            ]
+        );
+        assert_eq!(run(&mut monitor, "m 0xFFE").unwrap(), "FFE: 00 00\n");
+        assert_eq!(
+            run(&mut monitor, "mem 0x1000"),
+            Err("1000 is past the end of memory".to_string())
+        );
+    }
+
+    #[test]
+    fn set_registers() {
+        let mut monitor = monitor(PROGRAM);
+        assert_eq!(run(&mut monitor, "set v3 0x10"), Ok(String::new()));
+        assert_eq!(monitor.emu.registers()[3], 0x10);
+        assert_eq!(run(&mut monitor, "set I 0x300"), Ok(String::new()));
+        assert_eq!(monitor.emu.i(), 0x300);
+        assert_eq!(
+            run(&mut monitor, "set pc 0x208"),
+            Ok(">  208: 00E0  CLS\n".to_string())
+        );
+        assert_eq!(
+            run(&mut monitor, "set v3 0x100"),
+            Err("100 doesn't fit in a register".to_string())
+        );
+        assert_eq!(
+            run(&mut monitor, "set dt 1"),
+            Err("can't set dt".to_string())
+        );
+        assert!(run(&mut monitor, "regs").unwrap().contains("V3 10"));
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut monitor = monitor(PROGRAM);
+        assert_eq!(run(&mut monitor, "watch 0x301 2"), Ok(String::new()));
+        assert_eq!(
+            run(&mut monitor, "watch"),
+            Ok("0: 301, 2 bytes, w".to_string())
+        );
+        let out = run(&mut monitor, "continue").unwrap();
+        assert!(out.contains("write to 301 by 206"), "{}", out);
+        assert_eq!(
+            run(&mut monitor, "watch 0x300 1 x"),
+            Err("unknown access x, expected r, w or rw".to_string())
+        );
+        assert_eq!(
+            run(&mut monitor, "watch 0x300 1 r w"),
+            Err("usage: watch addr [len] [r|w|rw]".to_string())
+        );
+    }
+
+    #[test]
+    fn conditions() {
+        let mut monitor = monitor(PROGRAM);
+        run(&mut monitor, "cond v1 7").unwrap();
+        assert_eq!(run(&mut monitor, "cond"), Ok("0: V1 == 7".to_string()));
+        let out = run(&mut monitor, "c").unwrap();
+        assert!(out.contains("V1 == 7 after 202"), "{}", out);
+    }
+
+    #[test]
+    fn reports_mistakes() {
+        let mut monitor = monitor(PROGRAM);
+        assert_eq!(
+            run(&mut monitor, "frob"),
+            Err("unknown command frob, try help".to_string())
+        );
+        assert_eq!(
+            run(&mut monitor, "break 0xZZZ"),
+            Err("invalid number 0xZZZ".to_string())
+        );
+        assert_eq!(
+            run(&mut monitor, "clear frob 1"),
+            Err("can't clear frob, expected break, watch, cond or opbreak".to_string())
+        );
+        assert_eq!(
+            run(&mut monitor, "press g"),
+            Err("invalid key g, expected 0 to F".to_string())
+        );
+        assert_eq!(run(&mut monitor, "help"), Ok(HELP.to_string()));
+    }
+
+    #[test]
+    fn screen_in_block_characters() {
+        let mut monitor = monitor(&[
+            0x60, 0x00, // LD V0, 0
+            0xF0, 0x29, // LD F, V0
+            0xD0, 0x05, // DRW V0, V0, 5
+        ]);
+        run(&mut monitor, "step 3").unwrap();
+        let out = screen(&monitor.emu.display);
+        let lines: Vec<&str> = out.lines().collect();
+
+        // Two rows of pixels to a line, between borders
+        assert_eq!(lines.len(), 32 / 2 + 2);
+        let border = format!("+{}+", "-".repeat(64));
+        assert_eq!(lines[0], border);
+        assert_eq!(lines[17], border);
+        assert!(lines[1..17].iter().all(|line| line.chars().count() == 66));
+        // The digit 0, rows 0 and 1 on the first line
+        assert!(lines[1].starts_with("|█▀▀█ "), "{}", lines[1]);
+        assert!(lines[2].starts_with("|█  █ "), "{}", lines[2]);
+        assert!(lines[3].starts_with("|▀▀▀▀ "), "{}", lines[3]);
+        assert_eq!(lines[4], format!("|{}|", " ".repeat(64)));
+    }
+}
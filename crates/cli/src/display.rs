@@ -5,33 +5,27 @@ use sdl2::video::Window;
 
 use emulator::display::RenderTarget;
 
-/// Display buffer with optional rendering target
-///
-/// When no canvas is provided, Display runs in "headless" mode.
+/// Renders the emulator's display to an SDL window
 pub struct SdlRenderTarget {
-    canvas: Option<Canvas<Window>>,
+    canvas: Canvas<Window>,
 }
 
 impl SdlRenderTarget {
-    pub fn new(canvas: Option<Canvas<Window>>) -> Self {
+    pub fn new(canvas: Canvas<Window>) -> Self {
         Self { canvas }
     }
 }
 
 impl RenderTarget for SdlRenderTarget {
     fn size(&self) -> (usize, usize) {
-        if let Some(canvas) = &self.canvas {
-            if let Ok((width, height)) = canvas.output_size() {
-                return (width as usize, height as usize);
-            }
+        match self.canvas.output_size() {
+            Ok((width, height)) => (width as usize, height as usize),
+            Err(_) => (0, 0),
         }
-        (0_usize, 0_usize)
     }
 
     fn clear(&mut self) {
-        if let Some(canvas) = &mut self.canvas {
-            canvas.clear()
-        }
+        self.canvas.clear()
     }
 
     fn fill_rect(
@@ -42,23 +36,47 @@ impl RenderTarget for SdlRenderTarget {
         h: usize,
         color: emulator::display::Color,
     ) {
-        let canvas = match &mut self.canvas {
-            Some(canvas) => canvas,
-            None => return,
-        };
-
-        canvas.set_draw_color(match color {
-            emulator::display::Color::Black => Color::BLACK,
-            emulator::display::Color::White => Color::WHITE,
-        });
-        canvas
+        self.canvas.set_draw_color(sdl_color(color));
+        self.canvas
             .fill_rect(Rect::new(x as i32, y as i32, w as u32, h as u32))
             .unwrap();
     }
 
-    fn present(&mut self) {
-        if let Some(canvas) = &mut self.canvas {
-            canvas.present()
+    fn blit_frame(&mut self, frame: &[u8], cols: usize, rows: usize) {
+        let (width, height) = self.size();
+        let pixel_width = (width / cols) as u32;
+        let pixel_height = (height / rows) as u32;
+
+        // One draw call per color rather than one per pixel
+        let mut rects: [Vec<Rect>; 4] = Default::default();
+        for (idx, &pixel) in frame.iter().enumerate() {
+            let x = (idx % cols) as u32 * pixel_width;
+            let y = (idx / cols) as u32 * pixel_height;
+            rects[pixel as usize & 0b11].push(Rect::new(
+                x as i32,
+                y as i32,
+                pixel_width,
+                pixel_height,
+            ));
+        }
+
+        for (planes, rects) in rects.iter().enumerate() {
+            let color = emulator::display::Color::from_planes(planes as u8);
+            self.canvas.set_draw_color(sdl_color(color));
+            self.canvas.fill_rects(rects).unwrap();
         }
     }
+
+    fn present(&mut self) {
+        self.canvas.present()
+    }
+}
+
+fn sdl_color(color: emulator::display::Color) -> Color {
+    match color {
+        emulator::display::Color::Black => Color::BLACK,
+        emulator::display::Color::White => Color::WHITE,
+        emulator::display::Color::LightGray => Color::RGB(0xAA, 0xAA, 0xAA),
+        emulator::display::Color::DarkGray => Color::RGB(0x55, 0x55, 0x55),
+    }
 }
@@ -1,15 +1,29 @@
 use std::collections::HashMap;
-use std::str::FromStr;
-use std::time::Duration;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Mod};
 
-use emulator::Chip8;
+use emulator::audio::{AudioSink, WavSink, WAV_SAMPLE_RATE};
+use emulator::disassembler::{self, Syntax};
+use emulator::display::RenderTarget;
+use emulator::headless::HeadlessTarget;
+use emulator::replay::{PlaybackError, Replay};
+use emulator::rewind::Rewind;
+use emulator::{Chip8, CpuState, RomError};
 
+mod audio;
+mod debug;
 mod display;
+mod options;
 
-const CLOCK_RATE: u64 = 500_000_000; // in Hertz
+use options::Options;
+
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Frames of history kept for rewinding, 30 seconds worth
+const REWIND_FRAMES: usize = 30 * 60;
 
 pub fn main() -> std::io::Result<()> {
     let keymap: HashMap<Keycode, emulator::Keycode> = HashMap::from([
@@ -32,21 +46,53 @@ pub fn main() -> std::io::Result<()> {
     ]);
 
     let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        println!("usage: chip8 romfile");
-        return Ok(());
+    if args.len() >= 2 && args[1] == "disasm" {
+        return disasm(&args[2..]);
     }
-
-    let rom = match std::path::PathBuf::from_str(args[1].as_str()) {
-        Ok(path) => path,
-        Err(_) => {
-            println!("romfile does not exist");
+    if args.len() >= 2 && args[1] == "debug" {
+        return match Options::parse_debug(&args[2..]) {
+            Ok(options) => debug::run(&options),
+            Err(err) => {
+                println!("{}", err);
+                println!("{}", options::USAGE);
+                Ok(())
+            }
+        };
+    }
+    let options = match Options::parse(&args[1..]) {
+        Ok(options) => options,
+        Err(err) => {
+            println!("{}", err);
+            println!("{}", options::USAGE);
             return Ok(());
         }
     };
 
+    let rom = std::fs::read(&options.rom)?;
+    let playback = match &options.play {
+        Some(path) => {
+            let replay = Replay::from_bytes(&std::fs::read(path)?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            if !replay.matches_rom(&rom) {
+                eprintln!("warning: replay was recorded against a different ROM");
+            }
+            Some(replay)
+        }
+        None => None,
+    };
+    let quirks = playback
+        .as_ref()
+        .map_or(options.quirks, |replay| replay.quirks);
+
+    if options.headless {
+        if let Some(replay) = &playback {
+            return verify_headless(replay, &rom, &options);
+        }
+    }
+
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
+    let audio_subsystem = sdl_context.audio().ok();
 
     let window = video_subsystem
         .window("rust-sdl2 demo", 640, 320)
@@ -60,11 +106,44 @@ pub fn main() -> std::io::Result<()> {
     canvas.present();
     let mut event_pump = sdl_context.event_pump().unwrap();
 
-    let sdl_render_target = display::SdlRenderTarget::new(Some(canvas));
+    let sdl_render_target = display::SdlRenderTarget::new(canvas);
     let sdl_display = emulator::display::Display::new(sdl_render_target);
-    let mut emu = Chip8::new(sdl_display);
-    emu.load(rom)?;
+    let sdl_audio = audio::SdlAudioSink::new(audio_subsystem, options.wav.is_some());
+    let mut emu = Chip8::with_audio(sdl_display, sdl_audio, quirks);
+    emu.set_memory_size(options.memory_size);
+    if let Some(speed) = options.speed {
+        emu.set_cycles_per_frame(speed);
+    }
+    if let Some(seed) = options.seed {
+        emu.set_seed(seed);
+    }
+    match &playback {
+        Some(replay) => replay
+            .start(&mut emu, &rom)
+            .map_err(|err| invalid_rom(&options, err))?,
+        None => load_rom(&mut emu, &rom, &options)?,
+    }
+
+    // Replays have to start from a clean machine, so they can't restore anything saved
+    let replaying = options.record.is_some() || playback.is_some();
+
+    // SUPER-CHIP games keep high scores and the like in the RPL flags between runs
+    let flags_path = options.rom.with_extension("flags");
+    if !replaying {
+        if let Ok(flags) = std::fs::read(&flags_path) {
+            if let Ok(flags) = flags.try_into() {
+                emu.set_rpl_flags(flags);
+            }
+        }
+    }
+
+    let mut recording = options.record.as_ref().map(|_| Replay::new(&emu, &rom));
 
+    let mut rewind = Rewind::new(REWIND_FRAMES, 1);
+    let mut rewinding = false;
+    let mut keys = 0;
+    let mut frame = 0;
+    let mut next_frame = Instant::now();
     'running: loop {
         for event in event_pump.poll_iter() {
             match event {
@@ -74,20 +153,212 @@ pub fn main() -> std::io::Result<()> {
                     ..
                 } => break 'running,
                 Event::KeyDown {
+                    keycode: Some(Keycode::Backspace),
+                    ..
+                } => rewinding = !replaying,
+                Event::KeyUp {
+                    keycode: Some(Keycode::Backspace),
+                    ..
+                } => rewinding = false,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    ..
+                } => {
+                    if let Some(slot) = state_slot(keycode) {
+                        let save = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                        if save || !replaying {
+                            state_hotkey(&mut emu, &options.rom, slot, save);
+                        }
+                    } else if let Some(emu_key) = keymap.get(&keycode) {
+                        keys |= emu_key.mask();
+                    }
+                }
+                Event::KeyUp {
                     keycode: Some(keycode),
                     ..
                 } => {
                     if let Some(emu_key) = keymap.get(&keycode) {
-                        emu.push_key(emu_key)
+                        keys &= !emu_key.mask();
                     }
                 }
                 _ => {}
             }
         }
-        // The rest of the game loop goes here...
-        emu.tick();
+        if rewinding {
+            // Step back a frame at a time, holding on the oldest one once history runs out
+            if rewind.rewind(&mut emu) {
+                emu.render();
+            }
+        } else {
+            let result = if let Some(replay) = &playback {
+                if frame == replay.frames.len() {
+                    report_playback(emu.display.hash() == replay.final_hash);
+                    break 'running;
+                }
+                replay.play_frame(&mut emu, frame)
+            } else if let Some(replay) = &mut recording {
+                replay.record_frame(&mut emu, keys)
+            } else {
+                emu.set_key_mask(keys);
+                emu.run_frame()
+            };
+            frame += 1;
+
+            match result {
+                Ok(CpuState::Halted) => break 'running,
+                Ok(_) => {}
+                Err(err) => {
+                    eprintln!("emulator crashed: {}", err);
+                    std::process::exit(1);
+                }
+            }
+            rewind.record(&emu);
+        }
+
+        // Schedule against the previous deadline so frames don't drift, unless we've
+        // fallen so far behind that catching up would run the game in fast forward
+        next_frame += FRAME_TIME;
+        let now = Instant::now();
+        if next_frame > now {
+            std::thread::sleep(next_frame - now);
+        } else if now - next_frame > FRAME_TIME * 4 {
+            next_frame = now;
+        }
+    }
+
+    if !replaying && emu.rpl_flags() != [0; 16] {
+        std::fs::write(flags_path, emu.rpl_flags())?;
+    }
+
+    if let (Some(path), Some(recording)) = (&options.wav, emu.audio.recording()) {
+        recording.save(path)?;
+    }
+
+    if let (Some(path), Some(replay)) = (&options.record, &recording) {
+        std::fs::write(path, replay.to_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Play `replay` back without a window, exiting with an error if it doesn't reproduce
+///
+/// The audio is saved with `--wav` whether or not it does, at the same rate as with a
+/// window so the two files can be compared.
+fn verify_headless(replay: &Replay, rom: &[u8], options: &Options) -> io::Result<()> {
+    let display = emulator::display::Display::new(HeadlessTarget::new());
+    let audio = WavSink::new(WAV_SAMPLE_RATE);
+    let mut emu = Chip8::with_audio(display, audio, replay.quirks);
+
+    match replay.verify(&mut emu, rom) {
+        Ok(matches) => {
+            if let Some(path) = &options.wav {
+                emu.audio.save(path)?;
+            }
+            report_playback(matches);
+        }
+        Err(PlaybackError::Rom(err)) => return Err(invalid_rom(options, err)),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+fn load_rom<T, A>(emu: &mut Chip8<T, A>, rom: &[u8], options: &Options) -> io::Result<()>
+where
+    T: RenderTarget,
+    A: AudioSink,
+{
+    emu.load_bytes_at(rom, options.load_address)
+        .map_err(|err| invalid_rom(options, err))
+}
+
+fn invalid_rom(options: &Options, err: RomError) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", options.rom.display(), err),
+    )
+}
+
+fn report_playback(matches: bool) {
+    if matches {
+        println!("replay verified, final screen matches the recording");
+    } else {
+        eprintln!("replay diverged, final screen differs from the recording");
+        std::process::exit(1);
+    }
+}
+
+/// Save state slot for F1 to F9, which load the slot or save it while shift is held
+fn state_slot(keycode: Keycode) -> Option<u8> {
+    let slots = [
+        Keycode::F1,
+        Keycode::F2,
+        Keycode::F3,
+        Keycode::F4,
+        Keycode::F5,
+        Keycode::F6,
+        Keycode::F7,
+        Keycode::F8,
+        Keycode::F9,
+    ];
+    slots
+        .iter()
+        .position(|&key| key == keycode)
+        .map(|idx| idx as u8 + 1)
+}
+
+/// Save the emulator to a state slot next to the ROM, or load it back
+fn state_hotkey<T, A>(emu: &mut Chip8<T, A>, rom: &Path, slot: u8, save: bool)
+where
+    T: RenderTarget,
+    A: AudioSink,
+{
+    let path = rom.with_extension(format!("state{}", slot));
+    if save {
+        match std::fs::write(&path, emu.save_state()) {
+            Ok(()) => println!("saved state to slot {}", slot),
+            Err(err) => eprintln!("unable to save slot {}: {}", slot, err),
+        }
+        return;
+    }
+
+    let loaded = match std::fs::read(&path) {
+        Ok(state) => emu.load_state(&state).map_err(|err| err.to_string()),
+        Err(err) => Err(err.to_string()),
+    };
+    match loaded {
+        Ok(()) => println!("loaded state from slot {}", slot),
+        Err(err) => eprintln!("unable to load slot {}: {}", slot, err),
+    }
+}
+
+fn disasm(args: &[String]) -> std::io::Result<()> {
+    let mut syntax = Syntax::Cowgod;
+    let mut origin = emulator::PROGRAM_START;
+    let mut roms = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--octo" => syntax = Syntax::Octo,
+            "--eti660" => origin = emulator::ETI_660_PROGRAM_START,
+            _ => roms.push(arg),
+        }
+    }
+
+    let rom = match roms[..] {
+        [rom] if !rom.starts_with("--") => rom,
+        _ => {
+            println!("usage: chip8 disasm [--octo] [--eti660] romfile");
+            return Ok(());
+        }
+    };
 
-        ::std::thread::sleep(Duration::from_nanos(1_000_000_000 / CLOCK_RATE));
+    let contents = std::fs::read(rom)?;
+    for line in disassembler::disassemble(&contents, origin) {
+        println!("{}", line.render(syntax));
     }
 
     Ok(())
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use emulator::quirks::Quirks;
+
+pub const USAGE: &str = "usage: chip8 [options] romfile
+       chip8 disasm [--octo] [--eti660] romfile
+       chip8 debug [options] romfile
+
+options:
+  --quirks vip|chip48|schip|modern|xochip
+  --speed n        instructions per 60 Hz frame
+  --seed n         seed for the random number generator
+  --eti660         load the ROM at 0x600, for programs written for the ETI-660
+  --wav out.wav    record the audio output
+  --record file    record the keys pressed to a replay file
+  --play file      play back a replay file
+  --headless       with --play, check the replay without opening a window
+
+chip8 debug takes the same options, except --wav, --record, --play and --headless
+
+F1-F9 load a save state slot, shift+F1-F9 save to it
+hold backspace to rewind";
+
+/// Options for running a ROM in the SDL frontend
+pub struct Options {
+    pub rom: PathBuf,
+    pub quirks: Quirks,
+    /// Bytes of memory, 64 KiB with the XO-CHIP preset
+    pub memory_size: usize,
+    /// Instructions per frame, if overriding the emulator's default
+    pub speed: Option<usize>,
+    /// Seed for `CXNN`, random if not given
+    pub seed: Option<u64>,
+    /// Address to load the ROM at
+    pub load_address: u16,
+    /// Where to save a recording of the audio output
+    pub wav: Option<PathBuf>,
+    /// Replay file to record the session to
+    pub record: Option<PathBuf>,
+    /// Replay file to play back
+    pub play: Option<PathBuf>,
+    /// Verify the replay without a window
+    pub headless: bool,
+}
+
+impl Options {
+    /// Parse command line arguments, excluding the program name
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut rom = None;
+        let mut quirks = Quirks::default();
+        let mut memory_size = emulator::MEMORY_SIZE;
+        let mut speed = None;
+        let mut seed = None;
+        let mut load_address = emulator::PROGRAM_START;
+        let mut wav = None;
+        let mut record = None;
+        let mut play = None;
+        let mut headless = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--quirks" => {
+                    let name = args.next().ok_or("--quirks requires a preset name")?;
+                    quirks = name.parse().map_err(|err| format!("{}", err))?;
+                    memory_size = Quirks::preset_memory_size(name);
+                }
+                "--speed" => speed = Some(parse_number(arg, args.next())?),
+                "--seed" => seed = Some(parse_number(arg, args.next())?),
+                "--eti660" => load_address = emulator::ETI_660_PROGRAM_START,
+                "--wav" => {
+                    let path = args.next().ok_or("--wav requires a file name")?;
+                    wav = Some(PathBuf::from(path));
+                }
+                "--record" => {
+                    let path = args.next().ok_or("--record requires a file name")?;
+                    record = Some(PathBuf::from(path));
+                }
+                "--play" => {
+                    let path = args.next().ok_or("--play requires a file name")?;
+                    play = Some(PathBuf::from(path));
+                }
+                "--headless" => headless = true,
+                flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+                path if rom.is_none() => rom = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument {}", extra)),
+            }
+        }
+
+        if record.is_some() && play.is_some() {
+            return Err("--record and --play can't be used together".to_string());
+        }
+        if headless && play.is_none() {
+            return Err("--headless requires --play".to_string());
+        }
+
+        Ok(Self {
+            rom: rom.ok_or("missing romfile")?,
+            quirks,
+            memory_size,
+            speed,
+            seed,
+            load_address,
+            wav,
+            record,
+            play,
+            headless,
+        })
+    }
+
+    /// Parse arguments for `chip8 debug`, which has no audio output or replays
+    pub fn parse_debug(args: &[String]) -> Result<Self, String> {
+        let options = Self::parse(args)?;
+        let unsupported = [
+            ("--wav", options.wav.is_some()),
+            ("--record", options.record.is_some()),
+            ("--play", options.play.is_some()),
+            ("--headless", options.headless),
+        ];
+        match unsupported.iter().find(|(_, used)| *used) {
+            Some((flag, _)) => Err(format!("{} can't be used with chip8 debug", flag)),
+            None => Ok(options),
+        }
+    }
+}
+
+/// Parse the number following `flag`
+fn parse_number<N: FromStr>(flag: &str, value: Option<&String>) -> Result<N, String> {
+    let value = value.ok_or_else(|| format!("{} requires a number", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid number {} for {}", value, flag))
+}
@@ -0,0 +1,781 @@
+//! Debug Adapter Protocol server for debugging ROMs at the level of their assembly source.
+//!
+//! `launch` takes a `program`, either assembly source, which is assembled on the spot to
+//! map addresses back to lines, or a `.ch8` ROM, which can only be debugged by address.
+//! There is a single thread, and two scopes of variables: the registers and the stack.
+//! Data breakpoints watch memory, at an address or the one a variable such as `I` holds.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use assembler::Assembly;
+use emulator::debugger::{Debugger, Stop, Watchpoint};
+use emulator::headless::HeadlessTarget;
+use emulator::quirks::Quirks;
+use emulator::{Chip8, CpuState, EmulatorError};
+
+pub mod json;
+pub mod protocol;
+
+use json::Value;
+
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+const THREAD_ID: i64 = 1;
+
+/// `variablesReference` of each scope
+const REGISTERS: i64 = 1;
+const STACK: i64 = 2;
+
+/// Serve a debugging session, reading requests from `input` until the client disconnects
+pub fn serve<R, W>(input: R, output: W) -> io::Result<()>
+where
+    R: BufRead + Send + 'static,
+    W: Write,
+{
+    // Requests are read on their own thread so that they can arrive while the program runs
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut input = input;
+        while let Some(body) = protocol::read_body(&mut input).transpose() {
+            let failed = body.is_err();
+            if tx.send(body).is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    let mut adapter = Adapter::new(output);
+    let mut next_frame = Instant::now();
+    loop {
+        let message = if adapter.is_running() {
+            match rx.try_recv() {
+                Ok(message) => Some(message),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        } else {
+            match rx.recv() {
+                Ok(message) => Some(message),
+                Err(_) => return Ok(()),
+            }
+        };
+
+        if let Some(body) = message {
+            // A message that doesn't parse is skipped, only a broken stream ends the session
+            match protocol::parse_body(&body?) {
+                Ok(message) if !adapter.handle(&message)? => return Ok(()),
+                Ok(_) => {}
+                Err(err) => adapter.malformed(&err)?,
+            }
+            continue;
+        }
+
+        let now = Instant::now();
+        if next_frame < now {
+            next_frame = now;
+        }
+        adapter.run_frame()?;
+        next_frame += FRAME_TIME;
+        thread::sleep(next_frame.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// What the program is doing between requests
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+enum Mode {
+    Stopped,
+    Running,
+    /// Stepping until the stack is no deeper than `depth`
+    StepOut {
+        depth: usize,
+    },
+}
+
+/// Program being debugged
+struct Target {
+    emu: Chip8<HeadlessTarget>,
+    debugger: Debugger,
+    /// Assembly the ROM was built from, if launched from source
+    assembly: Option<Assembly>,
+    /// Breakpoint addresses set for each source file
+    breakpoints: HashMap<String, Vec<u16>>,
+    stop_on_entry: bool,
+    exited: bool,
+}
+
+struct Adapter<W: Write> {
+    output: W,
+    seq: i64,
+    target: Option<Target>,
+    mode: Mode,
+    /// Events to send once the response to the current request is out
+    events: Vec<Value>,
+}
+
+impl<W: Write> Adapter<W> {
+    fn new(output: W) -> Self {
+        Self {
+            output,
+            seq: 0,
+            target: None,
+            mode: Mode::Stopped,
+            events: Vec::new(),
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.mode != Mode::Stopped
+    }
+
+    /// Respond to a request, returning `false` once the client has disconnected
+    fn handle(&mut self, message: &Value) -> io::Result<bool> {
+        if message.get("type").as_str() != Some("request") {
+            return Ok(true);
+        }
+        let command = message.get("command").as_str().unwrap_or_default();
+        let args = message.get("arguments");
+
+        let res = match command {
+            "initialize" => Ok(capabilities()),
+            "launch" => self.launch(args),
+            "setBreakpoints" => self.set_breakpoints(args),
+            "dataBreakpointInfo" => self.data_breakpoint_info(args),
+            "setDataBreakpoints" => self.set_data_breakpoints(args),
+            "configurationDone" => self.configuration_done(),
+            "threads" => Ok(Value::object([(
+                "threads",
+                Value::from(vec![Value::object([
+                    ("id", Value::from(THREAD_ID)),
+                    ("name", Value::from("CHIP-8")),
+                ])]),
+            )])),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(scopes()),
+            "variables" => self.variables(args),
+            "readMemory" => self.read_memory(args),
+            "continue" => self.resume(),
+            "next" => self.next(),
+            "stepIn" => self.step_in(),
+            "stepOut" => self.step_out(),
+            "pause" => self.pause(),
+            "terminate" => self.terminate(),
+            "disconnect" => Ok(Value::Null),
+            _ => Err(format!("unsupported request {}", command)),
+        };
+
+        let mut response = vec![
+            ("seq", Value::from(self.next_seq())),
+            ("type", Value::from("response")),
+            ("request_seq", message.get("seq").clone()),
+            ("command", Value::from(command)),
+            ("success", Value::from(res.is_ok())),
+        ];
+        match res {
+            Ok(Value::Null) => {}
+            Ok(body) => response.push(("body", body)),
+            Err(err) => response.push(("message", Value::from(err))),
+        }
+        protocol::write_message(&mut self.output, &Value::object(response))?;
+        self.flush_events()?;
+
+        Ok(command != "disconnect")
+    }
+
+    fn launch(&mut self, args: &Value) -> Result<Value, String> {
+        if self.target.is_some() {
+            return Err("a program has already been launched".to_string());
+        }
+        let program = args
+            .get("program")
+            .as_str()
+            .ok_or("launch requires a program")?;
+        let (quirks, memory_size) = match args.get("quirks").as_str() {
+            Some(name) => {
+                let quirks: Quirks = name.parse().map_err(|err| format!("{}", err))?;
+                (quirks, Quirks::preset_memory_size(name))
+            }
+            None => (Quirks::default(), emulator::MEMORY_SIZE),
+        };
+
+        let path = fs::canonicalize(program).map_err(|err| format!("{}: {}", program, err))?;
+        let (rom, assembly) = if path.extension().is_some_and(|ext| ext == "ch8") {
+            let rom = fs::read(&path).map_err(|err| format!("{}: {}", program, err))?;
+            (rom, None)
+        } else {
+            let assembly = assembler::assemble_file(&path).map_err(|err| err.to_string())?;
+            (assembly.rom.clone(), Some(assembly))
+        };
+
+        let mut emu = Chip8::headless(quirks);
+        emu.set_memory_size(memory_size);
+        emu.load_bytes(&rom)
+            .map_err(|err| format!("{}: {}", program, err))?;
+        self.target = Some(Target {
+            emu,
+            debugger: Debugger::new(),
+            assembly,
+            breakpoints: HashMap::new(),
+            stop_on_entry: args.get("stopOnEntry").as_bool().unwrap_or(false),
+            exited: false,
+        });
+
+        // Breakpoints can only be placed once there's a program to map them to
+        self.event("initialized", Value::Null);
+        Ok(Value::Null)
+    }
+
+    fn configuration_done(&mut self) -> Result<Value, String> {
+        let target = self.target()?;
+        if target.stop_on_entry {
+            self.stopped("entry", None);
+        } else {
+            self.mode = Mode::Running;
+        }
+        Ok(Value::Null)
+    }
+
+    /// Replace the breakpoints in one source file, moving each to the next line with code
+    fn set_breakpoints(&mut self, args: &Value) -> Result<Value, String> {
+        let path = args
+            .get("source")
+            .get("path")
+            .as_str()
+            .ok_or("setBreakpoints requires a source path")?;
+        let source = fs::canonicalize(path)
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| path.to_string());
+        let lines: Vec<i64> = args
+            .get("breakpoints")
+            .as_array()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|bp| bp.get("line").as_i64())
+            .collect();
+
+        let target = self.target.as_mut().ok_or("no program has been launched")?;
+        for addr in target.breakpoints.remove(&source).unwrap_or_default() {
+            target.debugger.remove_breakpoint(addr);
+        }
+
+        let mut addrs = Vec::new();
+        let mut breakpoints = Vec::new();
+        for line in lines {
+            let mapping = target
+                .assembly
+                .as_ref()
+                .and_then(|assembly| assembly.line_from(&source, usize::try_from(line).ok()?));
+            let breakpoint = match mapping {
+                Some(mapping) => {
+                    target.debugger.add_breakpoint(mapping.addr);
+                    addrs.push(mapping.addr);
+                    Value::object([
+                        ("verified", Value::from(true)),
+                        ("line", Value::from(mapping.line)),
+                    ])
+                }
+                None => Value::object([
+                    ("verified", Value::from(false)),
+                    ("line", Value::from(line)),
+                    ("message", Value::from("no code at or after this line")),
+                ]),
+            };
+            breakpoints.push(breakpoint);
+        }
+        target.breakpoints.insert(source, addrs);
+
+        Ok(Value::object([("breakpoints", Value::from(breakpoints))]))
+    }
+
+    /// Describe the memory a variable points at, or an address, so it can be watched
+    fn data_breakpoint_info(&mut self, args: &Value) -> Result<Value, String> {
+        let name = args
+            .get("name")
+            .as_str()
+            .ok_or("dataBreakpointInfo requires a name")?;
+        let addr = match args.get("variablesReference").as_i64() {
+            _ if args.get("asAddress").as_bool() == Some(true) => memory_address(name),
+            Some(reference) => self.memory_reference(reference, name)?,
+            // Not from a scope, so a register holding an address or an address itself
+            None => self
+                .memory_reference(REGISTERS, name)?
+                .or_else(|| memory_address(name)),
+        };
+        let len = args.get("bytes").as_i64().unwrap_or(1);
+
+        let Some((addr, len)) = addr.zip(u16::try_from(len).ok().filter(|&len| len > 0)) else {
+            return Ok(Value::object([
+                ("dataId", Value::Null),
+                (
+                    "description",
+                    Value::from(format!("{} can't be watched", name)),
+                ),
+            ]));
+        };
+        Ok(Value::object([
+            ("dataId", Value::from(data_id(addr, len))),
+            (
+                "description",
+                Value::from(format!("{} bytes at 0x{:03X}", len, addr)),
+            ),
+            (
+                "accessTypes",
+                Value::from(vec!["read".into(), "write".into(), "readWrite".into()]),
+            ),
+        ]))
+    }
+
+    /// Address held by variable `name` of scope `reference`, if it holds one
+    fn memory_reference(&mut self, reference: i64, name: &str) -> Result<Option<u16>, String> {
+        let scope = Value::object([("variablesReference", Value::from(reference))]);
+        let variables = self.variables(&scope)?;
+        Ok(variables
+            .get("variables")
+            .as_array()
+            .unwrap_or_default()
+            .iter()
+            .find(|var| {
+                var.get("name")
+                    .as_str()
+                    .is_some_and(|var| var.eq_ignore_ascii_case(name))
+            })
+            .and_then(|var| var.get("memoryReference").as_str())
+            .and_then(memory_address))
+    }
+
+    /// Replace every data breakpoint
+    fn set_data_breakpoints(&mut self, args: &Value) -> Result<Value, String> {
+        let target = self.target.as_mut().ok_or("no program has been launched")?;
+        while target.debugger.remove_watchpoint(0).is_some() {}
+
+        let requested = args.get("breakpoints").as_array().unwrap_or_default();
+        let mut breakpoints = Vec::new();
+        for breakpoint in requested {
+            let memory = breakpoint.get("dataId").as_str().and_then(parse_data_id);
+            let watchpoint = memory.and_then(|(addr, len)| {
+                match breakpoint.get("accessType").as_str().unwrap_or("write") {
+                    "read" => Some(Watchpoint::read(addr, len)),
+                    "write" => Some(Watchpoint::write(addr, len)),
+                    "readWrite" => Some(Watchpoint::access(addr, len)),
+                    _ => None,
+                }
+            });
+            if let Some(watchpoint) = watchpoint {
+                target.debugger.add_watchpoint(watchpoint);
+            }
+            breakpoints.push(Value::object([(
+                "verified",
+                Value::from(watchpoint.is_some()),
+            )]));
+        }
+        Ok(Value::object([("breakpoints", Value::from(breakpoints))]))
+    }
+
+    /// The current instruction, then the `CALL` of each subroutine on the stack
+    fn stack_trace(&mut self) -> Result<Value, String> {
+        let target = self.target()?;
+        let mut pcs = vec![target.emu.pc()];
+        pcs.extend(
+            target
+                .emu
+                .stack()
+                .iter()
+                .rev()
+                .map(|ret| ret.wrapping_sub(2)),
+        );
+
+        let frames: Vec<Value> = pcs
+            .iter()
+            .enumerate()
+            .map(|(id, &pc)| target.frame(id, pc))
+            .collect();
+        Ok(Value::object([
+            ("totalFrames", Value::from(frames.len())),
+            ("stackFrames", Value::from(frames)),
+        ]))
+    }
+
+    fn variables(&mut self, args: &Value) -> Result<Value, String> {
+        let emu = &self.target()?.emu;
+        let variables = match args.get("variablesReference").as_i64() {
+            Some(REGISTERS) => {
+                let mut variables: Vec<Value> = emu
+                    .registers()
+                    .iter()
+                    .enumerate()
+                    .map(|(reg, value)| variable(&format!("V{:X}", reg), *value, None))
+                    .collect();
+                variables.extend([
+                    variable("I", emu.i(), Some(emu.i())),
+                    variable("PC", emu.pc(), Some(emu.pc())),
+                    variable("SP", emu.stack().len() as u8, None),
+                    variable("DT", emu.delay_timer(), None),
+                    variable("ST", emu.sound_timer(), None),
+                ]);
+                variables
+            }
+            Some(STACK) => emu
+                .stack()
+                .iter()
+                .enumerate()
+                .map(|(depth, &addr)| variable(&depth.to_string(), addr, Some(addr)))
+                .collect(),
+            _ => return Err("unknown variablesReference".to_string()),
+        };
+        Ok(Value::object([("variables", Value::from(variables))]))
+    }
+
+    fn read_memory(&mut self, args: &Value) -> Result<Value, String> {
+        let reference = args
+            .get("memoryReference")
+            .as_str()
+            .ok_or("readMemory requires a memoryReference")?;
+        let base = parse_address(reference).ok_or("invalid memoryReference")?;
+        let offset = args.get("offset").as_i64().unwrap_or(0);
+        let count = args.get("count").as_i64().unwrap_or(0).max(0) as usize;
+
+        let memory = self.target()?.emu.memory();
+        let addr = base
+            .checked_add(offset)
+            .and_then(|addr| usize::try_from(addr).ok())
+            .ok_or("address out of range")?;
+        let start = addr.min(memory.len());
+        let end = addr.saturating_add(count).min(memory.len());
+        Ok(Value::object([
+            ("address", Value::from(format!("0x{:03X}", addr))),
+            ("data", Value::from(protocol::base64(&memory[start..end]))),
+            ("unreadableBytes", Value::from(count - (end - start))),
+        ]))
+    }
+
+    fn resume(&mut self) -> Result<Value, String> {
+        let target = self.runnable()?;
+        // Step off a breakpoint first, or continuing would stop straight away
+        if target.debugger.stop_at(&target.emu).is_some() {
+            let res = target.debugger.step(&mut target.emu);
+            if !self.after_step(res) {
+                return Ok(continued());
+            }
+        }
+        self.mode = Mode::Running;
+        Ok(continued())
+    }
+
+    /// Step over a line, running any subroutine it calls to completion
+    fn next(&mut self) -> Result<Value, String> {
+        let target = self.runnable()?;
+        let depth = target.emu.stack().len();
+        let pc = target.emu.pc() as usize;
+        let is_call = target.emu.memory().get(pc).is_some_and(|op| op >> 4 == 0x2);
+
+        let res = target.debugger.step(&mut target.emu);
+        if self.after_step(res) {
+            match is_call {
+                true => self.mode = Mode::StepOut { depth },
+                false => self.stopped("step", None),
+            }
+        }
+        Ok(Value::Null)
+    }
+
+    fn step_in(&mut self) -> Result<Value, String> {
+        let target = self.runnable()?;
+        let res = target.debugger.step(&mut target.emu);
+        if self.after_step(res) {
+            self.stopped("step", None);
+        }
+        Ok(Value::Null)
+    }
+
+    /// Run until the current subroutine returns, or step a line when not in one
+    fn step_out(&mut self) -> Result<Value, String> {
+        let target = self.runnable()?;
+        match target.emu.stack().len().checked_sub(1) {
+            Some(depth) => self.mode = Mode::StepOut { depth },
+            None => return self.step_in(),
+        }
+        Ok(Value::Null)
+    }
+
+    fn pause(&mut self) -> Result<Value, String> {
+        self.target()?;
+        if self.is_running() {
+            self.stopped("pause", None);
+        }
+        Ok(Value::Null)
+    }
+
+    /// End the program, leaving it to be inspected but not run any further
+    fn terminate(&mut self) -> Result<Value, String> {
+        if let Some(target) = self.target.as_mut() {
+            target.exited = true;
+        }
+        self.mode = Mode::Stopped;
+        self.event("terminated", Value::Null);
+        Ok(Value::Null)
+    }
+
+    /// Run the program for a frame's worth of instructions
+    fn run_frame(&mut self) -> io::Result<()> {
+        let Some(target) = self.target.as_mut() else {
+            self.mode = Mode::Stopped;
+            return Ok(());
+        };
+
+        match self.mode {
+            Mode::Stopped => {}
+            Mode::Running => {
+                let res = target.debugger.run_frame(&mut target.emu);
+                self.after_step(res);
+            }
+            Mode::StepOut { depth } => {
+                // Nothing runs while waiting for a key, but time still passes
+                if target.emu.state() != CpuState::Running {
+                    let res = target.debugger.run_frame(&mut target.emu);
+                    self.after_step(res);
+                    return self.flush_events();
+                }
+
+                for _ in 0..target.emu.cycles_per_frame() {
+                    let target = self.target.as_mut().unwrap();
+                    let res = target.debugger.step(&mut target.emu);
+                    if !self.after_step(res) {
+                        break;
+                    }
+
+                    let target = self.target.as_ref().unwrap();
+                    let returned = target.emu.stack().len() <= depth;
+                    let stop = target.debugger.stop_at(&target.emu);
+                    let waiting = target.emu.state() != CpuState::Running;
+                    if returned {
+                        self.stopped("step", None);
+                    } else if let Some(stop) = stop {
+                        self.stopped(stop_reason(&stop), None);
+                    }
+                    if !self.is_running() || waiting {
+                        break;
+                    }
+                }
+            }
+        }
+        self.flush_events()
+    }
+
+    /// Report the program exiting, faulting or hitting a breakpoint, returning whether it
+    /// can carry on
+    fn after_step(&mut self, res: Result<Option<Stop>, EmulatorError>) -> bool {
+        let Some(target) = self.target.as_mut() else {
+            return false;
+        };
+        match res {
+            Err(err) => {
+                self.stopped("exception", Some(err.to_string()));
+                false
+            }
+            Ok(_) if target.emu.is_halted() => {
+                target.exited = true;
+                self.mode = Mode::Stopped;
+                self.event("exited", Value::object([("exitCode", Value::from(0))]));
+                self.event("terminated", Value::Null);
+                false
+            }
+            Ok(Some(stop)) => {
+                self.stopped(stop_reason(&stop), None);
+                false
+            }
+            Ok(None) => true,
+        }
+    }
+
+    /// Tell the client a message was skipped because it couldn't be parsed
+    fn malformed(&mut self, err: &str) -> io::Result<()> {
+        self.event(
+            "output",
+            Value::object([
+                ("category", Value::from("console")),
+                (
+                    "output",
+                    Value::from(format!("ignored malformed message: {}\n", err)),
+                ),
+            ]),
+        );
+        self.flush_events()
+    }
+
+    fn stopped(&mut self, reason: &str, text: Option<String>) {
+        self.mode = Mode::Stopped;
+        let mut body = vec![
+            ("reason", Value::from(reason)),
+            ("threadId", Value::from(THREAD_ID)),
+            ("allThreadsStopped", Value::from(true)),
+        ];
+        if let Some(text) = text {
+            body.push(("text", Value::from(text)));
+        }
+        self.event("stopped", Value::object(body));
+    }
+
+    fn event(&mut self, event: &str, body: Value) {
+        let mut message = vec![
+            ("type", Value::from("event")),
+            ("event", Value::from(event)),
+        ];
+        if !body.is_null() {
+            message.push(("body", body));
+        }
+        self.events.push(Value::object(message));
+    }
+
+    fn flush_events(&mut self) -> io::Result<()> {
+        for event in std::mem::take(&mut self.events) {
+            let Value::Object(mut members) = event else {
+                continue;
+            };
+            members.insert(0, ("seq".to_string(), Value::from(self.next_seq())));
+            protocol::write_message(&mut self.output, &Value::Object(members))?;
+        }
+        Ok(())
+    }
+
+    fn next_seq(&mut self) -> i64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    fn target(&self) -> Result<&Target, String> {
+        self.target
+            .as_ref()
+            .ok_or_else(|| "no program has been launched".to_string())
+    }
+
+    /// Target, as long as it's paused and hasn't exited
+    fn runnable(&mut self) -> Result<&mut Target, String> {
+        if self.is_running() {
+            return Err("the program is already running".to_string());
+        }
+        let target = self.target.as_mut().ok_or("no program has been launched")?;
+        if target.exited {
+            return Err("the program has exited".to_string());
+        }
+        Ok(target)
+    }
+}
+
+impl Target {
+    /// Stack frame at `pc`, named after the closest label before it
+    fn frame(&self, id: usize, pc: u16) -> Value {
+        let label = self
+            .assembly
+            .as_ref()
+            .and_then(|assembly| assembly.label_before(pc));
+        let name = match label {
+            Some((label, addr)) if addr == pc => label.to_string(),
+            Some((label, addr)) => format!("{}+{}", label, pc - addr),
+            None => format!("{:03X}", pc),
+        };
+
+        let mut frame = vec![
+            ("id", Value::from(id)),
+            ("name", Value::from(name)),
+            (
+                "instructionPointerReference",
+                Value::from(format!("0x{:03X}", pc)),
+            ),
+        ];
+        match self.assembly.as_ref().and_then(|asm| asm.line_at(pc)) {
+            Some(mapping) => frame.extend([
+                ("source", source(&mapping.source)),
+                ("line", Value::from(mapping.line)),
+                ("column", Value::from(1)),
+            ]),
+            None => frame.extend([("line", Value::from(0)), ("column", Value::from(0))]),
+        }
+        Value::object(frame)
+    }
+}
+
+fn capabilities() -> Value {
+    Value::object([
+        ("supportsConfigurationDoneRequest", Value::from(true)),
+        ("supportsDataBreakpoints", Value::from(true)),
+        ("supportsReadMemoryRequest", Value::from(true)),
+        ("supportsTerminateRequest", Value::from(true)),
+    ])
+}
+
+fn scopes() -> Value {
+    let scope = |name: &str, reference: i64| {
+        Value::object([
+            ("name", Value::from(name)),
+            ("variablesReference", Value::from(reference)),
+            ("expensive", Value::from(false)),
+        ])
+    };
+    Value::object([(
+        "scopes",
+        Value::from(vec![scope("Registers", REGISTERS), scope("Stack", STACK)]),
+    )])
+}
+
+/// `reason` of the `stopped` event for what the debugger stopped at
+fn stop_reason(stop: &Stop) -> &'static str {
+    match stop {
+        Stop::Breakpoint(_) => "breakpoint",
+        Stop::Opcode { .. } => "instruction breakpoint",
+        Stop::Watchpoint { .. } | Stop::Condition { .. } => "data breakpoint",
+    }
+}
+
+fn continued() -> Value {
+    Value::object([("allThreadsContinued", Value::from(true))])
+}
+
+fn source(path: &str) -> Value {
+    let name = Path::new(path)
+        .file_name()
+        .map_or(path.to_string(), |name| name.to_string_lossy().into_owned());
+    Value::object([("name", Value::from(name)), ("path", Value::from(path))])
+}
+
+/// Variable shown in hex, with a memory reference if it holds an address
+fn variable<N: Into<u16>>(name: &str, value: N, addr: Option<u16>) -> Value {
+    let value: u16 = value.into();
+    let mut members = vec![
+        ("name", Value::from(name)),
+        ("value", Value::from(format!("0x{:02X}", value))),
+        ("variablesReference", Value::from(0)),
+    ];
+    if let Some(addr) = addr {
+        members.push(("memoryReference", Value::from(format!("0x{:03X}", addr))));
+    }
+    Value::object(members)
+}
+
+/// `dataId` of `len` bytes of memory at `addr`
+fn data_id(addr: u16, len: u16) -> String {
+    format!("0x{:03X}/{}", addr, len)
+}
+
+/// Address in memory given as text, as by `parse_address`
+fn memory_address(text: &str) -> Option<u16> {
+    parse_address(text).and_then(|addr| u16::try_from(addr).ok())
+}
+
+/// Address and length of memory from a `dataId`
+fn parse_data_id(id: &str) -> Option<(u16, u16)> {
+    let (addr, len) = id.split_once('/')?;
+    Some((memory_address(addr)?, len.parse().ok()?))
+}
+
+/// Address given in hex with a `0x` prefix, or decimal
+fn parse_address(text: &str) -> Option<i64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
@@ -0,0 +1,9 @@
+use std::io::{self, BufReader};
+
+/// Speaks the Debug Adapter Protocol over stdin and stdout, for IDEs to launch
+pub fn main() {
+    if let Err(err) = dap::serve(BufReader::new(io::stdin()), io::stdout()) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,87 @@
+//! Framing for Debug Adapter Protocol messages, a `Content-Length` header then JSON.
+
+use std::io::{self, BufRead, ErrorKind, Write};
+
+use crate::json::{self, Value};
+
+/// Largest message accepted, far more than any request needs
+const MAX_MESSAGE: usize = 1 << 24;
+
+/// Read the next message, or `None` at the end of the stream
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    match read_body(reader)? {
+        Some(body) => parse_body(&body)
+            .map(Some)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err)),
+        None => Ok(None),
+    }
+}
+
+/// Read the body of the next message without parsing it, or `None` at the end of the
+/// stream
+///
+/// Only a broken header is an error: a body that doesn't parse has still been read in
+/// full, so the next message can be read after it.
+pub fn read_body<R: BufRead>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                len = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let len = match len {
+        Some(len) if len <= MAX_MESSAGE => len,
+        _ => return Err(invalid("missing or invalid Content-Length")),
+    };
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+/// Parse the body of a message
+pub fn parse_body(body: &[u8]) -> Result<Value, String> {
+    let text = std::str::from_utf8(body).map_err(|_| "message is not UTF-8".to_string())?;
+    json::parse(text).map_err(|err| err.to_string())
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/// Standard base64 with padding, how `readMemory` returns bytes
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (idx, &byte)| {
+            group | (byte as u32) << (16 - 8 * idx)
+        });
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(ALPHABET[(group >> (18 - 6 * idx)) as usize & 0x3F] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,3 @@
+; Adds one to V0
+        ADD V0, 1
+        RET
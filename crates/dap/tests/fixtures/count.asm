@@ -0,0 +1,13 @@
+; Counts to three in V0 with a subroutine from another file, then stores
+; the count at `result` and exits
+
+start:  LD V0, 0
+loop:   CALL bump
+        SE V0, 3
+        JP loop
+        LD I, result
+        LD [I], V0
+        EXIT
+
+bump:   INCLUDE "bump.asm"
+result: DB 0
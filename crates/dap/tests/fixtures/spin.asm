@@ -0,0 +1,2 @@
+; Never stops, for pausing
+loop:   JP loop
@@ -0,0 +1,111 @@
+use dap::json::{parse, Value};
+
+#[test]
+fn parses_nested_values() {
+    let value = parse(r#" {"a": [1, -2.5, true, null], "b": {"c": "d"}} "#).unwrap();
+    let items = value.get("a").as_array().unwrap();
+    assert_eq!(items[0].as_i64(), Some(1));
+    assert_eq!(items[1], Value::Number(-2.5));
+    assert_eq!(items[2].as_bool(), Some(true));
+    assert!(items[3].is_null());
+    assert_eq!(value.get("b").get("c").as_str(), Some("d"));
+    assert!(value.get("missing").is_null());
+}
+
+#[test]
+fn string_escapes_round_trip() {
+    let value = parse(r#""tab\t quote\" slash\\ é 😀 \/""#).unwrap();
+    assert_eq!(value.as_str(), Some("tab\t quote\" slash\\ é 😀 /"));
+    assert_eq!(parse(&value.to_string()).unwrap(), value);
+}
+
+#[test]
+fn writes_compactly() {
+    let value = Value::object([
+        ("seq", Value::from(1)),
+        ("items", Value::from(vec![Value::Null, Value::from(false)])),
+        ("text", Value::from("a\nb")),
+    ]);
+    assert_eq!(
+        value.to_string(),
+        r#"{"seq":1,"items":[null,false],"text":"a\nb"}"#
+    );
+}
+
+#[test]
+fn rejects_malformed_input() {
+    for text in [
+        "",
+        "{",
+        "[1,]",
+        r#"{"a" 1}"#,
+        "tru",
+        r#""\x""#,
+        "1 2",
+        r#""\ud83d""#,
+    ] {
+        assert!(parse(text).is_err(), "{:?} parsed", text);
+    }
+    assert_eq!(parse("[1, }").unwrap_err().offset, 4);
+}
+
+#[test]
+fn numbers_follow_the_grammar() {
+    for (text, num) in [
+        ("0", 0.0),
+        ("-0", 0.0),
+        ("10", 10.0),
+        ("0.5", 0.5),
+        ("-1.25e2", -125.0),
+        ("1E+2", 100.0),
+        ("2e-1", 0.2),
+    ] {
+        assert_eq!(parse(text), Ok(Value::Number(num)), "{:?}", text);
+    }
+
+    for (text, message) in [
+        ("01", "leading zero"),
+        ("-01", "leading zero"),
+        ("-", "invalid number"),
+        ("+1", "expected a value"),
+        (".5", "expected a value"),
+        ("1.", "invalid number"),
+        ("1.e3", "invalid number"),
+        ("1e", "invalid number"),
+        ("1e+", "invalid number"),
+        ("--1", "invalid number"),
+        ("1e400", "number out of range"),
+    ] {
+        assert_eq!(parse(text).unwrap_err().message, message, "{:?}", text);
+    }
+    assert_eq!(parse("[1, 2-3]").unwrap_err().offset, 5);
+}
+
+#[test]
+fn integers_must_fit() {
+    assert_eq!(
+        parse("-9007199254740992").unwrap().as_i64(),
+        Some(-(1 << 53))
+    );
+    assert_eq!(
+        parse("-9223372036854775808").unwrap().as_i64(),
+        Some(i64::MIN)
+    );
+    assert_eq!(parse("9223372036854775807").unwrap().as_i64(), None);
+    assert_eq!(parse("1e30").unwrap().as_i64(), None);
+    assert_eq!(parse("-1e30").unwrap().as_i64(), None);
+    assert_eq!(parse("1.5").unwrap().as_i64(), None);
+}
+
+#[test]
+fn strings_must_escape_control_characters() {
+    let err = parse("\"a\tb\"").unwrap_err();
+    assert_eq!(
+        (err.offset, err.message),
+        (2, "control character in string")
+    );
+    assert_eq!(parse("\"a\\u0009b\"").unwrap().as_str(), Some("a\tb"));
+    assert_eq!(parse(r#""\u+123""#).unwrap_err().message, "invalid escape");
+    assert_eq!(parse(r#""\u12"#).unwrap_err().message, "invalid escape");
+    assert_eq!(parse(r#""abc"#).unwrap_err().message, "unterminated string");
+}
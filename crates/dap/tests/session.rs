@@ -0,0 +1,511 @@
+//! Drives the `chip8-dap` binary over stdio with a scripted client.
+
+use std::collections::VecDeque;
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use dap::json::Value;
+use dap::protocol;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+    /// Events read while waiting for something else
+    events: VecDeque<Value>,
+    seq: i64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_chip8-dap"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+        let (tx, messages) = mpsc::channel();
+        thread::spawn(move || {
+            while let Some(message) = protocol::read_message(&mut stdout).unwrap() {
+                if tx.send(message).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self {
+            child,
+            stdin,
+            messages,
+            events: VecDeque::new(),
+            seq: 0,
+        }
+    }
+
+    /// Initialize and launch `program`, waiting until breakpoints can be set
+    fn launch(program: &str, stop_on_entry: bool) -> Self {
+        let mut client = Self::start();
+        client.ok("initialize", Value::object([("adapterID", "chip8".into())]));
+        let path = fixture(program).display().to_string();
+        client.ok(
+            "launch",
+            Value::object([
+                ("program", path.into()),
+                ("stopOnEntry", stop_on_entry.into()),
+            ]),
+        );
+        client.event("initialized");
+        client
+    }
+
+    fn recv(&mut self) -> Value {
+        self.messages
+            .recv_timeout(Duration::from_secs(10))
+            .expect("adapter stopped replying")
+    }
+
+    /// Send a request and return its response
+    fn request(&mut self, command: &str, args: Value) -> Value {
+        self.seq += 1;
+        let request = Value::object([
+            ("seq", self.seq.into()),
+            ("type", "request".into()),
+            ("command", command.into()),
+            ("arguments", args),
+        ]);
+        protocol::write_message(&mut self.stdin, &request).unwrap();
+
+        loop {
+            let message = self.recv();
+            if message.get("type").as_str() != Some("response") {
+                self.events.push_back(message);
+                continue;
+            }
+            assert_eq!(message.get("request_seq").as_i64(), Some(self.seq));
+            assert_eq!(message.get("command").as_str(), Some(command));
+            return message;
+        }
+    }
+
+    /// Send a request that must succeed and return the body of its response
+    fn ok(&mut self, command: &str, args: Value) -> Value {
+        let response = self.request(command, args);
+        assert_eq!(
+            response.get("success").as_bool(),
+            Some(true),
+            "{} failed: {}",
+            command,
+            response
+        );
+        response.get("body").clone()
+    }
+
+    /// Wait for the next event called `name`
+    fn event(&mut self, name: &str) -> Value {
+        let is_named = |message: &Value| message.get("event").as_str() == Some(name);
+        if let Some(idx) = self.events.iter().position(is_named) {
+            return self.events.remove(idx).unwrap();
+        }
+        loop {
+            let message = self.recv();
+            if is_named(&message) {
+                return message;
+            }
+            self.events.push_back(message);
+        }
+    }
+
+    fn stopped(&mut self) -> String {
+        let event = self.event("stopped");
+        event
+            .get("body")
+            .get("reason")
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    /// `(name, file, line)` of every stack frame, innermost first
+    fn frames(&mut self) -> Vec<(String, String, i64)> {
+        let body = self.ok("stackTrace", Value::object([("threadId", 1.into())]));
+        body.get("stackFrames")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|frame| {
+                (
+                    frame.get("name").as_str().unwrap().to_string(),
+                    frame
+                        .get("source")
+                        .get("name")
+                        .as_str()
+                        .unwrap()
+                        .to_string(),
+                    frame.get("line").as_i64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn line(&mut self) -> (String, i64) {
+        let (_, file, line) = self.frames().remove(0);
+        (file, line)
+    }
+
+    fn variables(&mut self, reference: i64) -> Vec<(String, String)> {
+        let args = Value::object([("variablesReference", reference.into())]);
+        self.ok("variables", args)
+            .get("variables")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|var| {
+                (
+                    var.get("name").as_str().unwrap().to_string(),
+                    var.get("value").as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn register(&mut self, name: &str) -> String {
+        let registers = self.variables(1);
+        let (_, value) = registers.into_iter().find(|(reg, _)| reg == name).unwrap();
+        value
+    }
+
+    fn set_breakpoints(&mut self, file: &str, lines: &[i64]) -> Value {
+        let breakpoints = lines
+            .iter()
+            .map(|&line| Value::object([("line", line.into())]))
+            .collect::<Vec<_>>();
+        let source = Value::object([("path", fixture(file).display().to_string().into())]);
+        self.ok(
+            "setBreakpoints",
+            Value::object([("source", source), ("breakpoints", breakpoints.into())]),
+        )
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn stop_on_entry() {
+    let mut client = Client::launch("count.asm", true);
+    client.ok("configurationDone", Value::Null);
+    assert_eq!(client.stopped(), "entry");
+
+    let threads = client.ok("threads", Value::Null);
+    assert_eq!(threads.get("threads").as_array().unwrap().len(), 1);
+    assert_eq!(
+        client.frames(),
+        vec![("start".to_string(), "count.asm".to_string(), 4)]
+    );
+}
+
+#[test]
+fn breakpoint_in_included_file() {
+    let mut client = Client::launch("count.asm", false);
+    // Line 1 is a comment, so the breakpoint moves to the first instruction
+    let body = client.set_breakpoints("bump.asm", &[1]);
+    let breakpoint = &body.get("breakpoints").as_array().unwrap()[0];
+    assert_eq!(breakpoint.get("verified").as_bool(), Some(true));
+    assert_eq!(breakpoint.get("line").as_i64(), Some(2));
+
+    client.ok("configurationDone", Value::Null);
+    assert_eq!(client.stopped(), "breakpoint");
+    assert_eq!(
+        client.frames(),
+        vec![
+            ("bump".to_string(), "bump.asm".to_string(), 2),
+            ("loop".to_string(), "count.asm".to_string(), 5),
+        ]
+    );
+    assert_eq!(client.register("V0"), "0x00");
+    assert_eq!(
+        client.variables(2),
+        vec![("0".to_string(), "0x204".to_string())]
+    );
+
+    client.ok("continue", Value::object([("threadId", 1.into())]));
+    assert_eq!(client.stopped(), "breakpoint");
+    assert_eq!(client.register("V0"), "0x01");
+
+    // Clearing the file's breakpoints lets the program run to the end
+    client.set_breakpoints("bump.asm", &[]);
+    client.ok("continue", Value::object([("threadId", 1.into())]));
+    client.event("exited");
+    client.event("terminated");
+}
+
+#[test]
+fn unresolved_breakpoint() {
+    let mut client = Client::launch("count.asm", false);
+    let body = client.set_breakpoints("count.asm", &[40]);
+    let breakpoint = &body.get("breakpoints").as_array().unwrap()[0];
+    assert_eq!(breakpoint.get("verified").as_bool(), Some(false));
+}
+
+#[test]
+fn stepping() {
+    let mut client = Client::launch("count.asm", true);
+    client.ok("configurationDone", Value::Null);
+    client.stopped();
+    let thread = || Value::object([("threadId", 1.into())]);
+
+    client.ok("next", thread());
+    assert_eq!(client.stopped(), "step");
+    assert_eq!(client.line(), ("count.asm".to_string(), 5));
+
+    // Stepping over the call runs the whole subroutine
+    client.ok("next", thread());
+    assert_eq!(client.stopped(), "step");
+    assert_eq!(client.line(), ("count.asm".to_string(), 6));
+    assert_eq!(client.register("V0"), "0x01");
+
+    client.ok("next", thread());
+    client.stopped();
+    client.ok("next", thread());
+    client.stopped();
+    assert_eq!(client.line(), ("count.asm".to_string(), 5));
+
+    client.ok("stepIn", thread());
+    assert_eq!(client.stopped(), "step");
+    assert_eq!(client.line(), ("bump.asm".to_string(), 2));
+    assert_eq!(client.register("SP"), "0x01");
+
+    client.ok("stepOut", thread());
+    assert_eq!(client.stopped(), "step");
+    assert_eq!(client.line(), ("count.asm".to_string(), 6));
+    assert_eq!(client.register("V0"), "0x02");
+    assert_eq!(client.register("SP"), "0x00");
+}
+
+#[test]
+fn runs_to_exit() {
+    let mut client = Client::launch("count.asm", false);
+    client.ok("configurationDone", Value::Null);
+    let exited = client.event("exited");
+    assert_eq!(exited.get("body").get("exitCode").as_i64(), Some(0));
+    client.event("terminated");
+
+    // The count written to `result`, still readable after exiting
+    let args = Value::object([("memoryReference", "0x212".into()), ("count", 1.into())]);
+    let body = client.ok("readMemory", args);
+    assert_eq!(body.get("data").as_str(), Some("Aw=="));
+
+    let response = client.request("next", Value::object([("threadId", 1.into())]));
+    assert_eq!(response.get("success").as_bool(), Some(false));
+}
+
+#[test]
+fn registers_and_memory() {
+    let mut client = Client::launch("count.asm", true);
+    client.ok("configurationDone", Value::Null);
+    client.stopped();
+
+    let scopes = client.ok("scopes", Value::object([("frameId", 0.into())]));
+    let names: Vec<_> = scopes
+        .get("scopes")
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|scope| scope.get("name").as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["Registers", "Stack"]);
+
+    let registers = client.variables(1);
+    let names: Vec<_> = registers.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names[..2], ["V0", "V1"]);
+    assert_eq!(names[15..], ["VF", "I", "PC", "SP", "DT", "ST"]);
+    assert_eq!(client.register("PC"), "0x200");
+    assert!(client.variables(2).is_empty());
+
+    let args = Value::object([
+        ("memoryReference", "0x1FE".into()),
+        ("offset", 2.into()),
+        ("count", 4.into()),
+    ]);
+    let body = client.ok("readMemory", args);
+    assert_eq!(body.get("address").as_str(), Some("0x200"));
+    // LD V0, 0 then CALL bump
+    assert_eq!(body.get("data").as_str(), Some("YAAiDg=="));
+
+    let args = Value::object([("memoryReference", "0xFFE".into()), ("count", 4.into())]);
+    let body = client.ok("readMemory", args);
+    assert_eq!(body.get("unreadableBytes").as_i64(), Some(2));
+}
+
+#[test]
+fn pause() {
+    let mut client = Client::launch("spin.asm", false);
+    client.ok("configurationDone", Value::Null);
+    thread::sleep(Duration::from_millis(50));
+    client.ok("pause", Value::object([("threadId", 1.into())]));
+    assert_eq!(client.stopped(), "pause");
+    assert_eq!(client.line(), ("spin.asm".to_string(), 2));
+}
+
+#[test]
+fn reports_assembly_errors() {
+    let mut client = Client::start();
+    client.ok("initialize", Value::object([("adapterID", "chip8".into())]));
+    let program = fixture("broken.asm").display().to_string();
+    let response = client.request("launch", Value::object([("program", program.into())]));
+    assert_eq!(response.get("success").as_bool(), Some(false));
+    let message = response.get("message").as_str().unwrap();
+    assert!(message.contains("broken.asm:2:"), "{}", message);
+}
+
+#[test]
+fn disconnect_ends_session() {
+    let mut client = Client::launch("count.asm", true);
+    assert_eq!(
+        client
+            .request("evaluate", Value::Null)
+            .get("success")
+            .as_bool(),
+        Some(false)
+    );
+    client.ok("disconnect", Value::Null);
+    assert!(client.child.wait().unwrap().success());
+}
+
+#[test]
+fn data_breakpoints() {
+    let mut client = Client::launch("count.asm", true);
+    client.ok("configurationDone", Value::Null);
+    client.stopped();
+    let thread = || Value::object([("threadId", 1.into())]);
+
+    // `result`, written once the count is done
+    let info = client.ok(
+        "dataBreakpointInfo",
+        Value::object([("name", "0x212".into()), ("asAddress", true.into())]),
+    );
+    assert_eq!(info.get("dataId").as_str(), Some("0x212/1"));
+    let breakpoint = Value::object([("dataId", "0x212/1".into()), ("accessType", "write".into())]);
+    let body = client.ok(
+        "setDataBreakpoints",
+        Value::object([("breakpoints", vec![breakpoint].into())]),
+    );
+    let verified = &body.get("breakpoints").as_array().unwrap()[0];
+    assert_eq!(verified.get("verified").as_bool(), Some(true));
+
+    client.ok("continue", thread());
+    assert_eq!(client.stopped(), "data breakpoint");
+    assert_eq!(client.line(), ("count.asm".to_string(), 10));
+    assert_eq!(client.register("V0"), "0x03");
+}
+
+#[test]
+fn data_breakpoint_while_stepping() {
+    let mut client = Client::launch("count.asm", false);
+    client.set_breakpoints("count.asm", &[9]);
+    client.ok("configurationDone", Value::Null);
+    assert_eq!(client.stopped(), "breakpoint");
+
+    // I points at `result`, V0 holds no address
+    let info = |client: &mut Client, name: &str| {
+        let args = Value::object([("variablesReference", 1.into()), ("name", name.into())]);
+        client.ok("dataBreakpointInfo", args).get("dataId").clone()
+    };
+    assert!(info(&mut client, "V0").is_null());
+    let id = info(&mut client, "I");
+    assert_eq!(id.as_str(), Some("0x212/1"));
+    let breakpoint = Value::object([("dataId", id), ("accessType", "readWrite".into())]);
+    client.ok(
+        "setDataBreakpoints",
+        Value::object([("breakpoints", vec![breakpoint].into())]),
+    );
+
+    client.ok("next", Value::object([("threadId", 1.into())]));
+    assert_eq!(client.stopped(), "data breakpoint");
+    assert_eq!(client.line(), ("count.asm".to_string(), 10));
+}
+
+#[test]
+fn terminate() {
+    let mut client = Client::launch("count.asm", true);
+    client.ok("configurationDone", Value::Null);
+    client.stopped();
+    client.ok("terminate", Value::Null);
+    client.event("terminated");
+
+    let response = client.request("next", Value::object([("threadId", 1.into())]));
+    assert_eq!(response.get("success").as_bool(), Some(false));
+    let response = client.request("continue", Value::object([("threadId", 1.into())]));
+    assert_eq!(response.get("success").as_bool(), Some(false));
+}
+
+#[test]
+fn read_memory_out_of_range() {
+    let mut client = Client::launch("count.asm", true);
+    for (reference, offset) in [("0x7FFFFFFFFFFFFFFF", 1), ("0x200", -0x201)] {
+        let args = Value::object([
+            ("memoryReference", reference.into()),
+            ("offset", offset.into()),
+            ("count", 1.into()),
+        ]);
+        let response = client.request("readMemory", args);
+        assert_eq!(response.get("success").as_bool(), Some(false));
+        assert_eq!(
+            response.get("message").as_str(),
+            Some("address out of range")
+        );
+    }
+}
+
+#[test]
+fn skips_malformed_messages() {
+    let mut client = Client::launch("count.asm", true);
+    for body in [&b"{\"seq\": 01}"[..], b"\xFF", b"[1, 2"] {
+        write!(client.stdin, "Content-Length: {}\r\n\r\n", body.len()).unwrap();
+        client.stdin.write_all(body).unwrap();
+        let output = client.event("output");
+        let text = output.get("body").get("output").as_str().unwrap();
+        assert!(text.starts_with("ignored malformed message"), "{}", text);
+    }
+
+    // A message that parses but isn't a request is ignored, then the session carries on
+    protocol::write_message(&mut client.stdin, &Value::from(vec![Value::Null])).unwrap();
+    client.ok("configurationDone", Value::Null);
+    assert_eq!(client.stopped(), "entry");
+}
+
+#[test]
+fn data_breakpoint_info_without_a_scope() {
+    let mut client = Client::launch("count.asm", false);
+    client.set_breakpoints("count.asm", &[9]);
+    client.ok("configurationDone", Value::Null);
+    client.stopped();
+
+    let mut data_id = |name: &str| {
+        let args = Value::object([("name", name.into())]);
+        client.ok("dataBreakpointInfo", args).get("dataId").clone()
+    };
+    // Registers are looked up by name, anything else is taken as an address
+    assert_eq!(data_id("I").as_str(), Some("0x212/1"));
+    assert_eq!(data_id("pc").as_str(), Some("0x20A/1"));
+    assert_eq!(data_id("0x300").as_str(), Some("0x300/1"));
+    assert!(data_id("V0").is_null());
+    assert!(data_id("0x10000").is_null());
+}
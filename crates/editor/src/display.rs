@@ -1,4 +1,5 @@
 use eframe::egui;
+use emulator::disassembler::{disassemble, Syntax};
 use emulator::display;
 use tokio::sync::{mpsc, oneshot};
 
@@ -82,16 +83,31 @@ impl display::RenderTarget for RenderTarget {
     }
 
     fn fill_rect(&mut self, x: usize, y: usize, w: usize, h: usize, color: display::Color) {
+        let pixel = rgba(color);
         for x in x..x + w {
             for y in y..y + h {
-                let pixel = match color {
-                    display::Color::Black => image::Rgba([0, 0, 0, 255]),
-                    display::Color::White => image::Rgba([255, 255, 255, 255]),
-                };
                 self.get_back_buffer().put_pixel(x as u32, y as u32, pixel);
             }
         }
     }
+
+    fn blit_frame(&mut self, frame: &[u8], cols: usize, rows: usize) {
+        let (w, h) = (self.w, self.h);
+        for (idx, pixel) in self.get_back_buffer().pixels_mut().enumerate() {
+            let (x, y) = (idx % w, idx / w);
+            let src = frame[y * rows / h * cols + x * cols / w];
+            *pixel = rgba(display::Color::from_planes(src));
+        }
+    }
+}
+
+fn rgba(color: display::Color) -> image::Rgba<u8> {
+    match color {
+        display::Color::Black => image::Rgba([0, 0, 0, 255]),
+        display::Color::White => image::Rgba([255, 255, 255, 255]),
+        display::Color::LightGray => image::Rgba([170, 170, 170, 255]),
+        display::Color::DarkGray => image::Rgba([85, 85, 85, 255]),
+    }
 }
 
 pub(crate) struct GameWindow {
@@ -100,6 +116,8 @@ pub(crate) struct GameWindow {
     buffer: Buffer,
     texture: egui::TextureHandle,
     tx: mpsc::Sender<RenderTargetEditorRequest>,
+    rom_tx: mpsc::Sender<Vec<u8>>,
+    disassembly: Vec<String>,
 }
 
 impl GameWindow {
@@ -108,6 +126,7 @@ impl GameWindow {
         height: usize,
         target: egui::TextureHandle,
         tx: mpsc::Sender<RenderTargetEditorRequest>,
+        rom_tx: mpsc::Sender<Vec<u8>>,
     ) -> Self {
         Self {
             w: width,
@@ -115,12 +134,53 @@ impl GameWindow {
             buffer: Default::default(),
             texture: target,
             tx,
+            rom_tx,
+            disassembly: Vec::new(),
+        }
+    }
+
+    /// Restart the emulator on `rom`
+    pub(crate) fn load_rom(&mut self, rom: Vec<u8>) {
+        self.disassembly = disassemble(&rom, emulator::PROGRAM_START)
+            .iter()
+            .map(|line| line.render(Syntax::Cowgod))
+            .collect();
+        self.rom_tx.blocking_send(rom).unwrap();
+    }
+}
+
+/// Contents of a file dropped onto the window, which only native builds give a path for
+fn read_dropped(file: &egui::DroppedFile) -> Option<Vec<u8>> {
+    if let Some(bytes) = &file.bytes {
+        return Some(bytes.to_vec());
+    }
+    let path = file.path.as_ref()?;
+    match std::fs::read(path) {
+        Ok(rom) => Some(rom),
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            None
         }
     }
 }
 
 impl eframe::App for GameWindow {
     fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        let dropped = ctx.input().raw.dropped_files.clone();
+        if let Some(rom) = dropped.iter().find_map(read_dropped) {
+            self.load_rom(rom);
+        }
+
+        egui::SidePanel::right("disassembly").show(ctx, |ui| {
+            if self.disassembly.is_empty() {
+                ui.label("Drop a ROM onto the window to run it");
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for line in &self.disassembly {
+                    ui.monospace(line);
+                }
+            });
+        });
         egui::CentralPanel::default().show(ctx, |ui| {
             let (tx, rx) = oneshot::channel();
             self.tx
@@ -1,6 +1,6 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-use std::{str::FromStr, time::Duration};
+use std::time::Duration;
 
 use eframe::egui;
 use tokio::runtime::Runtime;
@@ -37,20 +37,60 @@ fn main() {
                 tokio::sync::mpsc::channel::<display::RenderTargetEditorRequest>(10);
             let rt = display::RenderTarget::new(WIDTH, HEIGHT, cc.egui_ctx.clone(), render_rx);
 
-            let mut emu = emulator::Chip8::new(emulator::display::Display::new(rt));
-            emu.load(std::path::PathBuf::from_str(".\\res\\Maze.ch8").unwrap())
-                .unwrap();
+            // ROMs come from the command line or are dropped onto the window
+            let (rom_tx, mut rom_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(1);
+            let rom = rom_from_args();
+
+            let quirks = emulator::quirks::Quirks::default();
+            let mut emu = emulator::Chip8::new(emulator::display::Display::new(rt), quirks);
+            let mut running = false;
 
-            // let egui_ctx = &cc.egui_ctx;
             tokio::spawn(async move {
                 loop {
-                    emu.tick();
+                    if let Ok(rom) = rom_rx.try_recv() {
+                        // Start over on a fresh machine with the same render target
+                        let target = emu.into_display().target;
+                        emu = emulator::Chip8::new(emulator::display::Display::new(target), quirks);
+                        running = match emu.load_bytes(&rom) {
+                            Ok(()) => true,
+                            Err(err) => {
+                                eprintln!("unable to load ROM: {}", err);
+                                false
+                            }
+                        };
+                    }
+                    if running {
+                        running = match emu.run_frame() {
+                            Ok(emulator::CpuState::Halted) => false,
+                            Ok(_) => true,
+                            Err(err) => {
+                                eprintln!("emulator crashed: {}", err);
+                                false
+                            }
+                        };
+                    }
                     emu.display.target.tick(); // NOTE: this hacky af
+                    tokio::time::sleep(Duration::from_micros(16_667)).await;
                 }
             });
 
-            let gw = display::GameWindow::new(WIDTH, HEIGHT, target, render_tx);
+            let mut gw = display::GameWindow::new(WIDTH, HEIGHT, target, render_tx, rom_tx);
+            if let Some(rom) = rom {
+                gw.load_rom(rom);
+            }
             Box::new(gw)
         }),
     );
 }
+
+/// ROM named on the command line, if any
+fn rom_from_args() -> Option<Vec<u8>> {
+    let path = std::env::args().nth(1)?;
+    match std::fs::read(&path) {
+        Ok(rom) => Some(rom),
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            None
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::state::{Reader, StateError, Writer};
+
+/// Destination for the sound produced while the sound timer is running
+///
+/// Sinks receive mono 16-bit PCM at their own sample rate once per timer tick, silence
+/// included, so the samples form a continuous stream.
+pub trait AudioSink {
+    /// Samples per second expected by `queue_samples`
+    fn sample_rate(&self) -> u32;
+    /// Called whenever the buzzer starts or stops
+    fn set_buzzer(&mut self, _on: bool) {}
+    fn queue_samples(&mut self, samples: &[i16]);
+}
+
+/// Sample rate of recorded WAV files, the same with or without a window so that
+/// recordings of a replay compare bit-exactly
+pub const WAV_SAMPLE_RATE: u32 = 44_100;
+
+/// Sink that discards everything, used when no audio output is wanted
+#[derive(Default)]
+pub struct Silence;
+
+impl AudioSink for Silence {
+    fn sample_rate(&self) -> u32 {
+        0
+    }
+
+    fn queue_samples(&mut self, _samples: &[i16]) {}
+}
+
+/// Sink that keeps every sample in memory so it can be written out as a WAV file
+pub struct WavSink {
+    sample_rate: u32,
+    samples: Vec<i16>,
+}
+
+impl WavSink {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Everything queued so far
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    /// Write the samples as a mono 16-bit PCM WAV file
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let data_len = self.samples.len() as u32 * 2;
+        out.write_all(b"RIFF")?;
+        out.write_all(&(36 + data_len).to_le_bytes())?;
+        out.write_all(b"WAVEfmt ")?;
+        out.write_all(&16u32.to_le_bytes())?; // fmt chunk size
+        out.write_all(&1u16.to_le_bytes())?; // PCM
+        out.write_all(&1u16.to_le_bytes())?; // mono
+        out.write_all(&self.sample_rate.to_le_bytes())?;
+        out.write_all(&(self.sample_rate * 2).to_le_bytes())?; // byte rate
+        out.write_all(&2u16.to_le_bytes())?; // block align
+        out.write_all(&16u16.to_le_bytes())?; // bits per sample
+        out.write_all(b"data")?;
+        out.write_all(&data_len.to_le_bytes())?;
+        for sample in &self.samples {
+            out.write_all(&sample.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl AudioSink for WavSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queue_samples(&mut self, samples: &[i16]) {
+        self.samples.extend_from_slice(samples);
+    }
+}
+
+/// Plays XO-CHIP's 1-bit audio pattern while the sound timer is running
+///
+/// Programs that never load a pattern get a plain square wave.
+pub(crate) struct Buzzer {
+    on: bool,
+    /// 128 samples played most significant bit first, loaded by `F002`
+    pattern: [u8; 16],
+    /// Set by `FX3A`, 64 plays the pattern at 4000 bits per second
+    pitch: u8,
+    /// Position within the pattern in bits, as 32.32 fixed point so playback doesn't drift
+    phase: u64,
+    /// Sample rate remainder carried between ticks, so ticks don't drift at odd rates
+    remainder: u32,
+    buffer: Vec<i16>,
+}
+
+impl Buzzer {
+    /// 500 Hz square wave at the default pitch
+    const DEFAULT_PATTERN: [u8; 16] = [0xF0; 16];
+    const DEFAULT_PITCH: u8 = 64;
+    const AMPLITUDE: i16 = 0x2000;
+    const TICKS_PER_SECOND: u32 = 60;
+    const PATTERN_BITS: u64 = 128;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            on: false,
+            pattern: Self::DEFAULT_PATTERN,
+            pitch: Self::DEFAULT_PITCH,
+            phase: 0,
+            remainder: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub(crate) fn set_pattern(&mut self, pattern: [u8; 16]) {
+        self.pattern = pattern;
+    }
+
+    pub(crate) fn set_pitch(&mut self, pitch: u8) {
+        self.pitch = pitch;
+    }
+
+    pub(crate) fn save(&self, out: &mut Writer) {
+        out.u8(self.on as u8);
+        out.bytes(&self.pattern);
+        out.u8(self.pitch);
+        out.u64(self.phase);
+        out.u32(self.remainder);
+    }
+
+    pub(crate) fn load(input: &mut Reader) -> Result<Self, StateError> {
+        let buzzer = Self {
+            on: input.bool()?,
+            pattern: input.array()?,
+            pitch: input.u8()?,
+            phase: input.u64()?,
+            remainder: input.u32()?,
+            buffer: Vec::new(),
+        };
+        if buzzer.phase >= Self::PATTERN_BITS << 32 || buzzer.remainder >= Self::TICKS_PER_SECOND {
+            return Err(StateError::Malformed("audio position out of range"));
+        }
+        Ok(buzzer)
+    }
+
+    /// Pattern bits advanced per output sample, as 32.32 fixed point
+    fn step(&self, sample_rate: u32) -> u64 {
+        let bits_per_second = 4000.0 * 2f64.powf((self.pitch as f64 - 64.0) / 48.0);
+        (bits_per_second * (1u64 << 32) as f64 / sample_rate as f64).round() as u64
+    }
+
+    /// Produce one timer tick's worth of samples into `sink`
+    pub(crate) fn tick<A>(&mut self, sink: &mut A, on: bool)
+    where
+        A: AudioSink,
+    {
+        if on != self.on {
+            self.on = on;
+            sink.set_buzzer(on);
+        }
+
+        let rate = sink.sample_rate();
+        if rate == 0 {
+            return;
+        }
+        self.remainder += rate;
+        let count = self.remainder / Self::TICKS_PER_SECOND;
+        self.remainder %= Self::TICKS_PER_SECOND;
+
+        let step = self.step(rate);
+        self.buffer.clear();
+        for _ in 0..count {
+            if !on {
+                self.buffer.push(0);
+                continue;
+            }
+            let bit = (self.phase >> 32) as usize;
+            let sample = if self.pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                Self::AMPLITUDE
+            } else {
+                -Self::AMPLITUDE
+            };
+            self.buffer.push(sample);
+            self.phase = (self.phase + step) % (Self::PATTERN_BITS << 32);
+        }
+        sink.queue_samples(&self.buffer);
+    }
+}
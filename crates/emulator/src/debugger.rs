@@ -0,0 +1,453 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::audio::AudioSink;
+use crate::display::RenderTarget;
+use crate::instruction::Instruction;
+use crate::{Chip8, CpuState, EmulatorError};
+
+/// Kind of memory access made by an instruction
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Range of memory that pauses the program when accessed
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct Watchpoint {
+    pub addr: u16,
+    pub len: u16,
+    pub on_read: bool,
+    pub on_write: bool,
+}
+
+impl Watchpoint {
+    pub fn read(addr: u16, len: u16) -> Self {
+        Self {
+            addr,
+            len,
+            on_read: true,
+            on_write: false,
+        }
+    }
+
+    pub fn write(addr: u16, len: u16) -> Self {
+        Self {
+            addr,
+            len,
+            on_read: false,
+            on_write: true,
+        }
+    }
+
+    /// Pause on both reads and writes
+    pub fn access(addr: u16, len: u16) -> Self {
+        Self {
+            addr,
+            len,
+            on_read: true,
+            on_write: true,
+        }
+    }
+
+    /// First watched address in `range`, if `access` is watched
+    fn hit(&self, access: Access, range: &Range<usize>) -> Option<u16> {
+        let watched = match access {
+            Access::Read => self.on_read,
+            Access::Write => self.on_write,
+        };
+        let start = range.start.max(self.addr as usize);
+        let end = range.end.min(self.addr as usize + self.len as usize);
+        (watched && start < end).then_some(start as u16)
+    }
+}
+
+/// Machine register that conditions can watch
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Register {
+    V(u8),
+    I,
+    DelayTimer,
+    SoundTimer,
+}
+
+impl Register {
+    pub fn value<T, A>(self, emu: &Chip8<T, A>) -> u16
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        match self {
+            Self::V(reg) => emu.registers()[reg as usize & 0xF] as u16,
+            Self::I => emu.i(),
+            Self::DelayTimer => emu.delay_timer() as u16,
+            Self::SoundTimer => emu.sound_timer() as u16,
+        }
+    }
+}
+
+/// Register condition checked after every instruction
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Condition {
+    /// Pause whenever the register's value changes
+    Changes(Register),
+    /// Pause when the register takes on the value, having held a different one
+    Equals(Register, u16),
+}
+
+impl Condition {
+    pub fn register(&self) -> Register {
+        match *self {
+            Self::Changes(reg) | Self::Equals(reg, _) => reg,
+        }
+    }
+
+    fn met(&self, before: u16, after: u16) -> bool {
+        match *self {
+            Self::Changes(_) => before != after,
+            Self::Equals(_, val) => before != val && after == val,
+        }
+    }
+}
+
+/// Opcodes to pause on, matching those whose bits under `mask` equal `opcode`
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct OpcodeBreak {
+    pub opcode: u16,
+    pub mask: u16,
+}
+
+impl OpcodeBreak {
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.opcode & self.mask
+    }
+}
+
+pub struct InvalidPattern(String);
+
+impl fmt::Debug for InvalidPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid opcode pattern {}, expected four hex digits or wildcards such as DXYN",
+            self.0
+        )
+    }
+}
+
+impl fmt::Display for InvalidPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for OpcodeBreak {
+    type Err = InvalidPattern;
+
+    /// Parse a pattern such as `00E0` or `DXYN`, any letter past F being a wildcard
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidPattern(pattern.to_string());
+        if pattern.chars().count() != 4 {
+            return Err(invalid());
+        }
+
+        let mut res = Self { opcode: 0, mask: 0 };
+        for c in pattern.chars() {
+            res.opcode <<= 4;
+            res.mask <<= 4;
+            match c.to_digit(16) {
+                Some(digit) => {
+                    res.opcode |= digit as u16;
+                    res.mask |= 0xF;
+                }
+                None if c.is_ascii_alphabetic() || c == '_' => {}
+                None => return Err(invalid()),
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// Why the debugger paused the program
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Stop {
+    /// About to execute the instruction at a breakpoint
+    Breakpoint(u16),
+    /// About to execute an opcode matched by an opcode break
+    Opcode { pc: u16, opcode: u16 },
+    /// Instruction at `pc` accessed watched memory
+    Watchpoint { pc: u16, addr: u16, access: Access },
+    /// Instruction at `pc` met a register condition
+    Condition { pc: u16, condition: Condition },
+}
+
+/// Machine state as seen by a debugger, cheap to send to another thread
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Snapshot {
+    pub pc: u16,
+    pub i: u16,
+    pub registers: [u8; 16],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    /// Return addresses, innermost last
+    pub stack: Vec<u16>,
+    pub state: CpuState,
+}
+
+impl Snapshot {
+    pub fn of<T, A>(emu: &Chip8<T, A>) -> Self
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        Self {
+            pc: emu.pc(),
+            i: emu.i(),
+            registers: emu.registers(),
+            delay_timer: emu.delay_timer(),
+            sound_timer: emu.sound_timer(),
+            stack: emu.stack().to_vec(),
+            state: emu.state(),
+        }
+    }
+}
+
+/// Runs a `Chip8` an instruction at a time, pausing it on breakpoints, watchpoints,
+/// register conditions and opcodes
+///
+/// Breakpoints and opcode breaks pause before the instruction runs, watchpoints and
+/// conditions right after. Frames are still counted so timers run at the same rate as
+/// with `Chip8::run_frame`.
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+    conditions: Vec<Condition>,
+    opcode_breaks: Vec<OpcodeBreak>,
+    /// Instructions run so far in the current frame
+    cycle: usize,
+    /// Address just paused at before executing, which mustn't pause again on resuming
+    resume_at: Option<u16>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a breakpoint, returning false if there already was one at `addr`
+    pub fn add_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.insert(addr)
+    }
+
+    /// Remove a breakpoint, returning false if there wasn't one at `addr`
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    pub fn remove_watchpoint(&mut self, idx: usize) -> Option<Watchpoint> {
+        (idx < self.watchpoints.len()).then(|| self.watchpoints.remove(idx))
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn add_condition(&mut self, condition: Condition) {
+        self.conditions.push(condition);
+    }
+
+    pub fn remove_condition(&mut self, idx: usize) -> Option<Condition> {
+        (idx < self.conditions.len()).then(|| self.conditions.remove(idx))
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    pub fn add_opcode_break(&mut self, opcode_break: OpcodeBreak) {
+        self.opcode_breaks.push(opcode_break);
+    }
+
+    pub fn remove_opcode_break(&mut self, idx: usize) -> Option<OpcodeBreak> {
+        (idx < self.opcode_breaks.len()).then(|| self.opcode_breaks.remove(idx))
+    }
+
+    pub fn opcode_breaks(&self) -> &[OpcodeBreak] {
+        &self.opcode_breaks
+    }
+
+    /// Breakpoint or opcode break on the instruction about to run, for callers that step
+    /// one instruction at a time and decide for themselves when to stop
+    pub fn stop_at<T, A>(&self, emu: &Chip8<T, A>) -> Option<Stop>
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        let pc = emu.pc();
+        if self.breakpoints.contains(&pc) {
+            return Some(Stop::Breakpoint(pc));
+        }
+        let opcode = opcode_at(emu, pc)?;
+        self.opcode_breaks
+            .iter()
+            .any(|opcode_break| opcode_break.matches(opcode))
+            .then_some(Stop::Opcode { pc, opcode })
+    }
+
+    /// Execute the next instruction whatever breakpoint it's at, finishing the frame if
+    /// it was the frame's last
+    ///
+    /// Does nothing while the program is halted or waiting for a key.
+    pub fn step<T, A>(&mut self, emu: &mut Chip8<T, A>) -> Result<Option<Stop>, EmulatorError>
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        if emu.state() != CpuState::Running {
+            return Ok(None);
+        }
+
+        let stop = self.execute(emu)?;
+        if self.cycle >= emu.cycles_per_frame() || emu.state() != CpuState::Running {
+            self.end_frame(emu);
+        }
+        Ok(stop)
+    }
+
+    /// Run the rest of the current frame, returning early if the program is paused
+    ///
+    /// Call again to resume from where it paused. Returns `None` once the frame is over.
+    pub fn run_frame<T, A>(&mut self, emu: &mut Chip8<T, A>) -> Result<Option<Stop>, EmulatorError>
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        while self.cycle < emu.cycles_per_frame() && emu.state() == CpuState::Running {
+            if let Some(stop) = self.check_before(emu) {
+                return Ok(Some(stop));
+            }
+            if let Some(stop) = self.execute(emu)? {
+                return Ok(Some(stop));
+            }
+        }
+        self.end_frame(emu);
+        Ok(None)
+    }
+
+    fn end_frame<T, A>(&mut self, emu: &mut Chip8<T, A>)
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        self.cycle = 0;
+        emu.end_frame();
+    }
+
+    /// Check breakpoints and opcode breaks on the instruction about to run
+    fn check_before<T, A>(&mut self, emu: &Chip8<T, A>) -> Option<Stop>
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        let pc = emu.pc();
+        if self.resume_at.take() == Some(pc) {
+            return None;
+        }
+
+        let stop = self.stop_at(emu);
+        if stop.is_some() {
+            self.resume_at = Some(pc);
+        }
+        stop
+    }
+
+    /// Run one instruction, checking watchpoints and conditions against what it did
+    fn execute<T, A>(&mut self, emu: &mut Chip8<T, A>) -> Result<Option<Stop>, EmulatorError>
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        let pc = emu.pc();
+        let accesses = emu
+            .memory()
+            .get(pc as usize..)
+            .and_then(|bytes| Instruction::decode(bytes).ok())
+            .map_or_else(Vec::new, |instruction| accesses(emu, instruction));
+        let before: Vec<u16> = self
+            .conditions
+            .iter()
+            .map(|condition| condition.register().value(emu))
+            .collect();
+
+        emu.step()?;
+        self.cycle += 1;
+        self.resume_at = None;
+
+        for (access, range) in accesses {
+            if let Some(addr) = self
+                .watchpoints
+                .iter()
+                .find_map(|watchpoint| watchpoint.hit(access, &range))
+            {
+                return Ok(Some(Stop::Watchpoint { pc, addr, access }));
+            }
+        }
+        for (condition, before) in self.conditions.iter().zip(before) {
+            if condition.met(before, condition.register().value(emu)) {
+                let condition = *condition;
+                return Ok(Some(Stop::Condition { pc, condition }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// First word of the instruction at `pc`
+fn opcode_at<T, A>(emu: &Chip8<T, A>, pc: u16) -> Option<u16>
+where
+    T: RenderTarget,
+    A: AudioSink,
+{
+    match emu.memory().get(pc as usize..pc as usize + 2)? {
+        &[hi, lo] => Some((hi as u16) << 8 | lo as u16),
+        _ => None,
+    }
+}
+
+/// Memory `instruction` will read or write when run on `emu`
+fn accesses<T, A>(emu: &Chip8<T, A>, instruction: Instruction) -> Vec<(Access, Range<usize>)>
+where
+    T: RenderTarget,
+    A: AudioSink,
+{
+    use Instruction::*;
+
+    let at_i = |len: usize| emu.i() as usize..emu.i() as usize + len;
+    let span = |reg1: u8, reg2: u8| (reg1 as i8 - reg2 as i8).unsigned_abs() as usize + 1;
+    match instruction {
+        DrawSprite(_, _, size) => {
+            let len = if size == 0 { 32 } else { size as usize };
+            let planes = emu.display.planes().count_ones() as usize;
+            vec![(Access::Read, at_i(len * planes))]
+        }
+        StoreNumberFromRegToI(_) => vec![(Access::Write, at_i(3))],
+        StoreRegsToMem(max_reg) => vec![(Access::Write, at_i(max_reg as usize + 1))],
+        LoadRegsFromMem(max_reg) => vec![(Access::Read, at_i(max_reg as usize + 1))],
+        StoreRegRange(reg1, reg2) => vec![(Access::Write, at_i(span(reg1, reg2)))],
+        LoadRegRange(reg1, reg2) => vec![(Access::Read, at_i(span(reg1, reg2)))],
+        LoadAudioPattern => vec![(Access::Read, at_i(16))],
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,236 @@
+use std::fmt;
+
+use crate::instruction::Instruction;
+
+/// Assembly dialect used when rendering instructions
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Syntax {
+    /// Mnemonics from Cowgod's Chip-8 technical reference, e.g. `LD V1, #0A`
+    Cowgod,
+    /// Octo assembly language, e.g. `v1 := 0x0A`
+    Octo,
+}
+
+impl Instruction {
+    /// Render the instruction as a single line of assembly
+    pub fn mnemonic(&self, syntax: Syntax) -> String {
+        match syntax {
+            Syntax::Cowgod => self.cowgod(),
+            Syntax::Octo => self.octo(),
+        }
+    }
+
+    fn cowgod(&self) -> String {
+        use Instruction::*;
+
+        match *self {
+            ClearDisplay => "CLS".to_string(),
+            ReturnFromSubroutine => "RET".to_string(),
+            Jump(addr) => format!("JP #{:03X}", addr),
+            CallSubroutine(addr) => format!("CALL #{:03X}", addr),
+            SkipRegEqByte(reg, val) => format!("SE V{:X}, #{:02X}", reg, val),
+            SkipRegNotEqByte(reg, val) => format!("SNE V{:X}, #{:02X}", reg, val),
+            SkipRegEqReg(reg1, reg2) => format!("SE V{:X}, V{:X}", reg1, reg2),
+            SetRegToByte(reg, val) => format!("LD V{:X}, #{:02X}", reg, val),
+            AddByteToReg(reg, val) => format!("ADD V{:X}, #{:02X}", reg, val),
+            MoveValue(reg1, reg2) => format!("LD V{:X}, V{:X}", reg1, reg2),
+            OrRegs(reg1, reg2) => format!("OR V{:X}, V{:X}", reg1, reg2),
+            AndRegs(reg1, reg2) => format!("AND V{:X}, V{:X}", reg1, reg2),
+            XorRegs(reg1, reg2) => format!("XOR V{:X}, V{:X}", reg1, reg2),
+            AddRegs(reg1, reg2) => format!("ADD V{:X}, V{:X}", reg1, reg2),
+            SubRegs(reg1, reg2) => format!("SUB V{:X}, V{:X}", reg1, reg2),
+            ShiftRight(reg1, reg2) => format!("SHR V{:X}, V{:X}", reg1, reg2),
+            ReverseSubRegs(reg1, reg2) => format!("SUBN V{:X}, V{:X}", reg1, reg2),
+            ShiftLeft(reg1, reg2) => format!("SHL V{:X}, V{:X}", reg1, reg2),
+            SkipRegNotEqReg(reg1, reg2) => format!("SNE V{:X}, V{:X}", reg1, reg2),
+            SetI(addr) => format!("LD I, #{:03X}", addr),
+            JumpV0PlusByte(addr) => format!("JP V0, #{:03X}", addr),
+            SetRegToRandPlusByte(reg, val) => format!("RND V{:X}, #{:02X}", reg, val),
+            DrawSprite(reg_x, reg_y, size) => {
+                format!("DRW V{:X}, V{:X}, #{:X}", reg_x, reg_y, size)
+            }
+            SkipIfKey(reg) => format!("SKP V{:X}", reg),
+            SkipIfNotKey(reg) => format!("SKNP V{:X}", reg),
+            LoadDelayToReg(reg) => format!("LD V{:X}, DT", reg),
+            LoadKeyToReg(reg) => format!("LD V{:X}, K", reg),
+            SetDelayToReg(reg) => format!("LD DT, V{:X}", reg),
+            SetSoundToReg(reg) => format!("LD ST, V{:X}", reg),
+            AddRegToI(reg) => format!("ADD I, V{:X}", reg),
+            SetIToDigitSpriteLoc(reg) => format!("LD F, V{:X}", reg),
+            StoreNumberFromRegToI(reg) => format!("LD B, V{:X}", reg),
+            StoreRegsToMem(reg) => format!("LD [I], V{:X}", reg),
+            LoadRegsFromMem(reg) => format!("LD V{:X}, [I]", reg),
+            ScrollDown(rows) => format!("SCD #{:X}", rows),
+            ScrollRight => "SCR".to_string(),
+            ScrollLeft => "SCL".to_string(),
+            Exit => "EXIT".to_string(),
+            LowResolution => "LOW".to_string(),
+            HighResolution => "HIGH".to_string(),
+            SetIToBigDigitSpriteLoc(reg) => format!("LD HF, V{:X}", reg),
+            StoreRegsToFlags(reg) => format!("LD R, V{:X}", reg),
+            LoadRegsFromFlags(reg) => format!("LD V{:X}, R", reg),
+            StoreRegRange(reg1, reg2) => format!("SAVE V{:X}, V{:X}", reg1, reg2),
+            LoadRegRange(reg1, reg2) => format!("LOAD V{:X}, V{:X}", reg1, reg2),
+            ScrollUp(rows) => format!("SCU #{:X}", rows),
+            SetILong(addr) => format!("LD I, LONG #{:04X}", addr),
+            SelectPlanes(planes) => format!("PLANE #{:X}", planes),
+            LoadAudioPattern => "AUDIO".to_string(),
+            SetPitchToReg(reg) => format!("PITCH V{:X}", reg),
+        }
+    }
+
+    fn octo(&self) -> String {
+        use Instruction::*;
+
+        // Octo expresses skips as conditionals guarding the next instruction, so the
+        // comparison is the inverse of the skip condition.
+        match *self {
+            ClearDisplay => "clear".to_string(),
+            ReturnFromSubroutine => "return".to_string(),
+            Jump(addr) => format!("jump 0x{:03X}", addr),
+            CallSubroutine(addr) => format!(":call 0x{:03X}", addr),
+            SkipRegEqByte(reg, val) => format!("if v{:X} != 0x{:02X} then", reg, val),
+            SkipRegNotEqByte(reg, val) => format!("if v{:X} == 0x{:02X} then", reg, val),
+            SkipRegEqReg(reg1, reg2) => format!("if v{:X} != v{:X} then", reg1, reg2),
+            SetRegToByte(reg, val) => format!("v{:X} := 0x{:02X}", reg, val),
+            AddByteToReg(reg, val) => format!("v{:X} += 0x{:02X}", reg, val),
+            MoveValue(reg1, reg2) => format!("v{:X} := v{:X}", reg1, reg2),
+            OrRegs(reg1, reg2) => format!("v{:X} |= v{:X}", reg1, reg2),
+            AndRegs(reg1, reg2) => format!("v{:X} &= v{:X}", reg1, reg2),
+            XorRegs(reg1, reg2) => format!("v{:X} ^= v{:X}", reg1, reg2),
+            AddRegs(reg1, reg2) => format!("v{:X} += v{:X}", reg1, reg2),
+            SubRegs(reg1, reg2) => format!("v{:X} -= v{:X}", reg1, reg2),
+            ShiftRight(reg1, reg2) => format!("v{:X} >>= v{:X}", reg1, reg2),
+            ReverseSubRegs(reg1, reg2) => format!("v{:X} =- v{:X}", reg1, reg2),
+            ShiftLeft(reg1, reg2) => format!("v{:X} <<= v{:X}", reg1, reg2),
+            SkipRegNotEqReg(reg1, reg2) => format!("if v{:X} == v{:X} then", reg1, reg2),
+            SetI(addr) => format!("i := 0x{:03X}", addr),
+            JumpV0PlusByte(addr) => format!("jump0 0x{:03X}", addr),
+            SetRegToRandPlusByte(reg, val) => format!("v{:X} := random 0x{:02X}", reg, val),
+            DrawSprite(reg_x, reg_y, size) => {
+                format!("sprite v{:X} v{:X} 0x{:X}", reg_x, reg_y, size)
+            }
+            SkipIfKey(reg) => format!("if v{:X} -key then", reg),
+            SkipIfNotKey(reg) => format!("if v{:X} key then", reg),
+            LoadDelayToReg(reg) => format!("v{:X} := delay", reg),
+            LoadKeyToReg(reg) => format!("v{:X} := key", reg),
+            SetDelayToReg(reg) => format!("delay := v{:X}", reg),
+            SetSoundToReg(reg) => format!("buzzer := v{:X}", reg),
+            AddRegToI(reg) => format!("i += v{:X}", reg),
+            SetIToDigitSpriteLoc(reg) => format!("i := hex v{:X}", reg),
+            StoreNumberFromRegToI(reg) => format!("bcd v{:X}", reg),
+            StoreRegsToMem(reg) => format!("save v{:X}", reg),
+            LoadRegsFromMem(reg) => format!("load v{:X}", reg),
+            ScrollDown(rows) => format!("scroll-down 0x{:X}", rows),
+            ScrollRight => "scroll-right".to_string(),
+            ScrollLeft => "scroll-left".to_string(),
+            Exit => "exit".to_string(),
+            LowResolution => "lores".to_string(),
+            HighResolution => "hires".to_string(),
+            SetIToBigDigitSpriteLoc(reg) => format!("i := bighex v{:X}", reg),
+            StoreRegsToFlags(reg) => format!("saveflags v{:X}", reg),
+            LoadRegsFromFlags(reg) => format!("loadflags v{:X}", reg),
+            StoreRegRange(reg1, reg2) => format!("save v{:X} - v{:X}", reg1, reg2),
+            LoadRegRange(reg1, reg2) => format!("load v{:X} - v{:X}", reg1, reg2),
+            ScrollUp(rows) => format!("scroll-up 0x{:X}", rows),
+            SetILong(addr) => format!("i := long 0x{:04X}", addr),
+            SelectPlanes(planes) => format!("plane 0x{:X}", planes),
+            LoadAudioPattern => "audio".to_string(),
+            SetPitchToReg(reg) => format!("pitch := v{:X}", reg),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mnemonic(Syntax::Cowgod))
+    }
+}
+
+/// A single line of disassembled ROM
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Line {
+    /// Word that decodes to a valid instruction
+    Code {
+        addr: u16,
+        opcode: u16,
+        instruction: Instruction,
+    },
+    /// Bytes that don't decode, most likely sprites or other data
+    Data { addr: u16, bytes: Vec<u8> },
+}
+
+impl Line {
+    /// Address the line was loaded at
+    pub fn addr(&self) -> u16 {
+        match self {
+            Self::Code { addr, .. } | Self::Data { addr, .. } => *addr,
+        }
+    }
+
+    /// Render the line with its address and raw bytes, e.g. `200: 00E0  CLS`
+    pub fn render(&self, syntax: Syntax) -> String {
+        match self {
+            Self::Code {
+                addr,
+                opcode,
+                instruction,
+            } => format!(
+                "{:03X}: {:04X}  {}",
+                addr,
+                opcode,
+                instruction.mnemonic(syntax)
+            ),
+            Self::Data { addr, bytes } => {
+                let raw: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                let data = match syntax {
+                    Syntax::Cowgod => {
+                        let bytes: Vec<String> =
+                            bytes.iter().map(|byte| format!("#{:02X}", byte)).collect();
+                        format!("DB {}", bytes.join(", "))
+                    }
+                    Syntax::Octo => {
+                        let bytes: Vec<String> =
+                            bytes.iter().map(|byte| format!("0x{:02X}", byte)).collect();
+                        bytes.join(" ")
+                    }
+                };
+                format!("{:03X}: {:<4}  {}", addr, raw, data)
+            }
+        }
+    }
+}
+
+/// Disassemble a ROM loaded at `origin` using a linear sweep
+///
+/// Every word that decodes is treated as code; anything else, including a trailing odd
+/// byte, is emitted as data.
+pub fn disassemble(rom: &[u8], origin: u16) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+
+    while offset < rom.len() {
+        let addr = origin.wrapping_add(offset as u16);
+        let bytes = &rom[offset..];
+        match Instruction::decode(bytes) {
+            Ok(instruction) => {
+                lines.push(Line::Code {
+                    addr,
+                    opcode: u16::from(instruction),
+                    instruction,
+                });
+                offset += instruction.size();
+            }
+            Err(_) => {
+                let len = bytes.len().min(2);
+                lines.push(Line::Data {
+                    addr,
+                    bytes: bytes[..len].to_vec(),
+                });
+                offset += len;
+            }
+        }
+    }
+
+    lines
+}
@@ -1,6 +1,27 @@
+/// Color of a pixel, named after the default palette for each combination of XO-CHIP
+/// bitplanes
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub enum Color {
+    /// Neither plane set
     Black,
+    /// First plane only, the only color used by games that stick to a single plane
     White,
+    /// Second plane only
+    LightGray,
+    /// Both planes set
+    DarkGray,
+}
+
+impl Color {
+    /// Color of a framebuffer entry, see `Display::framebuffer`
+    pub fn from_planes(pixel: u8) -> Self {
+        match pixel & 0b11 {
+            0b00 => Self::Black,
+            0b01 => Self::White,
+            0b10 => Self::LightGray,
+            _ => Self::DarkGray,
+        }
+    }
 }
 
 pub trait RenderTarget {
@@ -8,6 +29,43 @@ pub trait RenderTarget {
     fn size(&self) -> (usize, usize);
     fn fill_rect(&mut self, x: usize, y: usize, w: usize, h: usize, color: Color);
     fn present(&mut self);
+
+    /// Called at the end of every frame, after `present` if the screen changed
+    fn end_frame(&mut self) {}
+
+    /// Draw a whole `cols` by `rows` frame in the format of `Display::framebuffer`
+    ///
+    /// The default scales each pixel up with `fill_rect`. Targets that can copy the frame
+    /// in bulk should override it.
+    fn blit_frame(&mut self, frame: &[u8], cols: usize, rows: usize) {
+        self.clear();
+
+        let (width, height) = self.size();
+        let pixel_width = width / cols;
+        let pixel_height = height / rows;
+
+        for y in 0..rows {
+            for x in 0..cols {
+                self.fill_rect(
+                    x * pixel_width,
+                    y * pixel_height,
+                    pixel_width,
+                    pixel_height,
+                    Color::from_planes(frame[y * cols + x]),
+                );
+            }
+        }
+    }
+}
+
+/// 64-bit FNV-1a, stable across platforms and releases
+pub(crate) fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    hash
 }
 
 pub struct Display<T>
@@ -15,95 +73,354 @@ where
     T: RenderTarget,
 {
     pub target: T,
-    pixels: [bool; 64 * 32],
+    /// One bit per bitplane for each pixel
+    pixels: Vec<u8>,
+    hires: bool,
+    /// Bitplanes affected by drawing, clearing and scrolling
+    planes: u8,
+    /// Whether pixels have changed since the last render
+    dirty: bool,
 }
 
 impl<T> Display<T>
 where
     T: RenderTarget,
 {
-    const ROWS: u16 = 32;
-    const COLS: u16 = 64;
+    pub(crate) const LORES: (usize, usize) = (64, 32);
+    pub(crate) const HIRES: (usize, usize) = (128, 64);
+    /// XO-CHIP bitplanes, one bit of every pixel each
+    pub(crate) const PLANES: u32 = 2;
 
     pub fn new(target: T) -> Self {
+        let (cols, rows) = Self::LORES;
         Self {
             target,
-            pixels: [false; (Self::COLS * Self::ROWS) as usize],
+            pixels: vec![0; cols * rows],
+            hires: false,
+            planes: 0b01,
+            dirty: true,
+        }
+    }
+
+    /// Current resolution as (columns, rows)
+    pub fn size(&self) -> (usize, usize) {
+        if self.hires {
+            Self::HIRES
+        } else {
+            Self::LORES
+        }
+    }
+
+    /// Whether the SUPER-CHIP 128x64 mode is active
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Switch between 64x32 and 128x64 modes, clearing the screen
+    pub(crate) fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        let (cols, rows) = self.size();
+        self.pixels = vec![0; cols * rows];
+        self.dirty = true;
+    }
+
+    /// Bitplanes currently selected with XO-CHIP's `FN01`
+    pub fn planes(&self) -> u8 {
+        self.planes
+    }
+
+    pub(crate) fn set_planes(&mut self, planes: u8) {
+        self.planes = planes & 0b11;
+    }
+
+    /// Every pixel on screen, row by row at the current resolution
+    ///
+    /// Each entry holds one bit per XO-CHIP bitplane, see `Color::from_planes`.
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Replace the whole screen, `pixels` being sized for the given resolution
+    pub(crate) fn restore(&mut self, hires: bool, planes: u8, pixels: Vec<u8>) {
+        self.hires = hires;
+        self.planes = planes & 0b11;
+        self.pixels = pixels;
+        self.dirty = true;
+    }
+
+    /// Stable 64-bit FNV-1a hash of the resolution and every pixel on screen
+    pub fn hash(&self) -> u64 {
+        let pixels = self.pixels.iter().copied();
+        fnv1a([self.hires as u8].into_iter().chain(pixels))
+    }
+
+    /// Blits a sprite `width` pixels wide to location (x, y), returning true if any pixels
+    /// were overwritten
+    ///
+    /// Each row of the sprite is `width / 8` bytes. With more than one plane selected,
+    /// `sprite` holds the data for each plane in turn. The starting position always wraps
+    /// around the screen. When `wrap` is set, pixels past the edge of the screen reappear on
+    /// the opposite side, otherwise they are clipped and can't collide.
+    pub(crate) fn blit_sprite(
+        &mut self,
+        x: u8,
+        y: u8,
+        sprite: &[u8],
+        width: usize,
+        wrap: bool,
+    ) -> bool {
+        let selected = self.planes;
+        let planes = [0b01, 0b10]
+            .into_iter()
+            .filter(|plane| selected & plane != 0);
+        let plane_len = sprite.len() / self.planes.count_ones().max(1) as usize;
+
+        let mut collision = false;
+        for (plane, data) in planes.zip(sprite.chunks(plane_len.max(1))) {
+            collision |= self.blit_plane(x, y, data, width, wrap, plane);
         }
+        collision
     }
 
-    /// Blits a sprite to location (x, y), returning true if any pixels were overwritten
-    pub(crate) fn blit_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
+    fn blit_plane(
+        &mut self,
+        x: u8,
+        y: u8,
+        sprite: &[u8],
+        width: usize,
+        wrap: bool,
+        plane: u8,
+    ) -> bool {
+        let (cols, rows) = self.size();
+        let (x, y) = (x as usize % cols, y as usize % rows);
         let mut collision = false;
-        for (idx, row) in sprite.iter().enumerate() {
-            let pixel_idx = x as usize + (Self::COLS * (y as u16 + idx as u16)) as usize;
-            if pixel_idx + 7 > self.pixels.len() {
+        for (idx, row) in sprite.chunks(width / 8).enumerate() {
+            let mut pixel_y = y + idx;
+            if wrap {
+                pixel_y %= rows;
+            } else if pixel_y >= rows {
                 continue;
             }
 
-            let existing = pixels_to_byte(&self.pixels[pixel_idx..pixel_idx + 8]);
-
-            self.pixels[pixel_idx..pixel_idx + 8].clone_from_slice(&byte_to_pixels(row ^ existing));
+            for bit in 0..width {
+                if row[bit / 8] & (0x80 >> (bit % 8)) == 0 {
+                    continue;
+                }
+                let mut pixel_x = x + bit;
+                if wrap {
+                    pixel_x %= cols;
+                } else if pixel_x >= cols {
+                    continue;
+                }
 
-            let collide = (row & existing) != 0;
-            collision = collision || collide;
+                let pixel = &mut self.pixels[pixel_y * cols + pixel_x];
+                collision = collision || *pixel & plane != 0;
+                *pixel ^= plane;
+                self.dirty = true;
+            }
         }
         collision
     }
 
-    /// Reset display to blank state
+    /// Shift the selected planes by (dx, dy) pixels, blanking the pixels scrolled in
+    pub(crate) fn scroll(&mut self, dx: isize, dy: isize) {
+        let (cols, rows) = self.size();
+        let mut pixels: Vec<u8> = self.pixels.iter().map(|px| px & !self.planes).collect();
+        for y in 0..rows {
+            for x in 0..cols {
+                let src_x = x as isize - dx;
+                let src_y = y as isize - dy;
+                if (0..cols as isize).contains(&src_x) && (0..rows as isize).contains(&src_y) {
+                    let src = self.pixels[src_y as usize * cols + src_x as usize];
+                    pixels[y * cols + x] |= src & self.planes;
+                }
+            }
+        }
+        self.pixels = pixels;
+        self.dirty = true;
+    }
+
+    /// Reset the selected planes to blank state
     pub(crate) fn clear(&mut self) {
-        self.pixels = [false; 64 * 32];
+        for pixel in self.pixels.iter_mut() {
+            *pixel &= !self.planes;
+        }
+        self.dirty = true;
     }
 
-    /// Render current pixel buffer to screen
+    /// Render current pixel buffer to screen, unless nothing changed since last time
     pub(crate) fn render(&mut self) {
-        self.target.clear();
-
-        let (width, height) = self.target.size();
-        let pixel_width = width / Self::COLS as usize;
-        let pixel_height = height / Self::ROWS as usize;
-
-        for y in 0..Self::ROWS {
-            for x in 0..Self::COLS {
-                let draw_color = if self.pixels[(y * Self::COLS + x) as usize] {
-                    Color::White
-                } else {
-                    Color::Black
-                };
-                self.target.fill_rect(
-                    x as usize * pixel_width as usize,
-                    y as usize * pixel_height as usize,
-                    pixel_width,
-                    pixel_height,
-                    draw_color,
-                );
-            }
+        if !self.dirty {
+            return;
         }
+        self.dirty = false;
 
+        let (cols, rows) = self.size();
+        self.target.blit_frame(&self.pixels, cols, rows);
         self.target.present();
     }
+
+    /// Render and tell the target the frame is over, whether or not anything changed
+    pub(crate) fn end_frame(&mut self) {
+        self.render();
+        self.target.end_frame();
+    }
 }
 
-fn pixels_to_byte(pixels: &[bool]) -> u8 {
-    let mut byte = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::HeadlessTarget;
 
-    for pixel in pixels {
-        byte <<= 1;
-        byte += if *pixel { 1 } else { 0 }
+    const ROW: [u8; 1] = [0xFF];
+    const BLOCK: [u8; 4] = [0xF0, 0xF0, 0xF0, 0xF0];
+
+    fn display() -> Display<HeadlessTarget> {
+        Display::new(HeadlessTarget::new())
     }
 
-    byte
-}
+    fn lit(display: &Display<HeadlessTarget>, x: usize, y: usize) -> bool {
+        let (cols, _) = display.size();
+        display.pixels[y * cols + x] & 0b01 != 0
+    }
+
+    fn lit_count(display: &Display<HeadlessTarget>) -> usize {
+        display.pixels.iter().filter(|&&pixel| pixel != 0).count()
+    }
+
+    #[test]
+    fn draws_inside_the_screen() {
+        let mut display = display();
+        assert!(!display.blit_sprite(10, 5, &BLOCK, 8, false));
+        for y in 5..9 {
+            for x in 10..14 {
+                assert!(lit(&display, x, y), "({}, {})", x, y);
+            }
+        }
+        assert_eq!(lit_count(&display), 16);
+    }
+
+    #[test]
+    fn start_coordinates_wrap_in_both_modes() {
+        for wrap in [false, true] {
+            let mut display = display();
+            display.blit_sprite(64 + 3, 32 + 2, &ROW, 8, wrap);
+            for x in 3..11 {
+                assert!(lit(&display, x, 2), "wrap {}: ({}, 2)", wrap, x);
+            }
+            assert_eq!(lit_count(&display), 8);
+        }
+    }
 
-fn byte_to_pixels(byte: u8) -> [bool; 8] {
-    let mut byte = byte;
-    let mut pixels = [false; 8];
+    #[test]
+    fn clips_at_right_edge_without_bleeding_into_next_row() {
+        let mut display = display();
+        display.blit_sprite(60, 4, &ROW, 8, false);
+        for x in 60..64 {
+            assert!(lit(&display, x, 4));
+        }
+        assert_eq!(lit_count(&display), 4);
+    }
+
+    #[test]
+    fn wraps_at_right_edge_onto_same_row() {
+        let mut display = display();
+        display.blit_sprite(60, 4, &ROW, 8, true);
+        for x in (60..64).chain(0..4) {
+            assert!(lit(&display, x, 4), "({}, 4)", x);
+        }
+        assert_eq!(lit_count(&display), 8);
+    }
+
+    #[test]
+    fn clips_at_bottom_edge() {
+        let mut display = display();
+        display.blit_sprite(0, 30, &BLOCK, 8, false);
+        assert!(lit(&display, 0, 30));
+        assert!(lit(&display, 0, 31));
+        assert!(!lit(&display, 0, 0));
+        assert_eq!(lit_count(&display), 8);
+    }
+
+    #[test]
+    fn wraps_at_bottom_edge() {
+        let mut display = display();
+        display.blit_sprite(0, 30, &BLOCK, 8, true);
+        for y in [30, 31, 0, 1] {
+            assert!(lit(&display, 0, y), "(0, {})", y);
+        }
+        assert_eq!(lit_count(&display), 16);
+    }
+
+    #[test]
+    fn bottom_right_corner() {
+        let mut clipped = display();
+        clipped.blit_sprite(62, 31, &BLOCK, 8, false);
+        assert!(lit(&clipped, 62, 31));
+        assert!(lit(&clipped, 63, 31));
+        assert_eq!(lit_count(&clipped), 2);
 
-    for i in 0..8 {
-        pixels[7 - i] = (byte & 0x1) != 0;
-        byte >>= 1;
+        let mut wrapped = display();
+        wrapped.blit_sprite(62, 31, &BLOCK, 8, true);
+        for (x, y) in [(62, 31), (63, 31), (0, 31), (1, 31), (62, 0), (1, 2)] {
+            assert!(lit(&wrapped, x, y), "({}, {})", x, y);
+        }
+        assert_eq!(lit_count(&wrapped), 16);
+    }
+
+    #[test]
+    fn collision_is_reported_and_pixels_are_erased() {
+        let mut display = display();
+        assert!(!display.blit_sprite(20, 10, &BLOCK, 8, false));
+        assert!(display.blit_sprite(22, 12, &BLOCK, 8, false));
+        assert!(!lit(&display, 22, 12));
+        assert!(lit(&display, 20, 10));
+        assert!(lit(&display, 25, 15));
     }
 
-    pixels
+    #[test]
+    fn clipped_pixels_never_collide() {
+        let mut display = display();
+        display.blit_sprite(0, 4, &ROW, 8, false);
+        // The right half would land on the pixels drawn above if it wrapped
+        assert!(!display.blit_sprite(60, 4, &ROW, 8, false));
+        assert!(display.blit_sprite(60, 4, &ROW, 8, true));
+    }
+
+    #[test]
+    fn hires_large_sprite_wraps_at_corner() {
+        let sprite = [0xFF; 32];
+        let mut wrapped = display();
+        wrapped.set_hires(true);
+        wrapped.blit_sprite(120, 60, &sprite, 16, true);
+        for (x, y) in [(120, 60), (127, 63), (0, 0), (7, 11)] {
+            assert!(lit(&wrapped, x, y), "({}, {})", x, y);
+        }
+        assert_eq!(lit_count(&wrapped), 256);
+
+        let mut clipped = display();
+        clipped.set_hires(true);
+        clipped.blit_sprite(120, 60, &sprite, 16, false);
+        assert_eq!(lit_count(&clipped), 8 * 4);
+    }
+
+    #[test]
+    fn hashes_every_frame_even_when_unchanged() {
+        let mut display = display();
+        display.end_frame();
+        display.blit_sprite(10, 5, &BLOCK, 8, false);
+        display.end_frame();
+        display.end_frame();
+        // Rendering between frames updates the hash without counting as a frame
+        display.clear();
+        display.render();
+        display.end_frame();
+
+        let hashes = display.target.frame_hashes();
+        assert_eq!(hashes.len(), 4);
+        assert_ne!(hashes[0], hashes[1]);
+        assert_eq!(hashes[1], hashes[2]);
+        assert_eq!(hashes[3], hashes[0]);
+        assert_eq!(display.target.hash(), Some(hashes[3]));
+    }
 }
@@ -0,0 +1,109 @@
+use crate::display::{fnv1a, Color, RenderTarget};
+
+/// Render target that keeps the screen in memory instead of showing it
+///
+/// The framebuffer always matches the emulated resolution, one entry per pixel, and a hash
+/// of the screen at the end of every frame is kept so tests can assert on what was on
+/// screen. Frames are only presented when the screen changes, so the hash of a frame that
+/// changed nothing repeats the one before it.
+pub struct HeadlessTarget {
+    cols: usize,
+    rows: usize,
+    back: Vec<Color>,
+    front: Vec<Color>,
+    /// Hash of the last presented frame
+    hash: Option<u64>,
+    hashes: Vec<u64>,
+}
+
+impl HeadlessTarget {
+    pub fn new() -> Self {
+        Self {
+            cols: 0,
+            rows: 0,
+            back: Vec::new(),
+            front: Vec::new(),
+            hash: None,
+            hashes: Vec::new(),
+        }
+    }
+
+    /// Resolution of the last presented frame as (columns, rows)
+    pub fn resolution(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    /// Last presented frame, row by row
+    pub fn framebuffer(&self) -> &[Color] {
+        &self.front
+    }
+
+    /// Color of pixel (x, y) in the last presented frame
+    pub fn pixel(&self, x: usize, y: usize) -> Option<Color> {
+        if x >= self.cols || y >= self.rows {
+            return None;
+        }
+        self.front.get(y * self.cols + x).copied()
+    }
+
+    /// Hash of the screen at the end of each frame run so far, oldest first
+    ///
+    /// Entry `n` is frame `n`, counting frames that didn't change the screen.
+    pub fn frame_hashes(&self) -> &[u64] {
+        &self.hashes
+    }
+
+    /// Hash of the last presented frame
+    pub fn hash(&self) -> Option<u64> {
+        self.hash
+    }
+
+    fn hash_frame(&self) -> u64 {
+        let size = [self.cols as u8, self.rows as u8];
+        let pixels = self.front.iter().map(|&color| color as u8);
+        fnv1a(size.into_iter().chain(pixels))
+    }
+}
+
+impl Default for HeadlessTarget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderTarget for HeadlessTarget {
+    fn clear(&mut self) {
+        self.back.fill(Color::Black);
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, w: usize, h: usize, color: Color) {
+        for row in y..(y + h).min(self.rows) {
+            for col in x..(x + w).min(self.cols) {
+                self.back[row * self.cols + col] = color;
+            }
+        }
+    }
+
+    fn present(&mut self) {
+        self.front.clone_from(&self.back);
+        self.hash = Some(self.hash_frame());
+    }
+
+    fn end_frame(&mut self) {
+        if let Some(hash) = self.hash {
+            self.hashes.push(hash);
+        }
+    }
+
+    fn blit_frame(&mut self, frame: &[u8], cols: usize, rows: usize) {
+        self.cols = cols;
+        self.rows = rows;
+        self.back.clear();
+        self.back
+            .extend(frame.iter().copied().map(Color::from_planes));
+    }
+}
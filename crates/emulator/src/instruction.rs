@@ -1,5 +1,6 @@
 use std::fmt;
 
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub enum Instruction {
     ClearDisplay,
     ReturnFromSubroutine,
@@ -16,9 +17,9 @@ pub enum Instruction {
     XorRegs(u8, u8),
     AddRegs(u8, u8),
     SubRegs(u8, u8),
-    ShiftRight(u8),
+    ShiftRight(u8, u8),
     ReverseSubRegs(u8, u8),
-    ShiftLeft(u8),
+    ShiftLeft(u8, u8),
     SkipRegNotEqReg(u8, u8),
     SetI(u16),
     JumpV0PlusByte(u16),
@@ -35,6 +36,62 @@ pub enum Instruction {
     StoreNumberFromRegToI(u8),
     StoreRegsToMem(u8),
     LoadRegsFromMem(u8),
+    // SUPER-CHIP 1.1
+    ScrollDown(u8),
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    LowResolution,
+    HighResolution,
+    SetIToBigDigitSpriteLoc(u8),
+    StoreRegsToFlags(u8),
+    LoadRegsFromFlags(u8),
+    // XO-CHIP
+    StoreRegRange(u8, u8),
+    LoadRegRange(u8, u8),
+    ScrollUp(u8),
+    SetILong(u16),
+    SelectPlanes(u8),
+    LoadAudioPattern,
+    SetPitchToReg(u8),
+}
+
+impl Instruction {
+    /// Decode the instruction at the start of `bytes`
+    ///
+    /// Unlike `try_from`, this also decodes `F000 NNNN`, whose address is in a second word.
+    pub fn decode(bytes: &[u8]) -> Result<Self, InstructionError> {
+        let word = |idx: usize| match bytes.get(idx..idx + 2) {
+            Some(&[hi, lo]) => Some((hi as u16) << 8 | lo as u16),
+            _ => None,
+        };
+
+        let raw = word(0).ok_or(InstructionError::Truncated(0))?;
+        match Self::try_from(raw) {
+            Err(InstructionError::Truncated(raw)) => match word(2) {
+                Some(addr) => Ok(Self::SetILong(addr)),
+                None => Err(InstructionError::Truncated(raw)),
+            },
+            res => res,
+        }
+    }
+
+    /// Number of bytes the instruction takes up in memory
+    pub fn size(&self) -> usize {
+        match self {
+            Self::SetILong(_) => 4,
+            _ => 2,
+        }
+    }
+
+    /// Encode the instruction into the bytes stored in memory
+    pub fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = u16::from(self).to_be_bytes().to_vec();
+        if let Self::SetILong(addr) = self {
+            bytes.extend_from_slice(&addr.to_be_bytes());
+        }
+        bytes
+    }
 }
 
 impl TryFrom<u16> for Instruction {
@@ -44,6 +101,13 @@ impl TryFrom<u16> for Instruction {
         match raw {
             0x00E0 => Ok(Self::ClearDisplay),
             0x00EE => Ok(Self::ReturnFromSubroutine),
+            0x00C0..=0x00CF => Ok(Self::ScrollDown((raw & 0xF) as u8)),
+            0x00D0..=0x00DF => Ok(Self::ScrollUp((raw & 0xF) as u8)),
+            0x00FB => Ok(Self::ScrollRight),
+            0x00FC => Ok(Self::ScrollLeft),
+            0x00FD => Ok(Self::Exit),
+            0x00FE => Ok(Self::LowResolution),
+            0x00FF => Ok(Self::HighResolution),
             0x1000..=0x1FFF => Ok(Self::Jump(raw & 0x0FFF)),
             0x2000..=0x2FFF => Ok(Self::CallSubroutine(raw & 0x0FFF)),
             0x3000..=0x3FFF => Ok(Self::SkipRegEqByte(
@@ -59,6 +123,14 @@ impl TryFrom<u16> for Instruction {
                     ((raw & 0x0F00) >> 8) as u8,
                     ((raw & 0xF0) >> 4) as u8,
                 )),
+                0x2 => Ok(Self::StoreRegRange(
+                    ((raw & 0x0F00) >> 8) as u8,
+                    ((raw & 0xF0) >> 4) as u8,
+                )),
+                0x3 => Ok(Self::LoadRegRange(
+                    ((raw & 0x0F00) >> 8) as u8,
+                    ((raw & 0xF0) >> 4) as u8,
+                )),
                 _ => Err(InstructionError::Invalid(raw)),
             },
             0x6000..=0x6FFF => Ok(Self::SetRegToByte(
@@ -76,12 +148,18 @@ impl TryFrom<u16> for Instruction {
                 0x3 => Ok(Self::XorRegs(((raw & 0x0F00) >> 8) as u8, ((raw & 0xF0) >> 4) as u8)),
                 0x4 => Ok(Self::AddRegs(((raw & 0x0F00) >> 8) as u8, ((raw & 0xF0) >> 4) as u8)),
                 0x5 => Ok(Self::SubRegs(((raw & 0x0F00) >> 8) as u8, ((raw & 0xF0) >> 4) as u8)),
-                0x6 => Ok(Self::ShiftRight(((raw & 0x0F00) >> 8) as u8)),
+                0x6 => Ok(Self::ShiftRight(
+                    ((raw & 0x0F00) >> 8) as u8,
+                    ((raw & 0xF0) >> 4) as u8,
+                )),
                 0x7 => Ok(Self::ReverseSubRegs(
                     ((raw & 0x0F00) >> 8) as u8,
-                    (raw & 0xFF) as u8,
+                    ((raw & 0xF0) >> 4) as u8,
+                )),
+                0xE => Ok(Self::ShiftLeft(
+                    ((raw & 0x0F00) >> 8) as u8,
+                    ((raw & 0xF0) >> 4) as u8,
                 )),
-                0xE => Ok(Self::ShiftLeft(((raw & 0x0F00) >> 8) as u8)),
                 _ => Err(InstructionError::Invalid(raw)),
             },
             0x9000..=0x9FFF => match raw & 0xF {
@@ -107,16 +185,23 @@ impl TryFrom<u16> for Instruction {
                 0xA1 => Ok(Self::SkipIfNotKey(((raw & 0x0F00) >> 8) as u8)),
                 _ => Err(InstructionError::Invalid(raw)),
             },
-            0xF000..=0xFFFF => match raw & 0xFF {
+            0xF000 => Err(InstructionError::Truncated(raw)),
+            0xF001..=0xFFFF => match raw & 0xFF {
+                0x01 => Ok(Self::SelectPlanes(((raw & 0x0F00) >> 8) as u8)),
+                0x02 if raw & 0x0F00 == 0 => Ok(Self::LoadAudioPattern),
                 0x07 => Ok(Self::LoadDelayToReg(((raw & 0x0F00) >> 8) as u8)),
                 0x0A => Ok(Self::LoadKeyToReg(((raw & 0x0F00) >> 8) as u8)),
                 0x15 => Ok(Self::SetDelayToReg(((raw & 0x0F00) >> 8) as u8)),
                 0x18 => Ok(Self::SetSoundToReg(((raw & 0x0F00) >> 8) as u8)),
                 0x1E => Ok(Self::AddRegToI(((raw & 0x0F00) >> 8) as u8)),
                 0x29 => Ok(Self::SetIToDigitSpriteLoc(((raw & 0x0F00) >> 8) as u8)),
+                0x30 => Ok(Self::SetIToBigDigitSpriteLoc(((raw & 0x0F00) >> 8) as u8)),
                 0x33 => Ok(Self::StoreNumberFromRegToI(((raw & 0x0F00) >> 8) as u8)),
+                0x3A => Ok(Self::SetPitchToReg(((raw & 0x0F00) >> 8) as u8)),
                 0x55 => Ok(Self::StoreRegsToMem(((raw & 0x0F00) >> 8) as u8)),
                 0x65 => Ok(Self::LoadRegsFromMem(((raw & 0x0F00) >> 8) as u8)),
+                0x75 => Ok(Self::StoreRegsToFlags(((raw & 0x0F00) >> 8) as u8)),
+                0x85 => Ok(Self::LoadRegsFromFlags(((raw & 0x0F00) >> 8) as u8)),
                 _ => Err(InstructionError::Invalid(raw)),
             },
             _ => Err(InstructionError::Invalid(raw)),
@@ -124,14 +209,83 @@ impl TryFrom<u16> for Instruction {
     }
 }
 
+impl From<Instruction> for u16 {
+    /// Encode an instruction back into its raw opcode
+    ///
+    /// For `SetILong` this is only the first word, see `Instruction::to_bytes`.
+    fn from(instr: Instruction) -> Self {
+        use Instruction::*;
+
+        let x = |reg: u8| (reg as u16 & 0xF) << 8;
+        let y = |reg: u8| (reg as u16 & 0xF) << 4;
+        let nnn = |addr: u16| addr & 0x0FFF;
+
+        match instr {
+            ClearDisplay => 0x00E0,
+            ReturnFromSubroutine => 0x00EE,
+            Jump(addr) => 0x1000 | nnn(addr),
+            CallSubroutine(addr) => 0x2000 | nnn(addr),
+            SkipRegEqByte(reg, val) => 0x3000 | x(reg) | val as u16,
+            SkipRegNotEqByte(reg, val) => 0x4000 | x(reg) | val as u16,
+            SkipRegEqReg(reg1, reg2) => 0x5000 | x(reg1) | y(reg2),
+            SetRegToByte(reg, val) => 0x6000 | x(reg) | val as u16,
+            AddByteToReg(reg, val) => 0x7000 | x(reg) | val as u16,
+            MoveValue(reg1, reg2) => 0x8000 | x(reg1) | y(reg2),
+            OrRegs(reg1, reg2) => 0x8001 | x(reg1) | y(reg2),
+            AndRegs(reg1, reg2) => 0x8002 | x(reg1) | y(reg2),
+            XorRegs(reg1, reg2) => 0x8003 | x(reg1) | y(reg2),
+            AddRegs(reg1, reg2) => 0x8004 | x(reg1) | y(reg2),
+            SubRegs(reg1, reg2) => 0x8005 | x(reg1) | y(reg2),
+            ShiftRight(reg1, reg2) => 0x8006 | x(reg1) | y(reg2),
+            ReverseSubRegs(reg1, reg2) => 0x8007 | x(reg1) | y(reg2),
+            ShiftLeft(reg1, reg2) => 0x800E | x(reg1) | y(reg2),
+            SkipRegNotEqReg(reg1, reg2) => 0x9000 | x(reg1) | y(reg2),
+            SetI(addr) => 0xA000 | nnn(addr),
+            JumpV0PlusByte(addr) => 0xB000 | nnn(addr),
+            SetRegToRandPlusByte(reg, val) => 0xC000 | x(reg) | val as u16,
+            DrawSprite(reg_x, reg_y, size) => 0xD000 | x(reg_x) | y(reg_y) | (size as u16 & 0xF),
+            SkipIfKey(reg) => 0xE09E | x(reg),
+            SkipIfNotKey(reg) => 0xE0A1 | x(reg),
+            LoadDelayToReg(reg) => 0xF007 | x(reg),
+            LoadKeyToReg(reg) => 0xF00A | x(reg),
+            SetDelayToReg(reg) => 0xF015 | x(reg),
+            SetSoundToReg(reg) => 0xF018 | x(reg),
+            AddRegToI(reg) => 0xF01E | x(reg),
+            SetIToDigitSpriteLoc(reg) => 0xF029 | x(reg),
+            StoreNumberFromRegToI(reg) => 0xF033 | x(reg),
+            StoreRegsToMem(reg) => 0xF055 | x(reg),
+            LoadRegsFromMem(reg) => 0xF065 | x(reg),
+            ScrollDown(rows) => 0x00C0 | (rows as u16 & 0xF),
+            ScrollRight => 0x00FB,
+            ScrollLeft => 0x00FC,
+            Exit => 0x00FD,
+            LowResolution => 0x00FE,
+            HighResolution => 0x00FF,
+            SetIToBigDigitSpriteLoc(reg) => 0xF030 | x(reg),
+            StoreRegsToFlags(reg) => 0xF075 | x(reg),
+            LoadRegsFromFlags(reg) => 0xF085 | x(reg),
+            StoreRegRange(reg1, reg2) => 0x5002 | x(reg1) | y(reg2),
+            LoadRegRange(reg1, reg2) => 0x5003 | x(reg1) | y(reg2),
+            ScrollUp(rows) => 0x00D0 | (rows as u16 & 0xF),
+            SetILong(_) => 0xF000,
+            SelectPlanes(planes) => 0xF001 | x(planes),
+            LoadAudioPattern => 0xF002,
+            SetPitchToReg(reg) => 0xF03A | x(reg),
+        }
+    }
+}
+
 pub enum InstructionError {
     Invalid(u16),
+    /// Opcode needs a second word that wasn't available
+    Truncated(u16),
 }
 
 impl fmt::Debug for InstructionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Invalid(raw) => write!(f, "invalid opcode {:X}", raw),
+            Self::Truncated(raw) => write!(f, "opcode {:X} is missing its second word", raw),
         }
     }
 }
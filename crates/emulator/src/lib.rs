@@ -1,16 +1,147 @@
+use std::fmt;
 use std::fs;
+use std::ops::Range;
 use std::path::PathBuf;
-use std::time;
 
+pub mod audio;
+pub mod debugger;
+pub mod disassembler;
 pub mod display;
-mod instruction;
+pub mod headless;
+pub mod instruction;
+pub mod quirks;
+pub mod replay;
+pub mod rewind;
+pub mod rng;
+pub mod state;
 
+use audio::{AudioSink, Buzzer, Silence};
 use display::RenderTarget;
+use headless::HeadlessTarget;
+use quirks::{LoadStore, Quirks};
+use rng::Rng;
 
-use crate::instruction::Instruction;
+use crate::instruction::{Instruction, InstructionError};
+
+/// Fault raised while executing a ROM
+///
+/// Every variant carries the address of the faulting instruction and, where one was
+/// fetched, its raw opcode.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum EmulatorError {
+    /// Opcode does not decode to any known instruction
+    InvalidOpcode { pc: usize, opcode: u16 },
+    /// Subroutine call with a full stack
+    StackOverflow { pc: usize, opcode: u16 },
+    /// Return from subroutine with an empty stack
+    StackUnderflow { pc: usize, opcode: u16 },
+    /// Instruction touched memory outside of the address space
+    MemoryOutOfBounds { pc: usize, opcode: u16, addr: usize },
+    /// Program counter ran off the end of memory
+    PcOutOfBounds { pc: usize },
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidOpcode { pc, opcode } => {
+                write!(f, "invalid opcode {:04X} at {:03X}", opcode, pc)
+            }
+            Self::StackOverflow { pc, opcode } => {
+                write!(f, "stack overflow by {:04X} at {:03X}", opcode, pc)
+            }
+            Self::StackUnderflow { pc, opcode } => {
+                write!(f, "stack underflow by {:04X} at {:03X}", opcode, pc)
+            }
+            Self::MemoryOutOfBounds { pc, opcode, addr } => write!(
+                f,
+                "out of bounds memory access to {:X} by {:04X} at {:03X}",
+                addr, opcode, pc
+            ),
+            Self::PcOutOfBounds { pc } => write!(f, "program counter out of bounds at {:X}", pc),
+        }
+    }
+}
+
+impl std::error::Error for EmulatorError {}
+
+/// Reason a ROM couldn't be loaded
+#[derive(Debug)]
+pub enum RomError {
+    /// ROM file couldn't be read
+    Io(std::io::Error),
+    /// ROM has no contents
+    Empty,
+    /// ROM doesn't fit in memory at its load address
+    TooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "unable to read ROM: {}", err),
+            Self::Empty => write!(f, "ROM is empty"),
+            Self::TooLarge { size, max } => {
+                write!(f, "ROM is {} bytes, only {} bytes fit in memory", size, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RomError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Faults raised by `execute`, before the faulting instruction is attached
+enum Fault {
+    StackOverflow,
+    StackUnderflow,
+    MemoryOutOfBounds(usize),
+}
+
+impl Fault {
+    fn at(self, pc: usize, opcode: u16) -> EmulatorError {
+        match self {
+            Self::StackOverflow => EmulatorError::StackOverflow { pc, opcode },
+            Self::StackUnderflow => EmulatorError::StackUnderflow { pc, opcode },
+            Self::MemoryOutOfBounds(addr) => EmulatorError::MemoryOutOfBounds { pc, opcode, addr },
+        }
+    }
+}
+
+/// Address ROMs are loaded at and start running from
+pub const PROGRAM_START: u16 = 0x200;
+/// Load address of programs written for the ETI-660, which reserved more memory for itself
+pub const ETI_660_PROGRAM_START: u16 = 0x600;
+/// Bytes of memory a machine starts with
+pub const MEMORY_SIZE: usize = 0x1000;
+/// Bytes of memory XO-CHIP programs can address
+pub const XO_CHIP_MEMORY_SIZE: usize = 0x10000;
+
+/// What the CPU is doing between instructions
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum CpuState {
+    Running,
+    /// Blocked on `FX0A`, the key will be stored in the given register
+    WaitingForKey(u8),
+    /// Program ended by executing `00FD`
+    Halted,
+}
 
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum Keycode {
+    Num0,
     Num1,
     Num2,
     Num3,
@@ -20,7 +151,6 @@ pub enum Keycode {
     Num7,
     Num8,
     Num9,
-    Num0,
     A,
     B,
     C,
@@ -29,10 +159,24 @@ pub enum Keycode {
     F,
 }
 
-pub struct Chip8<T> where T: display::RenderTarget
+impl Keycode {
+    /// Bit for this key in `Chip8::key_mask`
+    pub fn mask(self) -> u16 {
+        // Variants are declared in the same order as `Chip8::KEYMAP`
+        1 << self as u16
+    }
+}
+
+pub struct Chip8<T, A = Silence>
+where
+    T: display::RenderTarget,
+    A: AudioSink,
 {
     pub display: display::Display<T>,
-    memory: [u8; 4096],
+    pub audio: A,
+    buzzer: Buzzer,
+    quirks: Quirks,
+    memory: Vec<u8>,
     registers: [u8; 16],
     i: u16,
     delay_timer: u8,
@@ -40,15 +184,40 @@ pub struct Chip8<T> where T: display::RenderTarget
     pc: usize,
     sp: usize,
     stack: [u16; 16],
-    last_clock: time::Instant,
-    events: Vec<u8>,
+    cycles_per_frame: usize,
+    rng: Rng,
+    /// Whether each key of the hex keypad is held down
+    keys: [bool; 16],
+    /// Key pressed while `FX0A` waits, which is stored once it's released
+    key_wait: Option<u8>,
+    rpl_flags: [u8; 16],
+    state: CpuState,
 }
 
 impl<T> Chip8<T>
 where
     T: RenderTarget,
+{
+    /// Create an emulator without audio output
+    pub fn new(display: display::Display<T>, quirks: Quirks) -> Self {
+        Self::with_audio(display, Silence, quirks)
+    }
+}
+
+impl Chip8<HeadlessTarget> {
+    /// Create an emulator that draws to memory only, for tests and tooling
+    pub fn headless(quirks: Quirks) -> Self {
+        Self::new(display::Display::new(HeadlessTarget::new()), quirks)
+    }
+}
+
+impl<T, A> Chip8<T, A>
+where
+    T: RenderTarget,
+    A: AudioSink,
 {
     const KEYMAP: [Keycode; 16] = [
+        Keycode::Num0,
         Keycode::Num1,
         Keycode::Num2,
         Keycode::Num3,
@@ -58,7 +227,6 @@ where
         Keycode::Num7,
         Keycode::Num8,
         Keycode::Num9,
-        Keycode::Num0,
         Keycode::A,
         Keycode::B,
         Keycode::C,
@@ -67,19 +235,31 @@ where
         Keycode::F,
     ];
 
-    pub fn new(display: display::Display<T>) -> Self {
+    /// 600 instructions per second, a reasonable speed for most CHIP-8 games
+    pub const DEFAULT_CYCLES_PER_FRAME: usize = 10;
+    const BIG_FONT_START: usize = 0x50;
+
+    /// Create an emulator that plays the buzzer through `audio`
+    pub fn with_audio(display: display::Display<T>, audio: A, quirks: Quirks) -> Self {
         let mut res = Self {
             display,
-            memory: [0; 4096],
+            audio,
+            buzzer: Buzzer::new(),
+            quirks,
+            memory: vec![0; MEMORY_SIZE],
             registers: Default::default(),
             i: Default::default(),
             delay_timer: Default::default(),
             sound_timer: Default::default(),
-            pc: Default::default(),
+            pc: PROGRAM_START as usize,
             sp: Default::default(),
             stack: Default::default(),
-            last_clock: time::Instant::now(),
-            events: Default::default(),
+            cycles_per_frame: Self::DEFAULT_CYCLES_PER_FRAME,
+            rng: Rng::from_entropy(),
+            keys: Default::default(),
+            key_wait: None,
+            rpl_flags: Default::default(),
+            state: CpuState::Running,
         };
 
         // Add digit sprites to memory
@@ -102,84 +282,349 @@ where
             0xF0, 0x80, 0xF0, 0x80, 0x80, // F
         ]);
 
+        // Add SUPER-CHIP large digit sprites to memory
+        res.memory[Self::BIG_FONT_START..Self::BIG_FONT_START + 16 * 10].copy_from_slice(&[
+            0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+            0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+            0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+            0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+            0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+            0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+            0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+            0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+            0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+            0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+            0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+            0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+        ]);
+
         res
     }
 
-    pub fn load(&mut self, rom_path: PathBuf) -> std::io::Result<()> {
+    /// Take the display back, to hand its render target on to another machine
+    pub fn into_display(self) -> display::Display<T> {
+        self.display
+    }
+
+    /// Load the ROM at `rom_path` at `PROGRAM_START`
+    pub fn load(&mut self, rom_path: PathBuf) -> Result<(), RomError> {
         let contents = fs::read(rom_path)?;
+        self.load_bytes(&contents)
+    }
 
-        self.memory[0x200..0x200 + contents.len()].copy_from_slice(contents.as_slice());
+    /// Load `rom` at `PROGRAM_START`
+    pub fn load_bytes(&mut self, rom: &[u8]) -> Result<(), RomError> {
+        self.load_bytes_at(rom, PROGRAM_START)
+    }
 
+    /// Load `rom` at `addr` and start running from there
+    ///
+    /// Memory is left untouched if the ROM is rejected.
+    pub fn load_bytes_at(&mut self, rom: &[u8], addr: u16) -> Result<(), RomError> {
+        let start = addr as usize;
+        let max = self.memory.len().saturating_sub(start);
+        if rom.is_empty() {
+            return Err(RomError::Empty);
+        }
+        if rom.len() > max {
+            return Err(RomError::TooLarge {
+                size: rom.len(),
+                max,
+            });
+        }
+
+        self.memory[start..start + rom.len()].copy_from_slice(rom);
+        self.pc = start;
         Ok(())
     }
 
-    pub fn tick(&mut self) {
-        let raw_instruction = self.fetch();
-        match Self::decode(raw_instruction) {
-            Some(instr) => self.execute(instr),
-            None => (),
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Instructions executed by each call to `run_frame`
+    pub fn cycles_per_frame(&self) -> usize {
+        self.cycles_per_frame
+    }
+
+    pub fn set_cycles_per_frame(&mut self, cycles: usize) {
+        self.cycles_per_frame = cycles;
+    }
+
+    /// Seed of the generator behind `CXNN`
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
+    /// Restart `CXNN`'s random sequence from `seed`, making runs reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Emulate one 60 Hz frame
+    ///
+    /// Runs `cycles_per_frame` instructions, stopping early if the program halts or waits
+    /// for a key, then updates the timers and renders exactly once. The host is responsible
+    /// for calling this 60 times a second.
+    pub fn run_frame(&mut self) -> Result<CpuState, EmulatorError> {
+        for _ in 0..self.cycles_per_frame {
+            if self.step()? != CpuState::Running {
+                break;
+            }
+        }
+        self.end_frame();
+        Ok(self.state)
+    }
+
+    /// Update the timers and render, finishing a frame run instruction by instruction
+    /// with `step`
+    pub fn end_frame(&mut self) {
+        self.update_timers();
+        self.display.end_frame();
+    }
+
+    fn update_timers(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        // The buzzer sounds for as many frames as the timer was set to
+        let sounding = self.sound_timer > 0;
+        if sounding {
+            self.sound_timer -= 1;
+        }
+        self.buzzer.tick(&mut self.audio, sounding);
+    }
+
+    pub fn state(&self) -> CpuState {
+        self.state
+    }
+
+    /// Whether the program has ended by executing `00FD`
+    pub fn is_halted(&self) -> bool {
+        self.state == CpuState::Halted
+    }
+
+    /// SUPER-CHIP RPL user flags, which programs expect to survive between runs
+    pub fn rpl_flags(&self) -> [u8; 16] {
+        self.rpl_flags
+    }
+
+    /// Restore RPL user flags saved from a previous run
+    pub fn set_rpl_flags(&mut self, flags: [u8; 16]) {
+        self.rpl_flags = flags;
+    }
+
+    pub fn registers(&self) -> [u8; 16] {
+        self.registers
+    }
+
+    pub fn set_register(&mut self, reg: u8, val: u8) {
+        self.registers[reg as usize & 0xF] = val;
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn set_i(&mut self, addr: u16) {
+        self.i = addr;
+    }
+
+    /// Address of the next instruction to execute
+    pub fn pc(&self) -> u16 {
+        self.pc as u16
+    }
+
+    pub fn set_pc(&mut self, addr: u16) {
+        self.pc = addr as usize;
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn set_delay_timer(&mut self, val: u8) {
+        self.delay_timer = val;
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn set_sound_timer(&mut self, val: u8) {
+        self.sound_timer = val;
+    }
+
+    /// Return addresses of the subroutines currently being run, innermost last
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp]
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    /// Grow or shrink memory to `size` bytes, e.g. `XO_CHIP_MEMORY_SIZE`
+    ///
+    /// Set it before loading a ROM: shrinking drops everything past the new end.
+    pub fn set_memory_size(&mut self, size: usize) {
+        self.memory.resize(size, 0);
+    }
+
+    /// Fetch, decode and execute a single instruction
+    ///
+    /// Does nothing while the program is halted or waiting for a key. On error the program
+    /// counter is left pointing at the faulting instruction.
+    pub fn step(&mut self) -> Result<CpuState, EmulatorError> {
+        if self.state != CpuState::Running {
+            return Ok(self.state);
         }
-        self.render();
-        if self.last_clock.elapsed() > time::Duration::from_millis(16) {
-            if self.delay_timer > 0 {
-                self.delay_timer -= 1;
+
+        let pc = self.pc;
+        if let Err(err) = self.run_instruction(pc) {
+            self.pc = pc;
+            return Err(err);
+        }
+        Ok(self.state)
+    }
+
+    fn run_instruction(&mut self, pc: usize) -> Result<(), EmulatorError> {
+        let opcode = self.fetch()?;
+        let instr = match Instruction::try_from(opcode) {
+            Ok(instr) => instr,
+            Err(InstructionError::Truncated(_)) => Instruction::SetILong(self.fetch()?),
+            Err(InstructionError::Invalid(_)) => {
+                return Err(EmulatorError::InvalidOpcode { pc, opcode })
             }
-            if self.sound_timer > 0 {
-                self.sound_timer -= 1;
+        };
+        self.execute(instr).map_err(|fault| fault.at(pc, opcode))
+    }
+
+    pub fn key_down(&mut self, keycode: &Keycode) {
+        self.set_key(keycode, true);
+    }
+
+    pub fn key_up(&mut self, keycode: &Keycode) {
+        self.set_key(keycode, false);
+    }
+
+    /// Whether `keycode` is currently held down
+    pub fn is_key_down(&self, keycode: &Keycode) -> bool {
+        Self::key_index(keycode).is_some_and(|key| self.keys[key])
+    }
+
+    /// Held keys as a bitmask, bit N being set while key N is down
+    pub fn key_mask(&self) -> u16 {
+        self.keys
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (key, &down)| mask | (down as u16) << key)
+    }
+
+    /// Press and release keys so that exactly those in `mask` are held
+    pub fn set_key_mask(&mut self, mask: u16) {
+        for key in 0..self.keys.len() {
+            let down = mask & (1 << key) != 0;
+            if self.keys[key] != down {
+                self.set_key_index(key, down);
             }
-            self.last_clock = time::Instant::now();
         }
     }
 
-    pub fn push_key(&mut self, keycode: &Keycode) {
-        if let Some(key) = Self::KEYMAP.iter().position(|&el| el == *keycode) {
-            self.events.push(key as u8);
+    fn set_key(&mut self, keycode: &Keycode, down: bool) {
+        if let Some(key) = Self::key_index(keycode) {
+            self.set_key_index(key, down);
+        }
+    }
+
+    fn set_key_index(&mut self, key: usize, down: bool) {
+        self.keys[key] = down;
+
+        // As on the VIP, `FX0A` only takes a key once it has been pressed and released
+        if let CpuState::WaitingForKey(reg) = self.state {
+            match self.key_wait {
+                None if down => self.key_wait = Some(key as u8),
+                Some(pressed) if !down && pressed as usize == key => {
+                    self.registers[reg as usize] = pressed;
+                    self.key_wait = None;
+                    self.state = CpuState::Running;
+                }
+                _ => (),
+            }
         }
     }
 
-    fn fetch(&mut self) -> u16 {
+    fn key_index(keycode: &Keycode) -> Option<usize> {
+        Self::KEYMAP.iter().position(|el| el == keycode)
+    }
+
+    fn fetch(&mut self) -> Result<u16, EmulatorError> {
+        if self.pc + 1 >= self.memory.len() {
+            return Err(EmulatorError::PcOutOfBounds { pc: self.pc });
+        }
         let mut instruction: u16 = self.memory[self.pc] as u16;
         instruction <<= 8;
         instruction |= self.memory[self.pc + 1] as u16;
         self.pc += 2;
-        instruction
+        Ok(instruction)
+    }
+
+    /// Skip the next instruction, which is two words long for `F000 NNNN`
+    fn skip(&mut self) {
+        let long = self.memory.get(self.pc..self.pc + 2) == Some(&[0xF0, 0x00]);
+        self.pc += if long { 4 } else { 2 };
     }
 
-    fn decode(raw: u16) -> Option<Instruction> {
-        match Instruction::try_from(raw) {
-            Ok(instr) => Some(instr),
-            Err(_) => None,
+    /// Bounds-checked range of `len` bytes of memory starting at `addr`
+    fn mem_range(&self, addr: usize, len: usize) -> Result<Range<usize>, Fault> {
+        if addr + len > self.memory.len() {
+            return Err(Fault::MemoryOutOfBounds(addr + len - 1));
         }
+        Ok(addr..addr + len)
     }
 
-    fn execute(&mut self, instruction: Instruction) {
+    fn execute(&mut self, instruction: Instruction) -> Result<(), Fault> {
         use Instruction::*;
 
         match instruction {
             ClearDisplay => self.display.clear(),
             Jump(addr) => self.pc = addr as usize,
             ReturnFromSubroutine => {
-                self.pc = self.stack[self.sp] as usize;
+                if self.sp == 0 {
+                    return Err(Fault::StackUnderflow);
+                }
                 self.sp -= 1;
+                self.pc = self.stack[self.sp] as usize;
             }
             CallSubroutine(addr) => {
-                self.sp += 1;
+                if self.sp == self.stack.len() {
+                    return Err(Fault::StackOverflow);
+                }
                 self.stack[self.sp] = self.pc as u16;
+                self.sp += 1;
                 self.pc = addr as usize;
             }
             SkipRegEqByte(reg, val) => {
                 if self.registers[reg as usize] == val {
-                    self.pc += 2;
+                    self.skip();
                 }
             }
             SkipRegNotEqByte(reg, val) => {
                 if self.registers[reg as usize] != val {
-                    self.pc += 2;
+                    self.skip();
                 }
             }
             SkipRegEqReg(reg1, reg2) => {
                 if self.registers[reg1 as usize] == self.registers[reg2 as usize] {
-                    self.pc += 2;
+                    self.skip();
                 }
             }
             SetRegToByte(reg, val) => self.registers[reg as usize] = val,
@@ -187,83 +632,110 @@ where
                 self.registers[reg as usize] = self.registers[reg as usize].wrapping_add(val)
             }
             MoveValue(reg1, reg2) => self.registers[reg1 as usize] = self.registers[reg2 as usize],
-            OrRegs(reg1, reg2) => self.registers[reg1 as usize] |= self.registers[reg2 as usize],
-            AndRegs(reg1, reg2) => self.registers[reg1 as usize] &= self.registers[reg2 as usize],
-            XorRegs(reg1, reg2) => self.registers[reg1 as usize] ^= self.registers[reg2 as usize],
+            OrRegs(reg1, reg2) => {
+                self.registers[reg1 as usize] |= self.registers[reg2 as usize];
+                self.logic_vf_quirk();
+            }
+            AndRegs(reg1, reg2) => {
+                self.registers[reg1 as usize] &= self.registers[reg2 as usize];
+                self.logic_vf_quirk();
+            }
+            XorRegs(reg1, reg2) => {
+                self.registers[reg1 as usize] ^= self.registers[reg2 as usize];
+                self.logic_vf_quirk();
+            }
+            // VF is written after the result so that it holds the flag when it's also VX
             AddRegs(reg1, reg2) => {
-                let res =
-                    self.registers[reg1 as usize] as u16 + self.registers[reg2 as usize] as u16;
-                self.registers[0xF] = if res > 0xFF { 1 } else { 0 }; // carry
-                self.registers[reg1 as usize] = (res & 0xFF) as u8;
+                let val1 = self.registers[reg1 as usize];
+                let val2 = self.registers[reg2 as usize];
+                let (res, carry) = val1.overflowing_add(val2);
+                self.registers[reg1 as usize] = res;
+                self.registers[0xF] = carry as u8;
             }
             SubRegs(reg1, reg2) => {
                 let val1 = self.registers[reg1 as usize];
                 let val2 = self.registers[reg2 as usize];
-                self.registers[0xF] = if val1 > val2 { 1 } else { 0 }; // NOT borrow
                 self.registers[reg1 as usize] = val1.wrapping_sub(val2);
+                self.registers[0xF] = (val1 >= val2) as u8; // NOT borrow
             }
-            ShiftRight(reg) => {
-                let val = self.registers[reg as usize];
-                self.registers[0xF] = if val % 2 == 1 { 1 } else { 0 }; // data loss
-                self.registers[reg as usize] = val >> 1;
+            ShiftRight(reg1, reg2) => {
+                let val = self.registers[self.shift_source(reg1, reg2)];
+                self.registers[reg1 as usize] = val >> 1;
+                self.registers[0xF] = val & 0x1; // data loss
             }
             ReverseSubRegs(reg1, reg2) => {
                 let val1 = self.registers[reg1 as usize];
                 let val2 = self.registers[reg2 as usize];
-                self.registers[0xF] = if val2 > val1 { 1 } else { 0 }; // NOT borrow
                 self.registers[reg1 as usize] = val2.wrapping_sub(val1);
+                self.registers[0xF] = (val2 >= val1) as u8; // NOT borrow
             }
-            ShiftLeft(reg) => {
-                let val = self.registers[reg as usize];
-                self.registers[0xF] = if val & 0x80 != 0 { 1 } else { 0 }; // data loss
-                self.registers[reg as usize] = val << 1;
+            ShiftLeft(reg1, reg2) => {
+                let val = self.registers[self.shift_source(reg1, reg2)];
+                self.registers[reg1 as usize] = val << 1;
+                self.registers[0xF] = val >> 7; // data loss
             }
             SkipRegNotEqReg(reg1, reg2) => {
                 if self.registers[reg1 as usize] != self.registers[reg2 as usize] {
-                    self.pc += 2;
+                    self.skip();
                 }
             }
             SetI(addr) => self.i = addr,
-            JumpV0PlusByte(addr) => self.pc = self.registers[0x0] as usize + addr as usize,
+            JumpV0PlusByte(addr) => {
+                let reg = if self.quirks.jump_uses_vx {
+                    (addr >> 8) as usize
+                } else {
+                    0x0
+                };
+                self.pc = self.registers[reg] as usize + addr as usize;
+            }
             SetRegToRandPlusByte(reg, val) => {
-                self.registers[reg as usize] = rand::random::<u8>() & val;
+                self.registers[reg as usize] = self.rng.next_u8() & val;
             }
             DrawSprite(reg_x, reg_y, size) => {
                 let x = self.registers[reg_x as usize];
                 let y = self.registers[reg_y as usize];
-                let sprite = &self.memory[self.i as usize..(self.i as usize + size as usize)];
-                self.registers[0xF] = if self.display.blit_sprite(x, y, sprite) {
+                // Height 0 draws a 16x16 SUPER-CHIP sprite, or 8x16 in lores on SUPER-CHIP 1.1
+                let (width, len) = if size == 0 {
+                    if self.display.is_hires() || self.quirks.lores_wide_sprites {
+                        (16, 32)
+                    } else {
+                        (8, 16)
+                    }
+                } else {
+                    (8, size as usize)
+                };
+                let len = len * self.display.planes().count_ones() as usize;
+                let range = self.mem_range(self.i as usize, len)?;
+                let sprite = &self.memory[range];
+                let wrap = self.quirks.wrap_sprites;
+                self.registers[0xF] = if self.display.blit_sprite(x, y, sprite, width, wrap) {
                     1
                 } else {
                     0
                 }
             }
             SkipIfKey(reg) => {
-                let key_idx = self.registers[reg as usize];
-                assert!((key_idx as usize) < Self::KEYMAP.len());
-                if let Some(key) = self.events.pop() {
-                    self.pc += if key == key_idx { 2 } else { 0 };
+                if self.keys[(self.registers[reg as usize] & 0xF) as usize] {
+                    self.skip();
                 }
             }
             SkipIfNotKey(reg) => {
-                let key_idx = self.registers[reg as usize];
-                assert!((key_idx as usize) < Self::KEYMAP.len());
-                if let Some(key) = self.events.pop() {
-                    self.pc += if key != key_idx { 2 } else { 0 };
+                if !self.keys[(self.registers[reg as usize] & 0xF) as usize] {
+                    self.skip();
                 }
             }
             LoadDelayToReg(reg) => self.registers[reg as usize] = self.delay_timer,
-            LoadKeyToReg(reg) => loop {
-                if let Some(key) = self.events.pop() {
-                    self.registers[reg as usize] = key as u8;
-                    break;
-                }
-            },
+            LoadKeyToReg(reg) => {
+                // A key that is already held counts as pressed
+                self.key_wait = self.keys.iter().position(|&down| down).map(|key| key as u8);
+                self.state = CpuState::WaitingForKey(reg);
+            }
             SetDelayToReg(reg) => self.delay_timer = self.registers[reg as usize],
             SetSoundToReg(reg) => self.sound_timer = self.registers[reg as usize],
-            AddRegToI(reg) => self.i += self.registers[reg as usize] as u16,
+            AddRegToI(reg) => self.i = self.i.wrapping_add(self.registers[reg as usize] as u16),
             SetIToDigitSpriteLoc(reg) => {
-                self.i = self.registers[reg as usize] as u16 * 5;
+                let digit = self.registers[reg as usize] & 0xF;
+                self.i = digit as u16 * 5;
             }
             StoreNumberFromRegToI(reg) => {
                 let val = self.registers[reg as usize];
@@ -271,27 +743,101 @@ where
                 let tens = (val - hundreds * 100) / 10;
                 let ones = val - hundreds * 100 - tens * 10;
 
-                self.memory[self.i as usize] = hundreds;
-                self.memory[self.i as usize + 1] = tens;
-                self.memory[self.i as usize + 2] = ones;
+                let range = self.mem_range(self.i as usize, 3)?;
+                self.memory[range].copy_from_slice(&[hundreds, tens, ones]);
             }
             StoreRegsToMem(max_reg) => {
-                for (reg, val) in self.registers[0..=(max_reg as usize)].iter().enumerate() {
-                    self.memory[self.i as usize + reg] = *val;
-                }
+                let range = self.mem_range(self.i as usize, max_reg as usize + 1)?;
+                self.memory[range].copy_from_slice(&self.registers[0..=(max_reg as usize)]);
+                self.load_store_quirk(max_reg);
             }
             LoadRegsFromMem(max_reg) => {
-                for (reg, val) in self.registers[0..=(max_reg as usize)]
-                    .iter_mut()
-                    .enumerate()
-                {
-                    *val = self.memory[self.i as usize + reg] as u8;
+                let range = self.mem_range(self.i as usize, max_reg as usize + 1)?;
+                self.registers[0..=(max_reg as usize)].copy_from_slice(&self.memory[range]);
+                self.load_store_quirk(max_reg);
+            }
+            ScrollDown(rows) => self.display.scroll(0, rows as isize),
+            ScrollRight => self.display.scroll(4, 0),
+            ScrollLeft => self.display.scroll(-4, 0),
+            Exit => self.state = CpuState::Halted,
+            LowResolution => self.display.set_hires(false),
+            HighResolution => self.display.set_hires(true),
+            SetIToBigDigitSpriteLoc(reg) => {
+                let digit = self.registers[reg as usize] & 0xF;
+                self.i = (Self::BIG_FONT_START + digit as usize * 10) as u16;
+            }
+            StoreRegsToFlags(max_reg) => {
+                let count = max_reg as usize + 1;
+                self.rpl_flags[..count].copy_from_slice(&self.registers[..count]);
+            }
+            LoadRegsFromFlags(max_reg) => {
+                let count = max_reg as usize + 1;
+                self.registers[..count].copy_from_slice(&self.rpl_flags[..count]);
+            }
+            StoreRegRange(reg1, reg2) => {
+                let regs = Self::reg_range(reg1, reg2);
+                let range = self.mem_range(self.i as usize, regs.len())?;
+                let values: Vec<u8> = regs.iter().map(|&reg| self.registers[reg]).collect();
+                self.memory[range].copy_from_slice(&values);
+            }
+            LoadRegRange(reg1, reg2) => {
+                let regs = Self::reg_range(reg1, reg2);
+                let range = self.mem_range(self.i as usize, regs.len())?;
+                for (reg, addr) in regs.into_iter().zip(range) {
+                    self.registers[reg] = self.memory[addr];
                 }
             }
+            ScrollUp(rows) => self.display.scroll(0, -(rows as isize)),
+            SetILong(addr) => self.i = addr,
+            SelectPlanes(planes) => self.display.set_planes(planes),
+            LoadAudioPattern => {
+                let range = self.mem_range(self.i as usize, 16)?;
+                let mut pattern = [0; 16];
+                pattern.copy_from_slice(&self.memory[range]);
+                self.buzzer.set_pattern(pattern);
+            }
+            SetPitchToReg(reg) => self.buzzer.set_pitch(self.registers[reg as usize]),
+        }
+        Ok(())
+    }
+
+    /// Registers from `reg1` to `reg2` inclusive, counting down if `reg1` is larger
+    fn reg_range(reg1: u8, reg2: u8) -> Vec<usize> {
+        let (reg1, reg2) = (reg1 as usize, reg2 as usize);
+        if reg1 <= reg2 {
+            (reg1..=reg2).collect()
+        } else {
+            (reg2..=reg1).rev().collect()
+        }
+    }
+
+    /// Register shifted by `8XY6`/`8XYE`
+    fn shift_source(&self, reg1: u8, reg2: u8) -> usize {
+        if self.quirks.shift_uses_vy {
+            reg2 as usize
+        } else {
+            reg1 as usize
+        }
+    }
+
+    fn logic_vf_quirk(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.registers[0xF] = 0;
+        }
+    }
+
+    fn load_store_quirk(&mut self, max_reg: u8) {
+        match self.quirks.load_store {
+            LoadStore::Unchanged => (),
+            LoadStore::IncrementByX => self.i = self.i.wrapping_add(max_reg as u16),
+            LoadStore::IncrementByXPlusOne => self.i = self.i.wrapping_add(max_reg as u16 + 1),
         }
     }
 
-    fn render(&mut self) {
+    /// Draw the screen to the render target, which `run_frame` does on its own
+    ///
+    /// Useful after changing the machine by other means, such as `load_state`.
+    pub fn render(&mut self) {
         self.display.render();
     }
 }
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// What `FX55`/`FX65` leave in I once they're done
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum LoadStore {
+    /// I is left untouched
+    Unchanged,
+    /// I ends up pointing at the last register's byte
+    IncrementByX,
+    /// I ends up pointing just past the last register's byte
+    IncrementByXPlusOne,
+}
+
+/// Behaviour of the opcodes that interpreters have historically disagreed on
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct Quirks {
+    /// `8XY6`/`8XYE` shift VY into VX rather than shifting VX in place
+    pub shift_uses_vy: bool,
+    pub load_store: LoadStore,
+    /// `BNNN` jumps to NNN plus VX, X being the top nibble of NNN, instead of V0
+    pub jump_uses_vx: bool,
+    /// `8XY1`/`8XY2`/`8XY3` reset VF to zero
+    pub logic_resets_vf: bool,
+    /// Sprites drawn past the edge of the screen wrap around rather than being clipped
+    pub wrap_sprites: bool,
+    /// `DXY0` draws a 16x16 sprite in low resolution too, rather than the 8x16 one of
+    /// SUPER-CHIP 1.1
+    pub lores_wide_sprites: bool,
+}
+
+impl Quirks {
+    /// The original interpreter on the RCA COSMAC VIP
+    pub const COSMAC_VIP: Self = Self {
+        shift_uses_vy: true,
+        load_store: LoadStore::IncrementByXPlusOne,
+        jump_uses_vx: false,
+        logic_resets_vf: true,
+        wrap_sprites: false,
+        lores_wide_sprites: true,
+    };
+
+    /// CHIP-48 on the HP-48 calculators
+    pub const CHIP48: Self = Self {
+        shift_uses_vy: false,
+        load_store: LoadStore::IncrementByX,
+        jump_uses_vx: true,
+        logic_resets_vf: false,
+        wrap_sprites: false,
+        lores_wide_sprites: true,
+    };
+
+    /// SUPER-CHIP 1.1
+    pub const SCHIP: Self = Self {
+        shift_uses_vy: false,
+        load_store: LoadStore::Unchanged,
+        jump_uses_vx: true,
+        logic_resets_vf: false,
+        wrap_sprites: false,
+        lores_wide_sprites: false,
+    };
+
+    /// Modern interpreters such as Octo, which most recent games are written against
+    pub const MODERN: Self = Self {
+        shift_uses_vy: true,
+        load_store: LoadStore::IncrementByXPlusOne,
+        jump_uses_vx: false,
+        logic_resets_vf: false,
+        wrap_sprites: true,
+        lores_wide_sprites: true,
+    };
+
+    /// XO-CHIP, as implemented by Octo
+    pub const XO_CHIP: Self = Self {
+        shift_uses_vy: true,
+        load_store: LoadStore::IncrementByXPlusOne,
+        jump_uses_vx: false,
+        logic_resets_vf: false,
+        wrap_sprites: true,
+        lores_wide_sprites: true,
+    };
+
+    /// Names accepted by `from_str`, in the same order as `PRESETS`
+    pub const NAMES: [&'static str; 5] = ["vip", "chip48", "schip", "modern", "xochip"];
+    const PRESETS: [Self; 5] = [
+        Self::COSMAC_VIP,
+        Self::CHIP48,
+        Self::SCHIP,
+        Self::MODERN,
+        Self::XO_CHIP,
+    ];
+
+    /// Bytes of memory on the platform preset `name` is named after, 64 KiB for XO-CHIP
+    /// and 4 KiB for everything else
+    pub fn preset_memory_size(name: &str) -> usize {
+        if name.eq_ignore_ascii_case("xochip") {
+            crate::XO_CHIP_MEMORY_SIZE
+        } else {
+            crate::MEMORY_SIZE
+        }
+    }
+}
+
+impl Default for Quirks {
+    /// How the emulator behaved before quirks were configurable, which no single preset
+    /// matches: shifts ignore VY, I is left alone, `BNNN` adds V0, VF survives logic
+    /// opcodes, sprites are clipped and `DXY0` is 16x16 at either resolution
+    fn default() -> Self {
+        Self {
+            shift_uses_vy: false,
+            load_store: LoadStore::Unchanged,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+            wrap_sprites: false,
+            lores_wide_sprites: true,
+        }
+    }
+}
+
+pub struct UnknownPreset(String);
+
+impl fmt::Debug for UnknownPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown quirks preset {}, expected one of {}",
+            self.0,
+            Quirks::NAMES.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for UnknownPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = UnknownPreset;
+
+    /// Look up a preset by name, e.g. `schip`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::NAMES
+            .iter()
+            .position(|preset| preset.eq_ignore_ascii_case(name))
+            .map(|idx| Self::PRESETS[idx])
+            .ok_or_else(|| UnknownPreset(name.to_string()))
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+
+use crate::audio::AudioSink;
+use crate::display::RenderTarget;
+use crate::quirks::Quirks;
+use crate::state::{self, StateError, Writer};
+use crate::{Chip8, CpuState, EmulatorError, RomError, XO_CHIP_MEMORY_SIZE};
+
+const MAGIC: &[u8; 4] = b"C8RP";
+const VERSION: u16 = 6;
+
+/// Reason a replay couldn't be played back
+#[derive(Debug)]
+pub enum PlaybackError {
+    /// ROM doesn't load where the replay was recorded
+    Rom(RomError),
+    /// Emulator crashed during playback
+    Emulator(EmulatorError),
+}
+
+impl fmt::Display for PlaybackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rom(err) => write!(f, "{}", err),
+            Self::Emulator(err) => write!(f, "emulator crashed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PlaybackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Rom(err) => Some(err),
+            Self::Emulator(err) => Some(err),
+        }
+    }
+}
+
+impl From<RomError> for PlaybackError {
+    fn from(err: RomError) -> Self {
+        Self::Rom(err)
+    }
+}
+
+impl From<EmulatorError> for PlaybackError {
+    fn from(err: EmulatorError) -> Self {
+        Self::Emulator(err)
+    }
+}
+
+/// Input movie that reproduces a session exactly
+///
+/// Holds everything that can make two runs of the same ROM differ: the RNG seed, quirks,
+/// memory size, load address, speed and the keys held on every frame. The screen hash at the end of the recording is
+/// kept so playback can be verified without looking at it.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub quirks: Quirks,
+    /// Bytes of memory, as given to `Chip8::set_memory_size`
+    pub memory_size: usize,
+    /// Address the ROM was loaded at
+    pub load_address: u16,
+    pub cycles_per_frame: usize,
+    /// CRC-32 of the ROM the replay was recorded against
+    pub rom_checksum: u32,
+    /// Key mask, as given to `Chip8::set_key_mask`, for each frame
+    pub frames: Vec<u16>,
+    /// `Display::hash` after the last frame
+    pub final_hash: u64,
+}
+
+impl Replay {
+    /// Start recording `emu`, which should have just loaded `rom` and not run it yet
+    pub fn new<T, A>(emu: &Chip8<T, A>, rom: &[u8]) -> Self
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        Self {
+            seed: emu.seed(),
+            quirks: emu.quirks(),
+            memory_size: emu.memory().len(),
+            // Loading a ROM points the program counter at it
+            load_address: emu.pc(),
+            cycles_per_frame: emu.cycles_per_frame(),
+            rom_checksum: state::crc32(rom),
+            frames: Vec::new(),
+            final_hash: emu.display.hash(),
+        }
+    }
+
+    /// Run a frame of `emu` with `keys` held, recording it
+    pub fn record_frame<T, A>(
+        &mut self,
+        emu: &mut Chip8<T, A>,
+        keys: u16,
+    ) -> Result<CpuState, EmulatorError>
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        emu.set_key_mask(keys);
+        self.frames.push(keys);
+        let state = emu.run_frame();
+        self.final_hash = emu.display.hash();
+        state
+    }
+
+    /// Whether `rom` is the ROM the replay was recorded against
+    pub fn matches_rom(&self, rom: &[u8]) -> bool {
+        state::crc32(rom) == self.rom_checksum
+    }
+
+    /// Prepare `emu`, freshly created with `self.quirks`, for playback by loading `rom`
+    /// where it was recorded
+    pub fn start<T, A>(&self, emu: &mut Chip8<T, A>, rom: &[u8]) -> Result<(), RomError>
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        emu.set_memory_size(self.memory_size);
+        emu.load_bytes_at(rom, self.load_address)?;
+        emu.set_seed(self.seed);
+        emu.set_cycles_per_frame(self.cycles_per_frame);
+        Ok(())
+    }
+
+    /// Run recorded frame `frame` of `emu`
+    pub fn play_frame<T, A>(
+        &self,
+        emu: &mut Chip8<T, A>,
+        frame: usize,
+    ) -> Result<CpuState, EmulatorError>
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        emu.set_key_mask(self.frames[frame]);
+        emu.run_frame()
+    }
+
+    /// Play the whole replay of `rom` on `emu` as fast as possible, returning whether the
+    /// screen ends up the same as it did when recording
+    pub fn verify<T, A>(&self, emu: &mut Chip8<T, A>, rom: &[u8]) -> Result<bool, PlaybackError>
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        self.start(emu, rom)?;
+        for frame in 0..self.frames.len() {
+            self.play_frame(emu, frame)?;
+        }
+        Ok(emu.display.hash() == self.final_hash)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer::default();
+        out.u64(self.seed);
+        state::write_quirks(&mut out, &self.quirks);
+        out.u32(self.memory_size as u32);
+        out.u16(self.load_address);
+        out.u32(self.cycles_per_frame as u32);
+        out.u32(self.rom_checksum);
+        out.u64(self.final_hash);
+        out.u32(self.frames.len() as u32);
+        for &keys in &self.frames {
+            out.u16(keys);
+        }
+        state::seal(MAGIC, VERSION, &out.into_inner())
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, StateError> {
+        let mut input = state::unseal(MAGIC, VERSION, data)?;
+        let seed = input.u64()?;
+        let quirks = state::read_quirks(&mut input)?;
+        let memory_size = input.u32()? as usize;
+        if memory_size > XO_CHIP_MEMORY_SIZE {
+            return Err(StateError::Malformed("memory too large"));
+        }
+        let load_address = input.u16()?;
+        let cycles_per_frame = input.u32()? as usize;
+        let rom_checksum = input.u32()?;
+        let final_hash = input.u64()?;
+        let frames = (0..input.u32()?)
+            .map(|_| input.u16())
+            .collect::<Result<_, _>>()?;
+        input.finish()?;
+
+        Ok(Self {
+            seed,
+            quirks,
+            memory_size,
+            load_address,
+            cycles_per_frame,
+            rom_checksum,
+            frames,
+            final_hash,
+        })
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+
+use crate::audio::AudioSink;
+use crate::display::RenderTarget;
+use crate::Chip8;
+
+/// Difference between two consecutive snapshots
+struct Delta {
+    /// Length of the older snapshot
+    len: usize,
+    /// Run-length encoded XOR of the older snapshot against the newer one
+    data: Vec<u8>,
+}
+
+/// Bounded history of save states that the host can step back through
+///
+/// Only the newest snapshot is kept whole. Older ones are stored as the XOR against their
+/// successor, which is mostly zeros from one frame to the next and so compresses well.
+pub struct Rewind {
+    capacity: usize,
+    interval: usize,
+    frames: usize,
+    latest: Option<Vec<u8>>,
+    deltas: VecDeque<Delta>,
+}
+
+impl Rewind {
+    /// Keep up to `capacity` snapshots, taking one every `interval` frames
+    pub fn new(capacity: usize, interval: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            interval: interval.max(1),
+            frames: 0,
+            latest: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    /// Number of snapshots that can currently be rewound to
+    pub fn len(&self) -> usize {
+        self.latest.as_ref().map_or(0, |_| self.deltas.len() + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames = 0;
+        self.latest = None;
+        self.deltas.clear();
+    }
+
+    /// Call once per frame, snapshotting `emu` every `interval` frames
+    pub fn record<T, A>(&mut self, emu: &Chip8<T, A>)
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        self.frames += 1;
+        if self.frames < self.interval {
+            return;
+        }
+        self.frames = 0;
+
+        let state = emu.save_state();
+        if let Some(previous) = self.latest.take() {
+            self.deltas.push_back(Delta {
+                len: previous.len(),
+                data: rle_encode(&xor(&previous, &state)),
+            });
+            if self.deltas.len() >= self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+        self.latest = Some(state);
+    }
+
+    /// Step `emu` back to the newest snapshot older than the state it is in, returning
+    /// false once there is nothing older left
+    ///
+    /// The restored snapshot becomes the newest, so every call steps back one further.
+    pub fn rewind<T, A>(&mut self, emu: &mut Chip8<T, A>) -> bool
+    where
+        T: RenderTarget,
+        A: AudioSink,
+    {
+        // A snapshot taken or restored this frame is the state `emu` is already in
+        if self.frames == 0 {
+            let (latest, delta) = match (self.latest.take(), self.deltas.pop_back()) {
+                (Some(latest), Some(delta)) => (latest, delta),
+                (latest, _) => {
+                    self.latest = latest;
+                    return false;
+                }
+            };
+            let mut previous = xor(&latest, &rle_decode(&delta.data));
+            previous.truncate(delta.len);
+            self.latest = Some(previous);
+        }
+        self.frames = 0;
+
+        // Snapshots come from `save_state`, so they can only fail to load if corrupted
+        match &self.latest {
+            Some(state) => emu.load_state(state).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// XOR of two byte strings, the shorter being padded with zeros
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let len = a.len().max(b.len());
+    (0..len)
+        .map(|idx| a.get(idx).unwrap_or(&0) ^ b.get(idx).unwrap_or(&0))
+        .collect()
+}
+
+/// Encode as alternating runs of zeros and literal bytes, each preceded by a u16 length
+fn rle_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let zeros = bytes[idx..]
+            .iter()
+            .take(u16::MAX as usize)
+            .take_while(|&&byte| byte == 0)
+            .count();
+        idx += zeros;
+        let literals = bytes[idx..]
+            .iter()
+            .take(u16::MAX as usize)
+            .take_while(|&&byte| byte != 0)
+            .count();
+        out.extend_from_slice(&(zeros as u16).to_le_bytes());
+        out.extend_from_slice(&(literals as u16).to_le_bytes());
+        out.extend_from_slice(&bytes[idx..idx + literals]);
+        idx += literals;
+    }
+    out
+}
+
+fn rle_decode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut idx = 0;
+    while idx + 4 <= data.len() {
+        let zeros = u16::from_le_bytes([data[idx], data[idx + 1]]) as usize;
+        let literals = u16::from_le_bytes([data[idx + 2], data[idx + 3]]) as usize;
+        idx += 4;
+        out.resize(out.len() + zeros, 0);
+        out.extend_from_slice(&data[idx..idx + literals]);
+        idx += literals;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> Vec<u8> {
+        rle_decode(&rle_encode(bytes))
+    }
+
+    #[test]
+    fn rle_round_trips() {
+        assert_eq!(round_trip(&[]), []);
+        assert_eq!(round_trip(&[0; 100]), [0; 100]);
+        assert_eq!(round_trip(&[7; 100]), [7; 100]);
+
+        let mixed = [0, 0, 1, 2, 0, 3, 0, 0, 0, 4, 4];
+        assert_eq!(round_trip(&mixed), mixed);
+    }
+
+    #[test]
+    fn rle_splits_runs_longer_than_u16() {
+        let len = u16::MAX as usize * 2 + 5;
+        assert_eq!(round_trip(&vec![0; len]), vec![0; len]);
+        assert_eq!(round_trip(&vec![9; len]), vec![9; len]);
+
+        let mut mixed = vec![0; len];
+        mixed.extend(vec![1; len]);
+        mixed.extend(vec![0; 3]);
+        assert_eq!(round_trip(&mixed), mixed);
+    }
+
+    #[test]
+    fn rle_compresses_zeros() {
+        // A zero run and an empty literal run
+        assert_eq!(rle_encode(&[0; 1000]), [0xE8, 0x03, 0, 0]);
+    }
+
+    #[test]
+    fn xor_pads_the_shorter_side() {
+        assert_eq!(xor(&[1, 2, 3], &[1]), [0, 2, 3]);
+        assert_eq!(xor(&[1], &[1, 2, 3]), [0, 2, 3]);
+    }
+}
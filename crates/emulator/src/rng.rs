@@ -0,0 +1,54 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::state::{Reader, StateError, Writer};
+
+/// Small seedable random number generator behind `CXNN`
+///
+/// Uses SplitMix64, which accepts any seed and produces the same sequence on every
+/// platform, so a run can be reproduced from its seed alone.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct Rng {
+    seed: u64,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    /// Generator with a seed that differs between runs
+    pub fn from_entropy() -> Self {
+        Self::new(RandomState::new().build_hasher().finish())
+    }
+
+    /// Seed the generator was created with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub(crate) fn save(&self, out: &mut Writer) {
+        out.u64(self.seed);
+        out.u64(self.state);
+    }
+
+    pub(crate) fn load(input: &mut Reader) -> Result<Self, StateError> {
+        Ok(Self {
+            seed: input.u64()?,
+            state: input.u64()?,
+        })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
@@ -0,0 +1,341 @@
+use std::fmt;
+
+use crate::audio::{AudioSink, Buzzer};
+use crate::display::{Display, RenderTarget};
+use crate::quirks::{LoadStore, Quirks};
+use crate::rng::Rng;
+use crate::{Chip8, CpuState, XO_CHIP_MEMORY_SIZE};
+
+/// Reason a save state or replay couldn't be loaded
+#[derive(PartialEq, Eq, Clone)]
+pub enum StateError {
+    /// Data doesn't start with the expected magic number
+    UnknownFormat,
+    /// State was written by an incompatible version of the emulator
+    UnsupportedVersion { found: u16, expected: u16 },
+    /// Checksum doesn't match the contents
+    ChecksumMismatch,
+    /// Contents are truncated or describe an impossible machine
+    Malformed(&'static str),
+}
+
+impl fmt::Debug for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFormat => write!(f, "unrecognised file format"),
+            Self::UnsupportedVersion { found, expected } => write!(
+                f,
+                "version {} is not supported, expected version {}",
+                found, expected
+            ),
+            Self::ChecksumMismatch => write!(f, "file is corrupt, checksum mismatch"),
+            Self::Malformed(what) => write!(f, "malformed data: {}", what),
+        }
+    }
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl std::error::Error for StateError {}
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u16 = 5;
+/// Magic, version and checksum
+const HEADER_LEN: usize = 4 + 2 + 4;
+
+/// Prefix `payload` with a header identifying its format and guarding its contents
+pub(crate) fn seal(magic: &[u8; 4], version: u16, payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_LEN + payload.len());
+    data.extend_from_slice(magic);
+    data.extend_from_slice(&version.to_le_bytes());
+    data.extend_from_slice(&crc32(payload).to_le_bytes());
+    data.extend_from_slice(payload);
+    data
+}
+
+/// `sealed`, a save state or replay, with its payload replaced and checksummed again
+///
+/// Lets tests hand `load_state` and `Replay::from_bytes` payloads that pass the checksum
+/// but that the emulator would never write. Panics if `sealed` is shorter than a header.
+#[doc(hidden)]
+pub fn reseal(sealed: &[u8], payload: &[u8]) -> Vec<u8> {
+    let magic = sealed[0..4].try_into().unwrap();
+    let version = u16::from_le_bytes([sealed[4], sealed[5]]);
+    seal(magic, version, payload)
+}
+
+/// Check the header added by `seal`, returning the payload
+pub(crate) fn unseal<'a>(
+    magic: &[u8; 4],
+    version: u16,
+    data: &'a [u8],
+) -> Result<Reader<'a>, StateError> {
+    if data.len() < HEADER_LEN || &data[0..4] != magic {
+        return Err(StateError::UnknownFormat);
+    }
+    let found = u16::from_le_bytes([data[4], data[5]]);
+    if found != version {
+        return Err(StateError::UnsupportedVersion {
+            found,
+            expected: version,
+        });
+    }
+    let checksum = u32::from_le_bytes([data[6], data[7], data[8], data[9]]);
+    let payload = &data[HEADER_LEN..];
+    if crc32(payload) != checksum {
+        return Err(StateError::ChecksumMismatch);
+    }
+    Ok(Reader(payload))
+}
+
+/// CRC-32 as used by zip and PNG
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Little-endian encoder for save state contents
+#[derive(Default)]
+pub(crate) struct Writer(Vec<u8>);
+
+impl Writer {
+    pub(crate) fn u8(&mut self, val: u8) {
+        self.0.push(val);
+    }
+
+    pub(crate) fn u16(&mut self, val: u16) {
+        self.0.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, val: u32) {
+        self.0.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, val: u64) {
+        self.0.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub(crate) fn bytes(&mut self, val: &[u8]) {
+        self.0.extend_from_slice(val);
+    }
+
+    pub(crate) fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+/// Decoder for contents produced by `Writer`
+pub(crate) struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.0.len() < len {
+            return Err(StateError::Malformed("unexpected end of data"));
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    /// Check that everything has been read
+    pub(crate) fn finish(&self) -> Result<(), StateError> {
+        if !self.0.is_empty() {
+            return Err(StateError::Malformed("trailing data"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Malformed("invalid flag")),
+        }
+    }
+}
+
+pub(crate) fn write_quirks(out: &mut Writer, quirks: &Quirks) {
+    out.u8(quirks.shift_uses_vy as u8);
+    out.u8(match quirks.load_store {
+        LoadStore::Unchanged => 0,
+        LoadStore::IncrementByX => 1,
+        LoadStore::IncrementByXPlusOne => 2,
+    });
+    out.u8(quirks.jump_uses_vx as u8);
+    out.u8(quirks.logic_resets_vf as u8);
+    out.u8(quirks.wrap_sprites as u8);
+    out.u8(quirks.lores_wide_sprites as u8);
+}
+
+pub(crate) fn read_quirks(input: &mut Reader) -> Result<Quirks, StateError> {
+    Ok(Quirks {
+        shift_uses_vy: input.bool()?,
+        load_store: match input.u8()? {
+            0 => LoadStore::Unchanged,
+            1 => LoadStore::IncrementByX,
+            2 => LoadStore::IncrementByXPlusOne,
+            _ => return Err(StateError::Malformed("unknown load/store quirk")),
+        },
+        jump_uses_vx: input.bool()?,
+        logic_resets_vf: input.bool()?,
+        wrap_sprites: input.bool()?,
+        lores_wide_sprites: input.bool()?,
+    })
+}
+
+impl<T, A> Chip8<T, A>
+where
+    T: RenderTarget,
+    A: AudioSink,
+{
+    /// Snapshot the whole machine, display included, into a self-contained binary blob
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Writer::default();
+        write_quirks(&mut out, &self.quirks);
+        out.u32(self.memory.len() as u32);
+        out.bytes(&self.memory);
+        out.bytes(&self.registers);
+        out.u16(self.i);
+        out.u8(self.delay_timer);
+        out.u8(self.sound_timer);
+        out.u32(self.pc as u32);
+        out.u8(self.sp as u8);
+        for addr in self.stack {
+            out.u16(addr);
+        }
+        out.u32(self.cycles_per_frame as u32);
+        self.rng.save(&mut out);
+        for down in self.keys {
+            out.u8(down as u8);
+        }
+        out.u8(self.key_wait.unwrap_or(0xFF));
+        out.bytes(&self.rpl_flags);
+        match self.state {
+            CpuState::Running => out.bytes(&[0, 0]),
+            CpuState::WaitingForKey(reg) => out.bytes(&[1, reg]),
+            CpuState::Halted => out.bytes(&[2, 0]),
+        }
+        self.buzzer.save(&mut out);
+        out.u8(self.display.is_hires() as u8);
+        out.u8(self.display.planes());
+        out.bytes(self.display.framebuffer());
+
+        seal(MAGIC, VERSION, &out.into_inner())
+    }
+
+    /// Restore a snapshot taken by `save_state`
+    ///
+    /// The machine is left untouched if the state is rejected.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let mut input = unseal(MAGIC, VERSION, state)?;
+        let quirks = read_quirks(&mut input)?;
+        let memory_size = input.u32()? as usize;
+        if memory_size > XO_CHIP_MEMORY_SIZE {
+            return Err(StateError::Malformed("memory too large"));
+        }
+        let memory = input.bytes(memory_size)?.to_vec();
+        let registers = input.array()?;
+        let i = input.u16()?;
+        let delay_timer = input.u8()?;
+        let sound_timer = input.u8()?;
+        let pc = input.u32()? as usize;
+        let sp = input.u8()? as usize;
+        let mut stack = [0; 16];
+        for addr in stack.iter_mut() {
+            *addr = input.u16()?;
+        }
+        if sp > stack.len() {
+            return Err(StateError::Malformed("stack pointer out of range"));
+        }
+        let cycles_per_frame = input.u32()? as usize;
+        let rng = Rng::load(&mut input)?;
+        let mut keys = [false; 16];
+        for down in keys.iter_mut() {
+            *down = input.bool()?;
+        }
+        let key_wait = match input.u8()? {
+            0xFF => None,
+            key if key < 16 => Some(key),
+            _ => return Err(StateError::Malformed("invalid key")),
+        };
+        let rpl_flags = input.array()?;
+        let cpu_state = match input.array()? {
+            [0, _] => CpuState::Running,
+            [1, reg] if reg < 16 => CpuState::WaitingForKey(reg),
+            [2, _] => CpuState::Halted,
+            _ => return Err(StateError::Malformed("invalid CPU state")),
+        };
+        let buzzer = Buzzer::load(&mut input)?;
+        let hires = input.bool()?;
+        let planes = input.u8()?;
+        let (cols, rows) = if hires {
+            Display::<T>::HIRES
+        } else {
+            Display::<T>::LORES
+        };
+        let pixels = input.bytes(cols * rows)?.to_vec();
+        input.finish()?;
+        let mask = (1 << Display::<T>::PLANES) - 1;
+        if planes & !mask != 0 {
+            return Err(StateError::Malformed("unknown bitplanes selected"));
+        }
+        if pixels.iter().any(|&pixel| pixel & !mask != 0) {
+            return Err(StateError::Malformed("pixel outside the bitplanes"));
+        }
+
+        self.quirks = quirks;
+        self.memory = memory;
+        self.registers = registers;
+        self.i = i;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.pc = pc;
+        self.sp = sp;
+        self.stack = stack;
+        self.cycles_per_frame = cycles_per_frame;
+        self.rng = rng;
+        self.keys = keys;
+        self.key_wait = key_wait;
+        self.rpl_flags = rpl_flags;
+        self.state = cpu_state;
+        self.buzzer = buzzer;
+        self.display.restore(hires, planes, pixels);
+        Ok(())
+    }
+}
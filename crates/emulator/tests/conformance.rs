@@ -0,0 +1,231 @@
+//! Runs the test ROMs under `tests/fixtures` headlessly and compares the final screen
+//! against a golden image stored next to them.
+//!
+//! Goldens are plain text, one line per row with `.` for an unlit pixel and the plane
+//! mask for a lit one.
+//!
+//! The community suites under `tests/fixtures/community` are the conformance check, with
+//! goldens taken from a reference interpreter, never from this emulator. The ROMs written
+//! for this repository are extra regression checks, and `BLESS=1` rewrites only their
+//! goldens from the current output. `tests/fixtures/README.md` explains both.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use emulator::display::Display;
+use emulator::headless::HeadlessTarget;
+use emulator::quirks::Quirks;
+use emulator::{Chip8, CpuState, Keycode, XO_CHIP_MEMORY_SIZE};
+
+/// Long enough for every fixture to reach its final screen at the default speed
+const FRAMES: usize = 120;
+
+/// The community quirks test times a few frames for each quirk, so give it longer
+const COMMUNITY_FRAMES: usize = 600;
+
+/// The community suites read the test or platform to run from here, skipping their menus
+const MENU_CHOICE: usize = 0x1FF;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+fn emu(quirks: Quirks) -> Chip8<HeadlessTarget> {
+    let mut emu = Chip8::new(Display::new(HeadlessTarget::new()), quirks);
+    emu.set_seed(0);
+    emu
+}
+
+/// Run `rom` for `FRAMES` frames, with `keys` giving the key mask from a frame onwards
+fn run(rom: &str, quirks: Quirks, keys: &[(usize, u16)]) -> Chip8<HeadlessTarget> {
+    let mut emu = emu(quirks);
+    emu.load(fixture(rom)).unwrap();
+    run_frames(&mut emu, rom, FRAMES, keys);
+    emu
+}
+
+/// Run one of the community ROMs, choosing `choice` from its menu
+fn run_community(
+    rom: &str,
+    mut emu: Chip8<HeadlessTarget>,
+    choice: Option<u8>,
+    keys: &[(usize, u16)],
+) -> Chip8<HeadlessTarget> {
+    let path = fixture("community").join(rom);
+    emu.load(path.clone())
+        .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    if let Some(choice) = choice {
+        emu.memory_mut()[MENU_CHOICE] = choice;
+    }
+    run_frames(&mut emu, rom, COMMUNITY_FRAMES, keys);
+    emu
+}
+
+fn run_frames(emu: &mut Chip8<HeadlessTarget>, rom: &str, frames: usize, keys: &[(usize, u16)]) {
+    for frame in 0..frames {
+        if let Some(&(_, mask)) = keys.iter().rev().find(|(start, _)| *start <= frame) {
+            emu.set_key_mask(mask);
+        }
+        match emu.run_frame() {
+            Ok(CpuState::Halted) => break,
+            Ok(_) => {}
+            Err(err) => panic!("{} crashed on frame {}: {}", rom, frame, err),
+        }
+    }
+}
+
+fn render(display: &Display<HeadlessTarget>) -> String {
+    let (cols, _) = display.size();
+    display
+        .framebuffer()
+        .chunks(cols)
+        .map(|row| {
+            let mut line: String = row
+                .iter()
+                .map(|&pixel| match pixel {
+                    0 => '.',
+                    planes => char::from_digit(planes as u32, 16).unwrap(),
+                })
+                .collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+/// Compare the screen of `emu` against `golden`, or overwrite it when blessing
+fn check_golden(emu: &Chip8<HeadlessTarget>, golden: &str) {
+    let path = fixture(golden);
+    if std::env::var_os("BLESS").is_some() {
+        fs::write(&path, render(&emu.display)).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("{}: {}, run with BLESS=1", path.display(), err));
+    compare(emu, golden, &expected);
+}
+
+/// Compare the screen of `emu` against a golden taken from a reference interpreter
+fn check_reference(emu: &Chip8<HeadlessTarget>, golden: &str) {
+    let path = fixture("community").join(golden);
+    let expected = fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "{}: {}, take it from a reference interpreter",
+            path.display(),
+            err
+        )
+    });
+    compare(emu, golden, &expected);
+}
+
+fn compare(emu: &Chip8<HeadlessTarget>, golden: &str, expected: &str) {
+    let actual = render(&emu.display);
+    if actual != expected {
+        panic!(
+            "screen doesn't match {}\n\nexpected:\n{}\nactual:\n{}",
+            golden, expected, actual
+        );
+    }
+}
+
+#[test]
+fn opcodes() {
+    let emu = run("opcodes.ch8", Quirks::COSMAC_VIP, &[]);
+    check_golden(&emu, "opcodes.txt");
+}
+
+#[test]
+fn flags() {
+    let emu = run("flags.ch8", Quirks::COSMAC_VIP, &[]);
+    check_golden(&emu, "flags.txt");
+}
+
+#[test]
+fn quirks_cosmac_vip() {
+    let emu = run("quirks.ch8", Quirks::COSMAC_VIP, &[]);
+    check_golden(&emu, "quirks-vip.txt");
+}
+
+#[test]
+fn quirks_chip48() {
+    let emu = run("quirks.ch8", Quirks::CHIP48, &[]);
+    check_golden(&emu, "quirks-chip48.txt");
+}
+
+#[test]
+fn quirks_schip() {
+    let emu = run("quirks.ch8", Quirks::SCHIP, &[]);
+    check_golden(&emu, "quirks-schip.txt");
+}
+
+#[test]
+fn quirks_modern() {
+    let emu = run("quirks.ch8", Quirks::MODERN, &[]);
+    check_golden(&emu, "quirks-modern.txt");
+}
+
+#[test]
+fn keypad() {
+    let keys = [
+        (10, Keycode::Num1.mask()),
+        (20, 0),
+        (30, Keycode::A.mask()),
+        (40, Keycode::A.mask() | Keycode::F.mask()),
+        (50, Keycode::F.mask()),
+        (60, 0),
+        (70, Keycode::Num0.mask()),
+        (80, 0),
+    ];
+    let emu = run("keypad.ch8", Quirks::COSMAC_VIP, &keys);
+    check_golden(&emu, "keypad.txt");
+}
+
+#[test]
+#[ignore = "community ROMs not vendored, see tests/fixtures/community/README.md"]
+fn community_corax_plus() {
+    let emu = run_community("3-corax+.ch8", emu(Quirks::COSMAC_VIP), None, &[]);
+    check_reference(&emu, "3-corax+.txt");
+}
+
+#[test]
+#[ignore = "community ROMs not vendored, see tests/fixtures/community/README.md"]
+fn community_flags() {
+    let emu = run_community("4-flags.ch8", emu(Quirks::COSMAC_VIP), None, &[]);
+    check_reference(&emu, "4-flags.txt");
+}
+
+#[test]
+#[ignore = "community ROMs not vendored, see tests/fixtures/community/README.md"]
+fn community_quirks_chip8() {
+    let emu = run_community("5-quirks.ch8", emu(Quirks::COSMAC_VIP), Some(1), &[]);
+    check_reference(&emu, "5-quirks-chip8.txt");
+}
+
+#[test]
+#[ignore = "community ROMs not vendored, see tests/fixtures/community/README.md"]
+fn community_quirks_schip() {
+    let emu = run_community("5-quirks.ch8", emu(Quirks::SCHIP), Some(2), &[]);
+    check_reference(&emu, "5-quirks-schip.txt");
+}
+
+#[test]
+#[ignore = "community ROMs not vendored, see tests/fixtures/community/README.md"]
+fn community_quirks_xo_chip() {
+    let mut xo_chip = emu(Quirks::XO_CHIP);
+    xo_chip.set_memory_size(XO_CHIP_MEMORY_SIZE);
+    let emu = run_community("5-quirks.ch8", xo_chip, Some(3), &[]);
+    check_reference(&emu, "5-quirks-xochip.txt");
+}
+
+#[test]
+#[ignore = "community ROMs not vendored, see tests/fixtures/community/README.md"]
+fn community_keypad() {
+    // FX0A, which passes once a key is pressed and released
+    let keys = [(60, Keycode::Num5.mask()), (70, 0)];
+    let emu = run_community("6-keypad.ch8", emu(Quirks::COSMAC_VIP), Some(3), &keys);
+    check_reference(&emu, "6-keypad.txt");
+}
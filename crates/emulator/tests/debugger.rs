@@ -0,0 +1,255 @@
+use emulator::debugger::{
+    Access, Condition, Debugger, OpcodeBreak, Register, Snapshot, Stop, Watchpoint,
+};
+use emulator::headless::HeadlessTarget;
+use emulator::instruction::Instruction::{self, *};
+use emulator::quirks::Quirks;
+use emulator::{Chip8, CpuState};
+
+/// Machine running `program` from 0x200
+fn emu(program: &[Instruction]) -> Chip8<HeadlessTarget> {
+    let rom: Vec<u8> = program.iter().flat_map(|instr| instr.to_bytes()).collect();
+    let mut emu = Chip8::headless(Quirks::COSMAC_VIP);
+    emu.load_bytes(&rom).unwrap();
+    emu
+}
+
+/// Counts V0 up forever
+const COUNTER: [Instruction; 2] = [AddByteToReg(0, 1), Jump(0x200)];
+
+#[test]
+fn step_runs_one_instruction() {
+    let mut emu = emu(&COUNTER);
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x200);
+
+    assert_eq!(debugger.step(&mut emu), Ok(None));
+    assert_eq!(emu.pc(), 0x202);
+    assert_eq!(emu.registers()[0], 1);
+}
+
+#[test]
+fn step_finishes_frames() {
+    let mut emu = emu(&[SetRegToByte(0, 5), SetDelayToReg(0), Jump(0x204)]);
+    emu.set_cycles_per_frame(4);
+    let mut debugger = Debugger::new();
+    for _ in 0..3 {
+        debugger.step(&mut emu).unwrap();
+    }
+    assert_eq!(emu.delay_timer(), 5);
+    debugger.step(&mut emu).unwrap();
+    assert_eq!(emu.delay_timer(), 4);
+}
+
+#[test]
+fn breakpoint_pauses_and_resumes() {
+    let mut emu = emu(&COUNTER);
+    let mut debugger = Debugger::new();
+    assert!(debugger.add_breakpoint(0x202));
+    assert!(!debugger.add_breakpoint(0x202));
+
+    assert_eq!(
+        debugger.run_frame(&mut emu),
+        Ok(Some(Stop::Breakpoint(0x202)))
+    );
+    assert_eq!(emu.pc(), 0x202);
+    assert_eq!(emu.registers()[0], 1);
+
+    // Resuming runs the instruction at the breakpoint, pausing again next time round
+    assert_eq!(
+        debugger.run_frame(&mut emu),
+        Ok(Some(Stop::Breakpoint(0x202)))
+    );
+    assert_eq!(emu.registers()[0], 2);
+
+    assert!(debugger.remove_breakpoint(0x202));
+    assert_eq!(debugger.run_frame(&mut emu), Ok(None));
+}
+
+#[test]
+fn run_frame_finishes_the_frame_once() {
+    let mut emu = emu(&COUNTER);
+    emu.set_cycles_per_frame(10);
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x200);
+
+    // Five passes round the loop make up the frame, however often it pauses
+    let mut stops = 0;
+    while debugger.run_frame(&mut emu).unwrap().is_some() {
+        stops += 1;
+    }
+    assert_eq!(stops, 5);
+    assert_eq!(emu.registers()[0], 5);
+}
+
+#[test]
+fn write_watchpoints() {
+    let program = [
+        SetI(0x300),
+        SetRegToByte(0, 123),
+        StoreNumberFromRegToI(0),
+        SetI(0x310),
+        StoreRegsToMem(2),
+        Jump(0x20A),
+    ];
+    let mut emu = emu(&program);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::write(0x302, 1));
+    debugger.add_watchpoint(Watchpoint::write(0x311, 4));
+
+    let stop = debugger.run_frame(&mut emu).unwrap();
+    let access = Access::Write;
+    assert_eq!(
+        stop,
+        Some(Stop::Watchpoint {
+            pc: 0x204,
+            addr: 0x302,
+            access
+        })
+    );
+    assert_eq!(emu.memory()[0x302], 3);
+
+    let stop = debugger.run_frame(&mut emu).unwrap();
+    assert_eq!(
+        stop,
+        Some(Stop::Watchpoint {
+            pc: 0x208,
+            addr: 0x311,
+            access
+        })
+    );
+}
+
+#[test]
+fn read_watchpoints() {
+    let program = [
+        SetI(0x300),
+        StoreRegsToMem(0),
+        SetI(0x300),
+        DrawSprite(0, 0, 1),
+        Jump(0x208),
+    ];
+    let mut emu = emu(&program);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::read(0x300, 1));
+
+    let stop = debugger.run_frame(&mut emu).unwrap();
+    let access = Access::Read;
+    assert_eq!(
+        stop,
+        Some(Stop::Watchpoint {
+            pc: 0x206,
+            addr: 0x300,
+            access
+        })
+    );
+
+    assert_eq!(
+        debugger.remove_watchpoint(0),
+        Some(Watchpoint::read(0x300, 1))
+    );
+    assert!(debugger.watchpoints().is_empty());
+}
+
+#[test]
+fn register_conditions() {
+    let mut emu = emu(&COUNTER);
+    let mut debugger = Debugger::new();
+    let condition = Condition::Equals(Register::V(0), 3);
+    debugger.add_condition(condition);
+
+    let stop = debugger.run_frame(&mut emu).unwrap();
+    assert_eq!(
+        stop,
+        Some(Stop::Condition {
+            pc: 0x200,
+            condition
+        })
+    );
+    assert_eq!(emu.registers()[0], 3);
+
+    debugger.remove_condition(0);
+    let condition = Condition::Changes(Register::I);
+    debugger.add_condition(condition);
+    assert_eq!(debugger.run_frame(&mut emu), Ok(None));
+}
+
+#[test]
+fn opcode_breaks() {
+    let mut emu = emu(&[SetRegToByte(0, 1), ClearDisplay, Jump(0x200)]);
+    let mut debugger = Debugger::new();
+    debugger.add_opcode_break("00E0".parse().unwrap());
+
+    let stop = debugger.run_frame(&mut emu).unwrap();
+    assert_eq!(
+        stop,
+        Some(Stop::Opcode {
+            pc: 0x202,
+            opcode: 0x00E0
+        })
+    );
+    assert_eq!(emu.pc(), 0x202);
+}
+
+#[test]
+fn stop_at_the_next_instruction() {
+    let mut emu = emu(&[SetRegToByte(0, 1), ClearDisplay, Jump(0x200)]);
+    let mut debugger = Debugger::new();
+    assert_eq!(debugger.stop_at(&emu), None);
+
+    debugger.add_opcode_break("00E0".parse().unwrap());
+    debugger.step(&mut emu).unwrap();
+    let cls = Some(Stop::Opcode {
+        pc: 0x202,
+        opcode: 0x00E0,
+    });
+    assert_eq!(debugger.stop_at(&emu), cls);
+
+    // Breakpoints come first, and checking doesn't change what run_frame stops at
+    debugger.add_breakpoint(0x202);
+    assert_eq!(debugger.stop_at(&emu), Some(Stop::Breakpoint(0x202)));
+    debugger.remove_breakpoint(0x202);
+    assert_eq!(debugger.run_frame(&mut emu).unwrap(), cls);
+}
+
+#[test]
+fn opcode_patterns() {
+    let draw: OpcodeBreak = "DXYN".parse().unwrap();
+    assert_eq!(
+        draw,
+        OpcodeBreak {
+            opcode: 0xD000,
+            mask: 0xF000
+        }
+    );
+    assert!(draw.matches(0xD125));
+    assert!(!draw.matches(0x1D00));
+
+    let load: OpcodeBreak = "fx65".parse().unwrap();
+    assert!(load.matches(0xF365));
+    assert!(!load.matches(0xF355));
+
+    assert!("D12".parse::<OpcodeBreak>().is_err());
+    assert!("D1?3".parse::<OpcodeBreak>().is_err());
+}
+
+#[test]
+fn snapshot() {
+    let mut emu = emu(&[
+        CallSubroutine(0x204),
+        Jump(0x200),
+        SetI(0x123),
+        SetRegToByte(4, 9),
+    ]);
+    let mut debugger = Debugger::new();
+    for _ in 0..3 {
+        debugger.step(&mut emu).unwrap();
+    }
+
+    let snapshot = Snapshot::of(&emu);
+    assert_eq!(snapshot.pc, 0x208);
+    assert_eq!(snapshot.i, 0x123);
+    assert_eq!(snapshot.registers[4], 9);
+    assert_eq!(snapshot.stack, vec![0x202]);
+    assert_eq!(snapshot.state, CpuState::Running);
+}
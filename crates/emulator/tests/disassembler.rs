@@ -0,0 +1,174 @@
+//! Rendering of every instruction in both syntaxes, and the linear sweep over a ROM
+
+use emulator::disassembler::{disassemble, Line, Syntax};
+use emulator::instruction::Instruction::{self, *};
+
+/// Every instruction with its Cowgod and Octo rendering
+const MNEMONICS: &[(Instruction, &str, &str)] = &[
+    (ClearDisplay, "CLS", "clear"),
+    (ReturnFromSubroutine, "RET", "return"),
+    (Jump(0x2A4), "JP #2A4", "jump 0x2A4"),
+    (CallSubroutine(0x30E), "CALL #30E", ":call 0x30E"),
+    (SkipRegEqByte(0x1, 0x0A), "SE V1, #0A", "if v1 != 0x0A then"),
+    (
+        SkipRegNotEqByte(0x2, 0xFF),
+        "SNE V2, #FF",
+        "if v2 == 0xFF then",
+    ),
+    (SkipRegEqReg(0x3, 0x4), "SE V3, V4", "if v3 != v4 then"),
+    (SetRegToByte(0x5, 0x07), "LD V5, #07", "v5 := 0x07"),
+    (AddByteToReg(0x6, 0x10), "ADD V6, #10", "v6 += 0x10"),
+    (MoveValue(0x7, 0x8), "LD V7, V8", "v7 := v8"),
+    (OrRegs(0x9, 0xA), "OR V9, VA", "v9 |= vA"),
+    (AndRegs(0xB, 0xC), "AND VB, VC", "vB &= vC"),
+    (XorRegs(0xD, 0xE), "XOR VD, VE", "vD ^= vE"),
+    (AddRegs(0xF, 0x0), "ADD VF, V0", "vF += v0"),
+    (SubRegs(0x1, 0x2), "SUB V1, V2", "v1 -= v2"),
+    (ShiftRight(0x3, 0x4), "SHR V3, V4", "v3 >>= v4"),
+    (ReverseSubRegs(0x5, 0x6), "SUBN V5, V6", "v5 =- v6"),
+    (ShiftLeft(0x7, 0x8), "SHL V7, V8", "v7 <<= v8"),
+    (SkipRegNotEqReg(0x9, 0xA), "SNE V9, VA", "if v9 == vA then"),
+    (SetI(0x123), "LD I, #123", "i := 0x123"),
+    (JumpV0PlusByte(0x456), "JP V0, #456", "jump0 0x456"),
+    (
+        SetRegToRandPlusByte(0xB, 0x3F),
+        "RND VB, #3F",
+        "vB := random 0x3F",
+    ),
+    (
+        DrawSprite(0xC, 0xD, 0x5),
+        "DRW VC, VD, #5",
+        "sprite vC vD 0x5",
+    ),
+    (SkipIfKey(0xE), "SKP VE", "if vE -key then"),
+    (SkipIfNotKey(0xF), "SKNP VF", "if vF key then"),
+    (LoadDelayToReg(0x0), "LD V0, DT", "v0 := delay"),
+    (LoadKeyToReg(0x1), "LD V1, K", "v1 := key"),
+    (SetDelayToReg(0x2), "LD DT, V2", "delay := v2"),
+    (SetSoundToReg(0x3), "LD ST, V3", "buzzer := v3"),
+    (AddRegToI(0x4), "ADD I, V4", "i += v4"),
+    (SetIToDigitSpriteLoc(0x5), "LD F, V5", "i := hex v5"),
+    (StoreNumberFromRegToI(0x6), "LD B, V6", "bcd v6"),
+    (StoreRegsToMem(0x7), "LD [I], V7", "save v7"),
+    (LoadRegsFromMem(0x8), "LD V8, [I]", "load v8"),
+    (ScrollDown(0x4), "SCD #4", "scroll-down 0x4"),
+    (ScrollRight, "SCR", "scroll-right"),
+    (ScrollLeft, "SCL", "scroll-left"),
+    (Exit, "EXIT", "exit"),
+    (LowResolution, "LOW", "lores"),
+    (HighResolution, "HIGH", "hires"),
+    (SetIToBigDigitSpriteLoc(0x9), "LD HF, V9", "i := bighex v9"),
+    (StoreRegsToFlags(0xA), "LD R, VA", "saveflags vA"),
+    (LoadRegsFromFlags(0xB), "LD VB, R", "loadflags vB"),
+    (StoreRegRange(0x1, 0x4), "SAVE V1, V4", "save v1 - v4"),
+    (LoadRegRange(0x2, 0x5), "LOAD V2, V5", "load v2 - v5"),
+    (ScrollUp(0x3), "SCU #3", "scroll-up 0x3"),
+    (SetILong(0xBEEF), "LD I, LONG #BEEF", "i := long 0xBEEF"),
+    (SelectPlanes(0x3), "PLANE #3", "plane 0x3"),
+    (LoadAudioPattern, "AUDIO", "audio"),
+    (SetPitchToReg(0xC), "PITCH VC", "pitch := vC"),
+];
+
+#[test]
+fn cowgod_mnemonics() {
+    for &(instruction, cowgod, _) in MNEMONICS {
+        assert_eq!(instruction.mnemonic(Syntax::Cowgod), cowgod);
+        assert_eq!(instruction.to_string(), cowgod);
+    }
+}
+
+#[test]
+fn octo_mnemonics() {
+    for &(instruction, _, octo) in MNEMONICS {
+        assert_eq!(instruction.mnemonic(Syntax::Octo), octo);
+    }
+}
+
+#[test]
+fn mnemonics_of_decoded_opcodes() {
+    for &(instruction, cowgod, _) in MNEMONICS {
+        let decoded = Instruction::decode(&instruction.to_bytes()).unwrap();
+        assert_eq!(decoded.mnemonic(Syntax::Cowgod), cowgod);
+    }
+}
+
+#[test]
+fn code_and_data() {
+    let rom = [
+        0x00, 0xE0, // CLS
+        0x51, 0x21, // 5XY1 isn't an instruction
+        0xF0, 0x00, 0x12, 0x34, // LD I, LONG #1234 takes two words
+        0xA2, 0x00, // LD I, #200
+        0xFF, // odd byte left over
+    ];
+    let lines = disassemble(&rom, 0x200);
+
+    assert_eq!(
+        lines,
+        vec![
+            Line::Code {
+                addr: 0x200,
+                opcode: 0x00E0,
+                instruction: ClearDisplay,
+            },
+            Line::Data {
+                addr: 0x202,
+                bytes: vec![0x51, 0x21],
+            },
+            Line::Code {
+                addr: 0x204,
+                opcode: 0xF000,
+                instruction: SetILong(0x1234),
+            },
+            Line::Code {
+                addr: 0x208,
+                opcode: 0xA200,
+                instruction: SetI(0x200),
+            },
+            Line::Data {
+                addr: 0x20A,
+                bytes: vec![0xFF],
+            },
+        ]
+    );
+
+    let cowgod: Vec<_> = lines
+        .iter()
+        .map(|line| line.render(Syntax::Cowgod))
+        .collect();
+    assert_eq!(
+        cowgod,
+        [
+            "200: 00E0  CLS",
+            "202: 5121  DB #51, #21",
+            "204: F000  LD I, LONG #1234",
+            "208: A200  LD I, #200",
+            "20A: FF    DB #FF",
+        ]
+    );
+
+    let octo: Vec<_> = lines.iter().map(|line| line.render(Syntax::Octo)).collect();
+    assert_eq!(
+        octo,
+        [
+            "200: 00E0  clear",
+            "202: 5121  0x51 0x21",
+            "204: F000  i := long 0x1234",
+            "208: A200  i := 0x200",
+            "20A: FF    0xFF",
+        ]
+    );
+}
+
+#[test]
+fn empty_rom() {
+    assert!(disassemble(&[], 0x200).is_empty());
+}
+
+#[test]
+fn origin_wraps() {
+    let lines = disassemble(&[0x00, 0xE0, 0x00, 0xEE], 0xFFFE);
+    let addrs: Vec<_> = lines.iter().map(Line::addr).collect();
+    assert_eq!(addrs, [0xFFFE, 0x0000]);
+    assert_eq!(lines[1].render(Syntax::Cowgod), "000: 00EE  RET");
+}
@@ -0,0 +1,18 @@
+; Shared by the self-checking fixtures
+;
+; Compare V0 against V1, drawing a tick at VA, VB if they match and a cross if
+; they don't, then move along to the next mark. Clobbers I and VF.
+
+check:  LD I, cross
+        SNE V0, V1
+        LD I, tick
+        DRW VA, VB, 4
+        ADD VA, 5
+        SE VA, 61
+        RET
+        LD VA, 1
+        ADD VB, 5
+        RET
+
+tick:   SPRITE "...#", "..#.", "#.#.", ".#.."
+cross:  SPRITE "#..#", ".##.", ".##.", "#..#"
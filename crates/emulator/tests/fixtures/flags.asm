@@ -0,0 +1,123 @@
+; Self-checking test of the carry, borrow and shift flags at the edges
+;
+; Uses the same ticks and crosses as opcodes.asm, see check.asm.
+
+        LD VA, 1
+        LD VB, 1
+
+; 8XY4, carry out of exactly #100
+        LD V2, #FF
+        LD V3, #01
+        ADD V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, 0
+        CALL check
+        LD V0, V4
+        LD V1, 1
+        CALL check
+
+; 8XY4, largest sum without a carry
+        LD V2, #FF
+        LD V3, 0
+        ADD V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #FF
+        CALL check
+        LD V0, V4
+        LD V1, 0
+        CALL check
+
+; 8XY5, smallest borrow
+        LD V2, 0
+        LD V3, 1
+        SUB V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #FF
+        CALL check
+        LD V0, V4
+        LD V1, 0
+        CALL check
+
+; 8XY7, smallest borrow
+        LD V2, 1
+        LD V3, 0
+        SUBN V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #FF
+        CALL check
+        LD V0, V4
+        LD V1, 0
+        CALL check
+
+; 8XY6, shifting out a zero
+        LD V2, #FE
+        LD V3, #FE
+        SHR V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #7F
+        CALL check
+        LD V0, V4
+        LD V1, 0
+        CALL check
+
+; 8XYE, shifting out a zero
+        LD V2, #7F
+        LD V3, #7F
+        SHL V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #FE
+        CALL check
+        LD V0, V4
+        LD V1, 0
+        CALL check
+
+; 8XY4 with VF as the operand
+        LD VF, #10
+        LD V2, #20
+        ADD V2, VF
+        LD V0, V2
+        LD V1, #30
+        CALL check
+
+; 8XY5 and 8XY7 with equal operands don't borrow
+        LD V2, #42
+        LD V3, #42
+        SUB V2, V3
+        LD V0, VF
+        LD V1, 1
+        CALL check
+        LD V2, #42
+        SUBN V2, V3
+        LD V0, VF
+        LD V1, 1
+        CALL check
+
+; With VF as the destination it ends up holding the flag
+        LD VF, #FF
+        LD V2, #01
+        ADD VF, V2
+        LD V0, VF
+        LD V1, 1
+        CALL check
+        LD VF, #10
+        LD V2, #30
+        SUB VF, V2
+        LD V0, VF
+        LD V1, 0
+        CALL check
+        LD VF, #81
+        LD V2, #81
+        SHL VF, V2
+        LD V0, VF
+        LD V1, 1
+        CALL check
+
+done:   JP done
+
+        INCLUDE "check.asm"
@@ -0,0 +1,28 @@
+; Echoes every key pressed as a hex digit
+;
+; Waits for key 1 to be held with EX9E and marks it with a 1 in the top right
+; corner, then waits for it to be let go with EXA1 and puts a 0 under the 1.
+; Only then are keys echoed with FX0A, so key 1 never shows up as a digit.
+
+        LD V1, 1
+hold:   SKP V1
+        JP hold
+        LD F, V1
+        LD V3, 58
+        LD V4, 1
+        DRW V3, V4, 5
+
+        LD V5, 0
+release: SKNP V1
+        JP release
+        LD F, V5
+        LD V4, 8
+        DRW V3, V4, 5
+
+        LD VA, 1
+        LD VB, 8
+loop:   LD V0, K
+        LD F, V0
+        DRW VA, VB, 5
+        ADD VA, 5
+        JP loop
@@ -0,0 +1,259 @@
+; Self-checking opcode test in the spirit of corax+
+;
+; Each check leaves its result in V0 and the expected value in V1 and calls
+; `check`, which draws a tick when they match and a cross when they don't.
+; VA and VB hold the position of the next mark.
+
+        LD VA, 1
+        LD VB, 1
+
+; 3XNN and 6XNN
+        LD V0, #42
+        LD V1, #42
+        CALL check
+
+; 7XNN wraps and leaves VF alone
+        LD VF, 7
+        LD V2, #FF
+        ADD V2, 2
+        LD V0, VF
+        LD V1, 7
+        CALL check
+        LD V0, V2
+        LD V1, 1
+        CALL check
+
+; 8XY0
+        LD V2, #33
+        LD V0, V2
+        LD V1, #33
+        CALL check
+
+; 8XY1, 8XY2 and 8XY3
+        LD V0, #0F
+        LD V2, #F0
+        OR V0, V2
+        LD V1, #FF
+        CALL check
+        LD V0, #3C
+        LD V2, #0F
+        AND V0, V2
+        LD V1, #0C
+        CALL check
+        LD V0, #FF
+        LD V2, #0F
+        XOR V0, V2
+        LD V1, #F0
+        CALL check
+
+; 8XY4 with and without carry
+        LD V2, #F0
+        LD V3, #20
+        ADD V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #10
+        CALL check
+        LD V0, V4
+        LD V1, 1
+        CALL check
+        LD V2, #10
+        LD V3, #20
+        ADD V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #30
+        CALL check
+        LD V0, V4
+        LD V1, 0
+        CALL check
+
+; 8XY5 with and without borrow
+        LD V2, #30
+        LD V3, #10
+        SUB V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #20
+        CALL check
+        LD V0, V4
+        LD V1, 1
+        CALL check
+        LD V2, #10
+        LD V3, #30
+        SUB V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #E0
+        CALL check
+        LD V0, V4
+        LD V1, 0
+        CALL check
+
+; 8XY7
+        LD V2, #10
+        LD V3, #30
+        SUBN V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #20
+        CALL check
+        LD V0, V4
+        LD V1, 1
+        CALL check
+
+; 8XY6 and 8XYE, with VX and VY equal so the shift quirk doesn't matter
+        LD V2, #05
+        LD V3, #05
+        SHR V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #02
+        CALL check
+        LD V0, V4
+        LD V1, 1
+        CALL check
+        LD V2, #81
+        LD V3, #81
+        SHL V2, V3
+        LD V4, VF
+        LD V0, V2
+        LD V1, #02
+        CALL check
+        LD V0, V4
+        LD V1, 1
+        CALL check
+
+; 4XNN, 5XY0 and 9XY0
+        LD V2, 5
+        LD V3, 5
+        LD V0, 0
+        SNE V2, 6
+        LD V0, 1
+        LD V1, 0
+        CALL check
+        LD V0, 0
+        SE V2, V3
+        LD V0, 1
+        LD V1, 0
+        CALL check
+        LD V0, 0
+        SNE V2, V3
+        LD V0, 1
+        LD V1, 1
+        CALL check
+
+; 2NNN and 00EE
+        LD V0, 0
+        CALL set_v0
+        LD V1, #77
+        CALL check
+
+; ANNN, FX1E and FX65
+        LD I, data
+        LD V2, 2
+        ADD I, V2
+        LD V0, [I]
+        LD V1, #33
+        CALL check
+
+; FX55 and FX65 round trip
+        LD I, scratch
+        LD V0, #5A
+        LD V1, #A5
+        LD [I], V1
+        LD V0, 0
+        LD V1, 0
+        LD I, scratch
+        LD V1, [I]
+        LD V2, V1
+        LD V1, #5A
+        CALL check
+        LD V0, V2
+        LD V1, #A5
+        CALL check
+
+; FX33
+        LD V2, 234
+        LD I, scratch
+        LD B, V2
+        LD V2, [I]
+        LD V3, V1
+        LD V4, V2
+        LD V1, 2
+        CALL check
+        LD V0, V3
+        LD V1, 3
+        CALL check
+        LD V0, V4
+        LD V1, 4
+        CALL check
+
+; FX29
+        LD V2, #A
+        LD F, V2
+        LD V0, [I]
+        LD V1, #F0
+        CALL check
+
+; FX15 and FX07
+        LD V2, #20
+        LD DT, V2
+        LD V0, DT
+        LD V1, #20
+        CALL check
+
+; BNNN, jumping to the third entry of the table
+        LD V2, 0
+        LD V0, 4
+        JP V0, jumps
+jumps:  JP jumped
+        JP jumped
+        LD V2, 1
+jumped: LD V0, V2
+        LD V1, 1
+        CALL check
+
+; CXNN with a zero mask
+        RND V0, 0
+        LD V1, 0
+        CALL check
+
+; EX9E and EXA1 with nothing held
+        LD V2, 5
+        LD V0, 0
+        SKP V2
+        LD V0, 1
+        LD V1, 1
+        CALL check
+        LD V0, 0
+        SKNP V2
+        LD V0, 1
+        LD V1, 0
+        CALL check
+
+; DXYN collision
+        LD I, block
+        LD V2, 60
+        LD V3, 28
+        DRW V2, V3, 1
+        LD V4, VF
+        DRW V2, V3, 1
+        LD V5, VF
+        LD V0, V4
+        LD V1, 0
+        CALL check
+        LD V0, V5
+        LD V1, 1
+        CALL check
+
+done:   JP done
+
+set_v0: LD V0, #77
+        RET
+
+        INCLUDE "check.asm"
+
+block:  SPRITE "####"
+data:   DB #11, #22, #33
+scratch: DB 0, 0, 0
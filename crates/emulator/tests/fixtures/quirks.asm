@@ -0,0 +1,74 @@
+; Shows how the interpreter handles each quirk as hex digits, one row per quirk
+;
+; Row 1: VF after 8XY1, 0 if logic resets VF and 5 if it doesn't
+; Row 2: result of shifting VX=#10 right by 8XY6 with VY=#4, 2 if VY is shifted
+;        and 8 if VX is
+; Row 3: the three bytes after FX55 stores 1, 2 and then stores 9 again at
+;        wherever I was left, so 9 lands on the byte I pointed at
+; Row 4: offset taken by BNNN with V0=2 and VX=6
+; Row 5: an 8 pixel bar drawn 4 pixels from the right edge, clipped or wrapped
+
+        LD VA, 1
+        LD VB, 1
+
+        LD V0, 1
+        LD VF, 5
+        OR V0, V0
+        LD V0, VF
+        CALL digit
+
+        LD VA, 1
+        LD VB, 8
+        LD V2, #10
+        LD V3, #04
+        SHR V2, V3
+        LD V0, V2
+        CALL digit
+
+        LD VA, 1
+        LD VB, 15
+        LD V0, 1
+        LD V1, 2
+        LD I, scratch
+        LD [I], V1
+        LD V0, 9
+        LD [I], V0
+        LD I, scratch
+        LD V2, [I]
+        LD V3, V1
+        LD V4, V2
+        CALL digit
+        LD V0, V3
+        CALL digit
+        LD V0, V4
+        CALL digit
+
+        LD VA, 1
+        LD VB, 22
+        LD V0, 2
+        LD V1, 6
+        LD V2, 6
+        LD V3, 6
+        JP V0, jumps
+jumps:  LD V5, 0
+        LD V5, 2
+        JP jumped
+        LD V5, 6
+jumped: LD V0, V5
+        CALL digit
+
+        LD I, bar
+        LD V0, 60
+        LD V1, 29
+        DRW V0, V1, 1
+
+done:   JP done
+
+; Draw the digit in V0 at VA, VB and move right
+digit:  LD F, V0
+        DRW VA, VB, 5
+        ADD VA, 5
+        RET
+
+bar:    SPRITE "########"
+scratch: DB 0, 0, 0
@@ -0,0 +1,661 @@
+//! One test per `Instruction`, executed on a headless machine through the public API
+
+use emulator::audio::WavSink;
+use emulator::display::Display;
+use emulator::headless::HeadlessTarget;
+use emulator::instruction::Instruction::{self, *};
+use emulator::quirks::{LoadStore, Quirks};
+use emulator::{Chip8, CpuState, EmulatorError, Keycode};
+
+const VF: u8 = 0xF;
+
+fn emu() -> Chip8<HeadlessTarget> {
+    Chip8::headless(Quirks::COSMAC_VIP)
+}
+
+/// Place `instruction` at the program counter and execute it
+fn exec<A>(emu: &mut Chip8<HeadlessTarget, A>, instruction: Instruction) -> CpuState
+where
+    A: emulator::audio::AudioSink,
+{
+    let pc = emu.pc() as usize;
+    let bytes = instruction.to_bytes();
+    emu.memory_mut()[pc..pc + bytes.len()].copy_from_slice(&bytes);
+    emu.step().unwrap()
+}
+
+fn reg<A>(emu: &Chip8<HeadlessTarget, A>, reg: u8) -> u8
+where
+    A: emulator::audio::AudioSink,
+{
+    emu.registers()[reg as usize]
+}
+
+/// Run a two register ALU instruction on VX=`x`, VY=`y`, returning VX and VF
+fn alu(instruction: fn(u8, u8) -> Instruction, x: u8, y: u8) -> (u8, u8) {
+    let mut emu = emu();
+    emu.set_register(1, x);
+    emu.set_register(2, y);
+    emu.set_register(VF, 0xAA);
+    exec(&mut emu, instruction(1, 2));
+    (reg(&emu, 1), reg(&emu, VF))
+}
+
+/// Run a two register ALU instruction with VF as the destination, returning VF
+fn alu_into_vf(instruction: fn(u8, u8) -> Instruction, x: u8, y: u8) -> u8 {
+    let mut emu = emu();
+    emu.set_register(VF, x);
+    emu.set_register(2, y);
+    exec(&mut emu, instruction(VF, 2));
+    reg(&emu, VF)
+}
+
+fn pixel(emu: &Chip8<HeadlessTarget>, x: usize, y: usize) -> u8 {
+    let (cols, _) = emu.display.size();
+    emu.display.framebuffer()[y * cols + x]
+}
+
+/// Number of pixels lit on any plane
+fn lit(emu: &Chip8<HeadlessTarget>) -> usize {
+    emu.display
+        .framebuffer()
+        .iter()
+        .filter(|&&px| px != 0)
+        .count()
+}
+
+/// Draw a single pixel at the top left corner
+fn plot_corner(emu: &mut Chip8<HeadlessTarget>) {
+    emu.memory_mut()[0x300] = 0x80;
+    emu.set_i(0x300);
+    emu.set_register(0, 0);
+    exec(emu, DrawSprite(0, 0, 1));
+}
+
+#[test]
+fn clear_display() {
+    let mut emu = emu();
+    plot_corner(&mut emu);
+    exec(&mut emu, ClearDisplay);
+    assert_eq!(lit(&emu), 0);
+}
+
+#[test]
+fn jump() {
+    let mut emu = emu();
+    exec(&mut emu, Jump(0x345));
+    assert_eq!(emu.pc(), 0x345);
+}
+
+#[test]
+fn call_and_return() {
+    let mut emu = emu();
+    exec(&mut emu, CallSubroutine(0x400));
+    assert_eq!(emu.pc(), 0x400);
+    assert_eq!(emu.stack(), &[0x202]);
+
+    exec(&mut emu, ReturnFromSubroutine);
+    assert_eq!(emu.pc(), 0x202);
+    assert!(emu.stack().is_empty());
+}
+
+#[test]
+fn return_with_empty_stack() {
+    let mut emu = emu();
+    emu.memory_mut()[0x200..0x202].copy_from_slice(&[0x00, 0xEE]);
+    assert_eq!(
+        emu.step(),
+        Err(EmulatorError::StackUnderflow {
+            pc: 0x200,
+            opcode: 0x00EE
+        })
+    );
+    assert_eq!(emu.pc(), 0x200);
+}
+
+#[test]
+fn call_with_full_stack() {
+    let mut emu = emu();
+    for _ in 0..16 {
+        exec(&mut emu, CallSubroutine(0x200));
+    }
+    assert_eq!(
+        emu.step(),
+        Err(EmulatorError::StackOverflow {
+            pc: 0x200,
+            opcode: 0x2200
+        })
+    );
+}
+
+#[test]
+fn skip_reg_eq_byte() {
+    let mut emu = emu();
+    emu.set_register(3, 0x42);
+    exec(&mut emu, SkipRegEqByte(3, 0x42));
+    assert_eq!(emu.pc(), 0x204);
+    exec(&mut emu, SkipRegEqByte(3, 0x43));
+    assert_eq!(emu.pc(), 0x206);
+}
+
+#[test]
+fn skip_reg_not_eq_byte() {
+    let mut emu = emu();
+    emu.set_register(3, 0x42);
+    exec(&mut emu, SkipRegNotEqByte(3, 0x42));
+    assert_eq!(emu.pc(), 0x202);
+    exec(&mut emu, SkipRegNotEqByte(3, 0x43));
+    assert_eq!(emu.pc(), 0x206);
+}
+
+#[test]
+fn skip_reg_eq_reg() {
+    let mut emu = emu();
+    emu.set_register(3, 7);
+    emu.set_register(4, 7);
+    exec(&mut emu, SkipRegEqReg(3, 4));
+    assert_eq!(emu.pc(), 0x204);
+    emu.set_register(4, 8);
+    exec(&mut emu, SkipRegEqReg(3, 4));
+    assert_eq!(emu.pc(), 0x206);
+}
+
+#[test]
+fn skip_reg_not_eq_reg() {
+    let mut emu = emu();
+    emu.set_register(3, 7);
+    emu.set_register(4, 7);
+    exec(&mut emu, SkipRegNotEqReg(3, 4));
+    assert_eq!(emu.pc(), 0x202);
+    emu.set_register(4, 8);
+    exec(&mut emu, SkipRegNotEqReg(3, 4));
+    assert_eq!(emu.pc(), 0x206);
+}
+
+#[test]
+fn skip_over_long_instruction() {
+    let mut emu = Chip8::headless(Quirks::XO_CHIP);
+    emu.memory_mut()[0x202..0x206].copy_from_slice(&[0xF0, 0x00, 0x12, 0x34]);
+    exec(&mut emu, SkipRegEqByte(0, 0));
+    assert_eq!(emu.pc(), 0x206);
+}
+
+#[test]
+fn set_reg_to_byte() {
+    let mut emu = emu();
+    exec(&mut emu, SetRegToByte(5, 0x99));
+    assert_eq!(reg(&emu, 5), 0x99);
+}
+
+#[test]
+fn add_byte_to_reg() {
+    let mut emu = emu();
+    emu.set_register(5, 0xFF);
+    emu.set_register(VF, 0xAA);
+    exec(&mut emu, AddByteToReg(5, 2));
+    assert_eq!(reg(&emu, 5), 1);
+    assert_eq!(reg(&emu, VF), 0xAA, "7XNN must not touch VF");
+}
+
+#[test]
+fn move_value() {
+    let mut emu = emu();
+    emu.set_register(2, 0x33);
+    exec(&mut emu, MoveValue(1, 2));
+    assert_eq!(reg(&emu, 1), 0x33);
+}
+
+#[test]
+fn logic_ops() {
+    assert_eq!(alu(OrRegs, 0x0F, 0xF0), (0xFF, 0));
+    assert_eq!(alu(AndRegs, 0x3C, 0x0F), (0x0C, 0));
+    assert_eq!(alu(XorRegs, 0xFF, 0x0F), (0xF0, 0));
+}
+
+#[test]
+fn logic_ops_keep_vf_without_quirk() {
+    let mut emu = Chip8::headless(Quirks::SCHIP);
+    emu.set_register(VF, 0xAA);
+    exec(&mut emu, OrRegs(1, 2));
+    exec(&mut emu, AndRegs(1, 2));
+    exec(&mut emu, XorRegs(1, 2));
+    assert_eq!(reg(&emu, VF), 0xAA);
+}
+
+#[test]
+fn add_regs() {
+    assert_eq!(alu(AddRegs, 0x10, 0x20), (0x30, 0));
+    assert_eq!(alu(AddRegs, 0xFF, 0x00), (0xFF, 0));
+    assert_eq!(alu(AddRegs, 0xFF, 0x01), (0x00, 1));
+    assert_eq!(alu(AddRegs, 0xF0, 0x20), (0x10, 1));
+}
+
+#[test]
+fn add_regs_into_vf() {
+    assert_eq!(alu_into_vf(AddRegs, 0x10, 0x20), 0);
+    assert_eq!(alu_into_vf(AddRegs, 0xFF, 0x01), 1);
+}
+
+#[test]
+fn sub_regs() {
+    assert_eq!(alu(SubRegs, 0x30, 0x10), (0x20, 1));
+    assert_eq!(alu(SubRegs, 0x10, 0x30), (0xE0, 0));
+    assert_eq!(alu(SubRegs, 0x00, 0x01), (0xFF, 0));
+    // Equal operands don't borrow
+    assert_eq!(alu(SubRegs, 0x42, 0x42), (0x00, 1));
+}
+
+#[test]
+fn sub_regs_into_vf() {
+    assert_eq!(alu_into_vf(SubRegs, 0x30, 0x10), 1);
+    assert_eq!(alu_into_vf(SubRegs, 0x10, 0x30), 0);
+}
+
+#[test]
+fn reverse_sub_regs() {
+    assert_eq!(alu(ReverseSubRegs, 0x10, 0x30), (0x20, 1));
+    assert_eq!(alu(ReverseSubRegs, 0x30, 0x10), (0xE0, 0));
+    assert_eq!(alu(ReverseSubRegs, 0x01, 0x00), (0xFF, 0));
+    // Equal operands don't borrow
+    assert_eq!(alu(ReverseSubRegs, 0x42, 0x42), (0x00, 1));
+}
+
+#[test]
+fn reverse_sub_regs_into_vf() {
+    assert_eq!(alu_into_vf(ReverseSubRegs, 0x10, 0x30), 1);
+    assert_eq!(alu_into_vf(ReverseSubRegs, 0x30, 0x10), 0);
+}
+
+#[test]
+fn reverse_sub_regs_decodes_vy() {
+    assert_eq!(Instruction::try_from(0x8127).unwrap(), ReverseSubRegs(1, 2));
+}
+
+#[test]
+fn shift_right() {
+    // The VIP shifts VY into VX
+    assert_eq!(alu(ShiftRight, 0x00, 0x05), (0x02, 1));
+    assert_eq!(alu(ShiftRight, 0x00, 0xFE), (0x7F, 0));
+    assert_eq!(alu_into_vf(ShiftRight, 0x00, 0x05), 1);
+    assert_eq!(alu_into_vf(ShiftRight, 0x00, 0x04), 0);
+}
+
+#[test]
+fn shift_left() {
+    assert_eq!(alu(ShiftLeft, 0x00, 0x81), (0x02, 1));
+    assert_eq!(alu(ShiftLeft, 0x00, 0x7F), (0xFE, 0));
+    assert_eq!(alu_into_vf(ShiftLeft, 0x00, 0x81), 1);
+    assert_eq!(alu_into_vf(ShiftLeft, 0x00, 0x40), 0);
+}
+
+#[test]
+fn shifts_in_place_with_quirk() {
+    let mut emu = Chip8::headless(Quirks::SCHIP);
+    emu.set_register(1, 0x81);
+    emu.set_register(2, 0xFF);
+    exec(&mut emu, ShiftRight(1, 2));
+    assert_eq!((reg(&emu, 1), reg(&emu, VF)), (0x40, 1));
+    exec(&mut emu, ShiftLeft(1, 2));
+    assert_eq!((reg(&emu, 1), reg(&emu, VF)), (0x80, 0));
+}
+
+#[test]
+fn set_i() {
+    let mut emu = emu();
+    exec(&mut emu, SetI(0x123));
+    assert_eq!(emu.i(), 0x123);
+}
+
+#[test]
+fn jump_v0_plus_byte() {
+    let mut emu = emu();
+    emu.set_register(0, 0x10);
+    emu.set_register(3, 0x20);
+    exec(&mut emu, JumpV0PlusByte(0x300));
+    assert_eq!(emu.pc(), 0x310);
+
+    let mut emu = Chip8::headless(Quirks::SCHIP);
+    emu.set_register(0, 0x10);
+    emu.set_register(3, 0x20);
+    exec(&mut emu, JumpV0PlusByte(0x300));
+    assert_eq!(emu.pc(), 0x320);
+}
+
+#[test]
+fn set_reg_to_rand_plus_byte() {
+    let mut emu = emu();
+    emu.set_seed(1);
+    for _ in 0..32 {
+        exec(&mut emu, SetRegToRandPlusByte(1, 0x0F));
+        assert!(reg(&emu, 1) <= 0x0F);
+    }
+    exec(&mut emu, SetRegToRandPlusByte(1, 0));
+    assert_eq!(reg(&emu, 1), 0);
+
+    let values = |seed| {
+        let mut seeded = Chip8::headless(Quirks::COSMAC_VIP);
+        seeded.set_seed(seed);
+        (0..8)
+            .map(|_| {
+                exec(&mut seeded, SetRegToRandPlusByte(1, 0xFF));
+                reg(&seeded, 1)
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(values(7), values(7));
+}
+
+#[test]
+fn draw_sprite() {
+    let mut emu = emu();
+    emu.set_register(0, 2);
+    emu.set_register(1, 3);
+    emu.set_i(0); // Font digit 0
+    exec(&mut emu, DrawSprite(0, 1, 5));
+    assert_eq!(reg(&emu, VF), 0);
+    assert_eq!(pixel(&emu, 2, 3), 1);
+    assert_eq!(pixel(&emu, 3, 4), 0);
+    assert_eq!(pixel(&emu, 5, 4), 1);
+
+    exec(&mut emu, DrawSprite(0, 1, 5));
+    assert_eq!(reg(&emu, VF), 1);
+    assert_eq!(lit(&emu), 0);
+}
+
+#[test]
+fn draw_large_sprite() {
+    let large = |quirks, hires| {
+        let mut emu = Chip8::headless(quirks);
+        if hires {
+            exec(&mut emu, HighResolution);
+        }
+        emu.memory_mut()[0x300..0x320].fill(0xFF);
+        emu.set_i(0x300);
+        exec(&mut emu, DrawSprite(0, 0, 0));
+        lit(&emu)
+    };
+    assert_eq!(large(Quirks::MODERN, false), 256);
+    assert_eq!(large(Quirks::MODERN, true), 256);
+    // SUPER-CHIP 1.1 only draws 16 pixels wide in hires
+    assert_eq!(large(Quirks::SCHIP, false), 128);
+    assert_eq!(large(Quirks::SCHIP, true), 256);
+}
+
+#[test]
+fn draw_sprite_out_of_memory() {
+    let mut emu = emu();
+    emu.set_i(0xFFE);
+    emu.memory_mut()[0x200..0x202].copy_from_slice(&[0xD0, 0x05]);
+    assert_eq!(
+        emu.step(),
+        Err(EmulatorError::MemoryOutOfBounds {
+            pc: 0x200,
+            opcode: 0xD005,
+            addr: 0x1002
+        })
+    );
+}
+
+#[test]
+fn skip_if_key() {
+    let mut emu = emu();
+    emu.set_register(0, 0xA);
+    exec(&mut emu, SkipIfKey(0));
+    assert_eq!(emu.pc(), 0x202);
+    emu.key_down(&Keycode::A);
+    exec(&mut emu, SkipIfKey(0));
+    assert_eq!(emu.pc(), 0x206);
+}
+
+#[test]
+fn skip_if_not_key() {
+    let mut emu = emu();
+    emu.set_register(0, 0xA);
+    exec(&mut emu, SkipIfNotKey(0));
+    assert_eq!(emu.pc(), 0x204);
+    emu.key_down(&Keycode::A);
+    exec(&mut emu, SkipIfNotKey(0));
+    assert_eq!(emu.pc(), 0x206);
+}
+
+#[test]
+fn delay_timer() {
+    let mut emu = emu();
+    emu.set_register(0, 0x20);
+    exec(&mut emu, SetDelayToReg(0));
+    assert_eq!(emu.delay_timer(), 0x20);
+    exec(&mut emu, LoadDelayToReg(1));
+    assert_eq!(reg(&emu, 1), 0x20);
+}
+
+#[test]
+fn set_sound_to_reg() {
+    let mut emu = emu();
+    emu.set_register(0, 0x20);
+    exec(&mut emu, SetSoundToReg(0));
+    assert_eq!(emu.sound_timer(), 0x20);
+}
+
+#[test]
+fn load_key_to_reg() {
+    let mut emu = emu();
+    assert_eq!(exec(&mut emu, LoadKeyToReg(3)), CpuState::WaitingForKey(3));
+    emu.key_down(&Keycode::B);
+    assert_eq!(emu.state(), CpuState::WaitingForKey(3));
+    emu.key_up(&Keycode::B);
+    assert_eq!(emu.state(), CpuState::Running);
+    assert_eq!(reg(&emu, 3), 0xB);
+}
+
+#[test]
+fn add_reg_to_i() {
+    let mut emu = emu();
+    emu.set_i(0x100);
+    emu.set_register(0, 0x23);
+    exec(&mut emu, AddRegToI(0));
+    assert_eq!(emu.i(), 0x123);
+}
+
+#[test]
+fn set_i_to_digit_sprite_loc() {
+    let mut emu = emu();
+    emu.set_register(0, 0xA);
+    exec(&mut emu, SetIToDigitSpriteLoc(0));
+    let i = emu.i() as usize;
+    assert_eq!(&emu.memory()[i..i + 5], &[0xF0, 0x90, 0xF0, 0x90, 0x90]);
+
+    // Only the low nibble picks the digit
+    emu.set_register(0, 0x1A);
+    exec(&mut emu, SetIToDigitSpriteLoc(0));
+    assert_eq!(emu.i() as usize, i);
+}
+
+#[test]
+fn store_number_from_reg_to_i() {
+    let mut emu = emu();
+    emu.set_i(0x300);
+    emu.set_register(0, 234);
+    exec(&mut emu, StoreNumberFromRegToI(0));
+    assert_eq!(&emu.memory()[0x300..0x303], &[2, 3, 4]);
+    assert_eq!(emu.i(), 0x300);
+}
+
+#[test]
+fn store_and_load_regs() {
+    let quirks = |load_store| Quirks {
+        load_store,
+        ..Quirks::COSMAC_VIP
+    };
+    for (load_store, i) in [
+        (LoadStore::Unchanged, 0x300),
+        (LoadStore::IncrementByX, 0x302),
+        (LoadStore::IncrementByXPlusOne, 0x303),
+    ] {
+        let mut emu = Chip8::headless(quirks(load_store));
+        emu.set_i(0x300);
+        emu.set_register(0, 1);
+        emu.set_register(1, 2);
+        emu.set_register(2, 3);
+        emu.set_register(3, 4);
+        exec(&mut emu, StoreRegsToMem(2));
+        assert_eq!(&emu.memory()[0x300..0x304], &[1, 2, 3, 0]);
+        assert_eq!(emu.i(), i);
+
+        let mut emu = Chip8::headless(quirks(load_store));
+        emu.memory_mut()[0x300..0x304].copy_from_slice(&[5, 6, 7, 8]);
+        emu.set_i(0x300);
+        exec(&mut emu, LoadRegsFromMem(2));
+        assert_eq!(&emu.registers()[..4], &[5, 6, 7, 0]);
+        assert_eq!(emu.i(), i);
+    }
+}
+
+#[test]
+fn scroll() {
+    let mut emu = emu();
+    plot_corner(&mut emu);
+    exec(&mut emu, ScrollDown(2));
+    assert_eq!(pixel(&emu, 0, 2), 1);
+    exec(&mut emu, ScrollRight);
+    assert_eq!(pixel(&emu, 4, 2), 1);
+    exec(&mut emu, ScrollLeft);
+    assert_eq!(pixel(&emu, 0, 2), 1);
+    exec(&mut emu, ScrollUp(2));
+    assert_eq!(pixel(&emu, 0, 0), 1);
+    assert_eq!(lit(&emu), 1);
+}
+
+#[test]
+fn exit() {
+    let mut emu = emu();
+    assert_eq!(exec(&mut emu, Exit), CpuState::Halted);
+    assert!(emu.is_halted());
+    assert_eq!(emu.step(), Ok(CpuState::Halted));
+    assert_eq!(emu.pc(), 0x202);
+}
+
+#[test]
+fn resolution() {
+    let mut emu = emu();
+    exec(&mut emu, HighResolution);
+    assert!(emu.display.is_hires());
+    assert_eq!(emu.display.size(), (128, 64));
+    exec(&mut emu, LowResolution);
+    assert!(!emu.display.is_hires());
+    assert_eq!(emu.display.size(), (64, 32));
+}
+
+#[test]
+fn set_i_to_big_digit_sprite_loc() {
+    let mut emu = emu();
+    emu.set_register(0, 1);
+    exec(&mut emu, SetIToBigDigitSpriteLoc(0));
+    let i = emu.i() as usize;
+    assert_eq!(&emu.memory()[i..i + 3], &[0x18, 0x78, 0x78]);
+}
+
+#[test]
+fn rpl_flags() {
+    let mut emu = emu();
+    emu.set_register(0, 1);
+    emu.set_register(1, 2);
+    emu.set_register(2, 3);
+    exec(&mut emu, StoreRegsToFlags(1));
+    assert_eq!(&emu.rpl_flags()[..3], &[1, 2, 0]);
+
+    emu.set_rpl_flags([9; 16]);
+    exec(&mut emu, LoadRegsFromFlags(1));
+    assert_eq!(&emu.registers()[..3], &[9, 9, 3]);
+}
+
+#[test]
+fn store_and_load_reg_range() {
+    let mut emu = Chip8::headless(Quirks::XO_CHIP);
+    emu.set_i(0x300);
+    emu.set_register(2, 1);
+    emu.set_register(3, 2);
+    emu.set_register(4, 3);
+    exec(&mut emu, StoreRegRange(2, 4));
+    assert_eq!(&emu.memory()[0x300..0x303], &[1, 2, 3]);
+    exec(&mut emu, StoreRegRange(4, 2));
+    assert_eq!(&emu.memory()[0x300..0x303], &[3, 2, 1]);
+    assert_eq!(emu.i(), 0x300);
+
+    emu.memory_mut()[0x300..0x303].copy_from_slice(&[7, 8, 9]);
+    exec(&mut emu, LoadRegRange(5, 7));
+    assert_eq!(&emu.registers()[5..8], &[7, 8, 9]);
+    exec(&mut emu, LoadRegRange(7, 5));
+    assert_eq!(&emu.registers()[5..8], &[9, 8, 7]);
+}
+
+#[test]
+fn set_i_long() {
+    let mut emu = Chip8::headless(Quirks::XO_CHIP);
+    exec(&mut emu, SetILong(0xBEEF));
+    assert_eq!(emu.i(), 0xBEEF);
+    assert_eq!(emu.pc(), 0x204);
+}
+
+#[test]
+fn select_planes() {
+    let mut emu = Chip8::headless(Quirks::XO_CHIP);
+    exec(&mut emu, SelectPlanes(3));
+    assert_eq!(emu.display.planes(), 3);
+}
+
+/// One frame of buzzer output from a machine set up by `setup`
+fn buzz(setup: impl FnOnce(&mut Chip8<HeadlessTarget, WavSink>)) -> Vec<i16> {
+    let display = Display::new(HeadlessTarget::new());
+    let mut emu = Chip8::with_audio(display, WavSink::new(8000), Quirks::XO_CHIP);
+    setup(&mut emu);
+    emu.set_register(0, 2);
+    exec(&mut emu, SetSoundToReg(0));
+    emu.set_cycles_per_frame(0);
+    emu.run_frame().unwrap();
+    emu.audio.samples().to_vec()
+}
+
+#[test]
+fn buzzer_sounds_for_every_frame_of_the_sound_timer() {
+    let display = Display::new(HeadlessTarget::new());
+    let mut emu = Chip8::with_audio(display, WavSink::new(8000), Quirks::XO_CHIP);
+    emu.set_cycles_per_frame(0);
+    emu.set_sound_timer(1);
+
+    emu.run_frame().unwrap();
+    assert_eq!(emu.sound_timer(), 0);
+    assert!(emu.audio.samples().iter().any(|&sample| sample != 0));
+
+    let sounded = emu.audio.samples().len();
+    emu.run_frame().unwrap();
+    let after = &emu.audio.samples()[sounded..];
+    assert!(after.iter().all(|&sample| sample == 0));
+}
+
+#[test]
+fn load_audio_pattern() {
+    let silent = buzz(|emu| {
+        emu.set_i(0x300);
+        exec(emu, LoadAudioPattern);
+    });
+    assert!(!silent.is_empty());
+    assert!(silent.iter().all(|&sample| sample < 0));
+
+    let loud = buzz(|emu| {
+        emu.memory_mut()[0x300..0x310].fill(0xFF);
+        emu.set_i(0x300);
+        exec(emu, LoadAudioPattern);
+    });
+    assert!(loud.iter().all(|&sample| sample > 0));
+}
+
+#[test]
+fn set_pitch_to_reg() {
+    let edges = |samples: Vec<i16>| samples.windows(2).filter(|w| w[0] != w[1]).count();
+    let low = edges(buzz(|_| {}));
+    let high = edges(buzz(|emu| {
+        emu.set_register(1, 112);
+        exec(emu, SetPitchToReg(1));
+    }));
+    assert!(high > low, "{} edges at pitch 112, {} at 64", high, low);
+}
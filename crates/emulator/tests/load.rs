@@ -0,0 +1,64 @@
+use emulator::headless::HeadlessTarget;
+use emulator::quirks::Quirks;
+use emulator::{Chip8, RomError, ETI_660_PROGRAM_START, PROGRAM_START, XO_CHIP_MEMORY_SIZE};
+
+fn emu() -> Chip8<HeadlessTarget> {
+    Chip8::headless(Quirks::COSMAC_VIP)
+}
+
+#[test]
+fn load_bytes() {
+    let mut emu = emu();
+    emu.load_bytes(&[0x12, 0x34]).unwrap();
+    assert_eq!(&emu.memory()[0x200..0x202], &[0x12, 0x34]);
+    assert_eq!(emu.pc(), PROGRAM_START);
+}
+
+#[test]
+fn load_bytes_at_eti_660_address() {
+    let mut emu = emu();
+    emu.load_bytes_at(&[0x12, 0x34], ETI_660_PROGRAM_START)
+        .unwrap();
+    assert_eq!(&emu.memory()[0x600..0x602], &[0x12, 0x34]);
+    assert_eq!(emu.pc(), 0x600);
+}
+
+#[test]
+fn largest_rom_fits() {
+    let mut emu = emu();
+    emu.load_bytes(&[0xAA; 0x1000 - 0x200]).unwrap();
+    assert_eq!(emu.memory()[0xFFF], 0xAA);
+}
+
+#[test]
+fn rejects_oversized_rom() {
+    let mut emu = emu();
+    let before = emu.memory().to_vec();
+    match emu.load_bytes(&[0xAA; 0x1000 - 0x200 + 1]) {
+        Err(RomError::TooLarge { size, max }) => assert_eq!((size, max), (3585, 3584)),
+        res => panic!("expected TooLarge, got {:?}", res),
+    }
+    assert_eq!(emu.memory(), &before[..]);
+}
+
+#[test]
+fn xo_chip_memory_fits_larger_roms() {
+    let mut emu = emu();
+    emu.set_memory_size(XO_CHIP_MEMORY_SIZE);
+    assert_eq!(emu.memory().len(), 0x10000);
+    emu.load_bytes(&[0xAA; 0x10000 - 0x200]).unwrap();
+    assert_eq!(emu.memory()[0xFFFF], 0xAA);
+    // The font is kept
+    assert_eq!(&emu.memory()[0..5], &[0xF0, 0x90, 0x90, 0x90, 0xF0]);
+}
+
+#[test]
+fn rejects_empty_rom() {
+    assert!(matches!(emu().load_bytes(&[]), Err(RomError::Empty)));
+}
+
+#[test]
+fn load_missing_file() {
+    let res = emu().load("does/not/exist.ch8".into());
+    assert!(matches!(res, Err(RomError::Io(_))));
+}
@@ -0,0 +1,163 @@
+//! Recording replays, their file format and deterministic playback
+
+use emulator::audio::{WavSink, WAV_SAMPLE_RATE};
+use emulator::display::Display;
+use emulator::headless::HeadlessTarget;
+use emulator::quirks::Quirks;
+use emulator::replay::{PlaybackError, Replay};
+use emulator::state::StateError;
+use emulator::{Chip8, RomError, ETI_660_PROGRAM_START, XO_CHIP_MEMORY_SIZE};
+
+/// Scatters random digits over the screen, a different digit while key 5 is held
+fn rom(addr: u16) -> Vec<u8> {
+    let mut rom = vec![
+        0xC0, 0x3F, // RND V0, #3F
+        0xC1, 0x1F, // RND V1, #1F
+        0x62, 0x00, // LD V2, 0
+        0x63, 0x05, // LD V3, 5
+        0xE3, 0xA1, // SKNP V3
+        0x62, 0x08, // LD V2, 8
+        0xF2, 0x29, // LD F, V2
+        0xD0, 0x15, // DRW V0, V1, 5
+    ];
+    // JP back to the start, wherever that is
+    rom.extend_from_slice(&(0x1000 | addr).to_be_bytes());
+    rom
+}
+
+/// Key mask held on `frame` while recording
+fn keys(frame: usize) -> u16 {
+    if frame % 20 < 7 {
+        1 << 5
+    } else {
+        0
+    }
+}
+
+/// Record `frames` frames of `emu` after loading `rom(addr)` into it
+fn record(mut emu: Chip8<HeadlessTarget>, addr: u16, frames: usize) -> Replay {
+    emu.load_bytes_at(&rom(addr), addr).unwrap();
+    let mut replay = Replay::new(&emu, &rom(addr));
+    for frame in 0..frames {
+        replay.record_frame(&mut emu, keys(frame)).unwrap();
+    }
+    replay
+}
+
+fn emu() -> Chip8<HeadlessTarget> {
+    Chip8::headless(Quirks::COSMAC_VIP)
+}
+
+#[test]
+fn playback_is_deterministic() {
+    let replay = record(emu(), 0x200, 120);
+    assert_eq!(replay.frames.len(), 120);
+
+    // A fresh machine has a different seed, the replay has to supply the recorded one
+    let mut emu = emu();
+    assert!(replay.verify(&mut emu, &rom(0x200)).unwrap());
+    assert_eq!(emu.display.hash(), replay.final_hash);
+}
+
+#[test]
+fn playback_reproduces_audio() {
+    // Beeps while key 5 is held
+    let rom = [
+        0x60, 0x04, // LD V0, 4
+        0x63, 0x05, // LD V3, 5
+        0xE3, 0xA1, // SKNP V3
+        0xF0, 0x18, // LD ST, V0
+        0x12, 0x04, // JP #204
+    ];
+    let emu = || {
+        let display = Display::new(HeadlessTarget::new());
+        Chip8::with_audio(display, WavSink::new(WAV_SAMPLE_RATE), Quirks::COSMAC_VIP)
+    };
+
+    let mut recorded = emu();
+    recorded.load_bytes(&rom).unwrap();
+    let mut replay = Replay::new(&recorded, &rom);
+    for frame in 0..60 {
+        replay.record_frame(&mut recorded, keys(frame)).unwrap();
+    }
+
+    let mut played = emu();
+    assert!(replay.verify(&mut played, &rom).unwrap());
+    let samples = played.audio.samples();
+    assert_eq!(samples.len(), WAV_SAMPLE_RATE as usize);
+    assert!(samples.iter().any(|&sample| sample != 0));
+    assert_eq!(samples, recorded.audio.samples());
+}
+
+#[test]
+fn different_input_diverges() {
+    let mut replay = record(emu(), 0x200, 120);
+    replay.frames[3] ^= 1 << 5;
+    assert!(!replay.verify(&mut emu(), &rom(0x200)).unwrap());
+}
+
+#[test]
+fn plays_back_at_the_recorded_load_address() {
+    let mut xo_chip = emu();
+    xo_chip.set_memory_size(XO_CHIP_MEMORY_SIZE);
+    let replay = record(xo_chip, ETI_660_PROGRAM_START, 60);
+    assert_eq!(replay.load_address, ETI_660_PROGRAM_START);
+    assert_eq!(replay.memory_size, XO_CHIP_MEMORY_SIZE);
+
+    let mut emu = emu();
+    let rom = rom(ETI_660_PROGRAM_START);
+    assert!(replay.verify(&mut emu, &rom).unwrap());
+    assert_eq!(emu.memory().len(), XO_CHIP_MEMORY_SIZE);
+    assert_eq!(&emu.memory()[0x600..0x600 + rom.len()], &rom[..]);
+}
+
+#[test]
+fn rom_that_doesnt_fit() {
+    let replay = record(emu(), 0x200, 1);
+    match replay.verify(&mut emu(), &[0xAA; 0x1000]) {
+        Err(PlaybackError::Rom(RomError::TooLarge { .. })) => {}
+        res => panic!("expected TooLarge, got {:?}", res),
+    }
+}
+
+#[test]
+fn matches_rom() {
+    let replay = record(emu(), 0x200, 1);
+    assert!(replay.matches_rom(&rom(0x200)));
+    assert!(!replay.matches_rom(&rom(0x600)));
+}
+
+#[test]
+fn file_round_trip() {
+    let replay = record(emu(), ETI_660_PROGRAM_START, 30);
+    let bytes = replay.to_bytes();
+    assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+}
+
+#[test]
+fn rejects_unknown_format() {
+    let mut bytes = record(emu(), 0x200, 30).to_bytes();
+    bytes[..4].copy_from_slice(b"C8ST");
+    assert_eq!(Replay::from_bytes(&bytes), Err(StateError::UnknownFormat));
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut bytes = record(emu(), 0x200, 30).to_bytes();
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    match Replay::from_bytes(&bytes) {
+        Err(StateError::UnsupportedVersion { found: 1, .. }) => {}
+        res => panic!("expected UnsupportedVersion, got {:?}", res),
+    }
+}
+
+#[test]
+fn rejects_corrupt_data() {
+    let mut bytes = record(emu(), 0x200, 30).to_bytes();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0x01;
+    assert_eq!(
+        Replay::from_bytes(&bytes),
+        Err(StateError::ChecksumMismatch)
+    );
+}
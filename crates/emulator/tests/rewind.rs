@@ -0,0 +1,102 @@
+//! Stepping back through the rewind history of a machine counting frames in V0
+
+use emulator::headless::HeadlessTarget;
+use emulator::quirks::Quirks;
+use emulator::rewind::Rewind;
+use emulator::Chip8;
+
+/// Machine that adds one to V0 every frame
+fn counter() -> Chip8<HeadlessTarget> {
+    let mut emu = Chip8::headless(Quirks::COSMAC_VIP);
+    emu.load_bytes(&[
+        0x70, 0x01, // ADD V0, 1
+        0x12, 0x00, // JP #200
+    ])
+    .unwrap();
+    emu.set_cycles_per_frame(2);
+    emu
+}
+
+/// Run `frames` frames of `emu`, recording each one
+fn run(emu: &mut Chip8<HeadlessTarget>, rewind: &mut Rewind, frames: usize) {
+    for _ in 0..frames {
+        emu.run_frame().unwrap();
+        rewind.record(emu);
+    }
+}
+
+fn count(emu: &Chip8<HeadlessTarget>) -> u8 {
+    emu.registers()[0]
+}
+
+#[test]
+fn each_rewind_steps_back_a_frame() {
+    let mut emu = counter();
+    let mut rewind = Rewind::new(100, 1);
+    run(&mut emu, &mut rewind, 10);
+    assert_eq!(count(&emu), 10);
+
+    for frames_back in 1..=9 {
+        assert!(rewind.rewind(&mut emu));
+        assert_eq!(count(&emu), 10 - frames_back);
+    }
+    // The first snapshot is as far back as it goes
+    assert!(!rewind.rewind(&mut emu));
+    assert_eq!(count(&emu), 1);
+    assert_eq!(rewind.len(), 1);
+}
+
+#[test]
+fn recording_resumes_from_the_rewound_state() {
+    let mut emu = counter();
+    let mut rewind = Rewind::new(100, 1);
+    run(&mut emu, &mut rewind, 10);
+    rewind.rewind(&mut emu);
+    rewind.rewind(&mut emu);
+    assert_eq!(count(&emu), 8);
+
+    run(&mut emu, &mut rewind, 5);
+    assert_eq!(count(&emu), 13);
+    assert!(rewind.rewind(&mut emu));
+    assert_eq!(count(&emu), 12);
+    assert_eq!(rewind.len(), 12);
+}
+
+#[test]
+fn capacity_evicts_the_oldest_snapshots() {
+    let mut emu = counter();
+    let mut rewind = Rewind::new(5, 1);
+    run(&mut emu, &mut rewind, 20);
+    assert_eq!(rewind.len(), 5);
+
+    while rewind.rewind(&mut emu) {}
+    assert_eq!(count(&emu), 16);
+}
+
+#[test]
+fn snapshots_are_taken_every_interval() {
+    let mut emu = counter();
+    let mut rewind = Rewind::new(100, 4);
+    run(&mut emu, &mut rewind, 10);
+    assert_eq!(rewind.len(), 2);
+
+    // Frames 9 and 10 weren't snapshotted, so the newest snapshot is already a step back
+    assert!(rewind.rewind(&mut emu));
+    assert_eq!(count(&emu), 8);
+    assert!(rewind.rewind(&mut emu));
+    assert_eq!(count(&emu), 4);
+    assert!(!rewind.rewind(&mut emu));
+}
+
+#[test]
+fn empty_history() {
+    let mut emu = counter();
+    let mut rewind = Rewind::new(100, 1);
+    assert!(rewind.is_empty());
+    assert!(!rewind.rewind(&mut emu));
+
+    run(&mut emu, &mut rewind, 3);
+    rewind.clear();
+    assert!(!rewind.rewind(&mut emu));
+    assert_eq!(count(&emu), 3);
+}
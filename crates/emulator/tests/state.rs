@@ -0,0 +1,164 @@
+//! Save states round-trip the whole machine and reject anything they can't trust
+
+use emulator::audio::WavSink;
+use emulator::display::Display;
+use emulator::headless::HeadlessTarget;
+use emulator::quirks::Quirks;
+use emulator::state::{reseal, StateError};
+use emulator::Chip8;
+
+/// Magic, version and checksum in front of the payload
+const HEADER_LEN: usize = 10;
+
+fn emu() -> Chip8<HeadlessTarget, WavSink> {
+    let display = Display::new(HeadlessTarget::new());
+    Chip8::with_audio(display, WavSink::new(8000), Quirks::COSMAC_VIP)
+}
+
+/// A machine part way through drawing digits, with the buzzer on
+fn running() -> Chip8<HeadlessTarget, WavSink> {
+    let mut emu = emu();
+    emu.set_seed(7);
+    emu.load_bytes(&[
+        0x60, 0x0A, // LD V0, #0A
+        0xF0, 0x29, // LD F, V0
+        0xD1, 0x15, // DRW V1, V1, 5
+        0x71, 0x06, // ADD V1, 6
+        0xC2, 0xFF, // RND V2, #FF
+        0xF0, 0x18, // LD ST, V0
+        0x22, 0x10, // CALL #210
+        0x12, 0x0E, // JP #20E
+        0x00, 0xEE, // RET
+    ])
+    .unwrap();
+    emu.set_cycles_per_frame(3);
+    emu.run_frame().unwrap();
+    emu.run_frame().unwrap();
+    emu
+}
+
+/// Try to load `state` into a running machine, checking a rejected state changes nothing
+fn load(state: &[u8]) -> Result<(), StateError> {
+    let mut emu = running();
+    let before = emu.save_state();
+    let res = emu.load_state(state);
+    if res.is_err() {
+        assert_eq!(emu.save_state(), before);
+    }
+    res
+}
+
+#[test]
+fn round_trip() {
+    let mut original = running();
+    let state = original.save_state();
+
+    let mut restored = emu();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+
+    assert_eq!(restored.registers(), original.registers());
+    assert_eq!(restored.i(), original.i());
+    assert_eq!(restored.pc(), original.pc());
+    assert_eq!(restored.stack(), original.stack());
+    assert_eq!(restored.delay_timer(), original.delay_timer());
+    assert_eq!(restored.sound_timer(), original.sound_timer());
+    assert_eq!(restored.memory(), original.memory());
+    let framebuffer = restored.display.framebuffer();
+    assert_eq!(framebuffer, original.display.framebuffer());
+    assert!(framebuffer.iter().any(|&pixel| pixel != 0));
+
+    // Both keep running identically, buzzer included
+    let original_start = original.audio.samples().len();
+    original.run_frame().unwrap();
+    restored.run_frame().unwrap();
+    assert_eq!(restored.registers(), original.registers());
+    assert_eq!(restored.display.hash(), original.display.hash());
+    let samples = restored.audio.samples();
+    assert_eq!(samples, &original.audio.samples()[original_start..]);
+    assert!(samples.iter().any(|&sample| sample != 0));
+}
+
+#[test]
+fn rejects_unknown_format() {
+    let mut state = running().save_state();
+    state[0] ^= 0xFF;
+    assert_eq!(load(&state), Err(StateError::UnknownFormat));
+    assert_eq!(load(b"C8S"), Err(StateError::UnknownFormat));
+    assert_eq!(load(&[]), Err(StateError::UnknownFormat));
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut state = running().save_state();
+    state[4..6].copy_from_slice(&999u16.to_le_bytes());
+    match load(&state) {
+        Err(StateError::UnsupportedVersion { found: 999, .. }) => {}
+        res => panic!("expected UnsupportedVersion, got {:?}", res),
+    }
+}
+
+#[test]
+fn rejects_corrupt_payload() {
+    let state = running().save_state();
+    for idx in [HEADER_LEN, state.len() / 2, state.len() - 1] {
+        let mut corrupt = state.clone();
+        corrupt[idx] ^= 0x01;
+        assert_eq!(load(&corrupt), Err(StateError::ChecksumMismatch));
+    }
+}
+
+#[test]
+fn rejects_truncated_payload() {
+    let state = running().save_state();
+    let payload = &state[HEADER_LEN..];
+    for len in [0, 10, payload.len() - 1] {
+        assert_eq!(
+            load(&reseal(&state, &payload[..len])),
+            Err(StateError::Malformed("unexpected end of data"))
+        );
+    }
+}
+
+#[test]
+fn rejects_trailing_data() {
+    let state = running().save_state();
+    let mut payload = state[HEADER_LEN..].to_vec();
+    payload.push(0);
+    assert_eq!(
+        load(&reseal(&state, &payload)),
+        Err(StateError::Malformed("trailing data"))
+    );
+}
+
+#[test]
+fn rejects_impossible_machine() {
+    let state = running().save_state();
+    let mut payload = state[HEADER_LEN..].to_vec();
+    // The first quirk is a flag, anything but 0 or 1 is meaningless
+    payload[0] = 2;
+    assert_eq!(
+        load(&reseal(&state, &payload)),
+        Err(StateError::Malformed("invalid flag"))
+    );
+}
+
+#[test]
+fn rejects_pixels_outside_the_bitplanes() {
+    let state = running().save_state();
+    let mut payload = state[HEADER_LEN..].to_vec();
+    // The framebuffer comes last, a lores screen of 64x32 after the selected planes
+    let last = payload.len() - 1;
+    payload[last] = 0b100;
+    assert_eq!(
+        load(&reseal(&state, &payload)),
+        Err(StateError::Malformed("pixel outside the bitplanes"))
+    );
+
+    let mut payload = state[HEADER_LEN..].to_vec();
+    payload[last - 64 * 32] = 0b111;
+    assert_eq!(
+        load(&reseal(&state, &payload)),
+        Err(StateError::Malformed("unknown bitplanes selected"))
+    );
+}
@@ -0,0 +1,354 @@
+//! GDB remote serial protocol server for debugging a `Chip8` over TCP.
+//!
+//! Registers are numbered as in `TARGET_XML`: V0-VF, then I, PC, SP, DT and ST. I and PC
+//! are sent little-endian, the byte order GDB assumes for a target it has no architecture
+//! for.
+
+use std::io::{self, ErrorKind};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use emulator::audio::{AudioSink, Silence};
+use emulator::debugger::{Access, Debugger, Stop, Watchpoint};
+use emulator::display::RenderTarget;
+use emulator::{Chip8, EmulatorError};
+
+mod packet;
+
+use packet::Connection;
+
+/// Target description naming the CHIP-8 registers, served through `qXfer:features:read`
+pub const TARGET_XML: &str = include_str!("target.xml");
+
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Register numbers past V0-VF
+const I: usize = 16;
+const PC: usize = 17;
+const SP: usize = 18;
+const DT: usize = 19;
+const ST: usize = 20;
+const REGISTERS: usize = 21;
+
+/// Debugs one `Chip8` on behalf of a GDB client
+pub struct Stub<T, A = Silence>
+where
+    T: RenderTarget,
+    A: AudioSink,
+{
+    emu: Chip8<T, A>,
+    debugger: Debugger,
+}
+
+impl<T, A> Stub<T, A>
+where
+    T: RenderTarget,
+    A: AudioSink,
+{
+    pub fn new(emu: Chip8<T, A>) -> Self {
+        Self {
+            emu,
+            debugger: Debugger::new(),
+        }
+    }
+
+    pub fn emulator(&self) -> &Chip8<T, A> {
+        &self.emu
+    }
+
+    pub fn into_inner(self) -> Chip8<T, A> {
+        self.emu
+    }
+
+    /// Serve a GDB session until it detaches, kills the target or disconnects
+    ///
+    /// The emulator only runs while GDB has asked it to continue, at 60 frames a second.
+    pub fn serve(&mut self, stream: TcpStream) -> io::Result<()> {
+        let mut conn = Connection::new(stream)?;
+        while let Some(packet) = conn.read_packet()? {
+            let packet = String::from_utf8_lossy(&packet);
+            let reply = match packet.as_ref() {
+                "k" => return Ok(()),
+                detach if detach.starts_with('D') => {
+                    conn.send(b"OK")?;
+                    return Ok(());
+                }
+                "QStartNoAckMode" => {
+                    conn.send(b"OK")?;
+                    conn.no_ack = true;
+                    continue;
+                }
+                cont if cont.starts_with('c') => {
+                    let reply = match parse_hex(&cont[1..]) {
+                        Some(addr) => {
+                            self.emu.set_pc(addr as u16);
+                            self.cont(&mut conn)
+                        }
+                        None if cont.len() == 1 => self.cont(&mut conn),
+                        None => Ok(error()),
+                    };
+                    match reply {
+                        // GDB went away while the target was running
+                        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                        reply => reply?,
+                    }
+                }
+                packet => self.handle(packet),
+            };
+            conn.send(reply.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reply to any packet that doesn't need the connection itself
+    fn handle(&mut self, packet: &str) -> String {
+        let (command, args) = packet.split_at(packet.len().min(1));
+        let reply = match command {
+            "?" => Some(self.stop_reply(Ok(None))),
+            "g" => Some(self.read_registers()),
+            "G" => self.write_registers(args),
+            "p" => parse_hex(args).and_then(|reg| self.read_register(reg)),
+            "P" => self.write_register(args),
+            "m" => self.read_memory(args),
+            "M" => self.write_memory(args),
+            "Z" => self.breakpoint(args, true),
+            "z" => self.breakpoint(args, false),
+            "s" => match parse_hex(args) {
+                Some(addr) => {
+                    self.emu.set_pc(addr as u16);
+                    Some(self.step())
+                }
+                None if args.is_empty() => Some(self.step()),
+                None => None,
+            },
+            "H" => Some("OK".to_string()),
+            "q" => return query(args).unwrap_or_default(),
+            _ => return String::new(),
+        };
+        reply.unwrap_or_else(error)
+    }
+
+    fn step(&mut self) -> String {
+        let res = self.debugger.step(&mut self.emu);
+        self.stop_reply(res)
+    }
+
+    /// Run until a breakpoint, the program exiting or GDB interrupting
+    fn cont(&mut self, conn: &mut Connection) -> io::Result<String> {
+        let mut next_frame = Instant::now();
+        loop {
+            match self.debugger.run_frame(&mut self.emu) {
+                Ok(None) if !self.emu.is_halted() => {}
+                res => return Ok(self.stop_reply(res)),
+            }
+            if conn.interrupted()? {
+                return Ok("S02".to_string());
+            }
+
+            next_frame += FRAME_TIME;
+            thread::sleep(next_frame.saturating_duration_since(Instant::now()));
+        }
+    }
+
+    fn stop_reply(&self, res: Result<Option<Stop>, EmulatorError>) -> String {
+        match res {
+            Ok(Some(Stop::Breakpoint(_))) => "T05swbreak:;".to_string(),
+            Ok(Some(Stop::Watchpoint { addr, access, .. })) => {
+                let kind = match access {
+                    Access::Read => "rwatch",
+                    Access::Write => "watch",
+                };
+                format!("T05{}:{:x};", kind, addr)
+            }
+            Ok(Some(_)) => "S05".to_string(),
+            Ok(None) if self.emu.is_halted() => "W00".to_string(),
+            Ok(None) => "S05".to_string(),
+            // Reported as an illegal instruction, the closest signal to a bad opcode or stack
+            Err(_) => "S04".to_string(),
+        }
+    }
+
+    /// Value and size in bytes of register `reg`
+    fn register(&self, reg: usize) -> Option<(u16, usize)> {
+        let value = match reg {
+            0..=15 => (self.emu.registers()[reg] as u16, 1),
+            I => (self.emu.i(), 2),
+            PC => (self.emu.pc(), 2),
+            SP => (self.emu.stack().len() as u16, 1),
+            DT => (self.emu.delay_timer() as u16, 1),
+            ST => (self.emu.sound_timer() as u16, 1),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn read_register(&self, reg: usize) -> Option<String> {
+        let (value, size) = self.register(reg)?;
+        Some(encode_hex(&value.to_le_bytes()[..size]))
+    }
+
+    fn read_registers(&self) -> String {
+        (0..REGISTERS)
+            .filter_map(|reg| self.read_register(reg))
+            .collect()
+    }
+
+    /// Set register `reg` from its little-endian bytes
+    ///
+    /// The stack pointer can't be moved, but writing back its current value succeeds so
+    /// that `G` works with what `g` returned.
+    fn set_register(&mut self, reg: usize, bytes: &[u8]) -> Option<String> {
+        let (current, size) = self.register(reg)?;
+        if bytes.len() != size {
+            return None;
+        }
+        let value = u16::from_le_bytes([bytes[0], bytes.get(1).copied().unwrap_or(0)]);
+
+        match reg {
+            0..=15 => self.emu.set_register(reg as u8, value as u8),
+            I => self.emu.set_i(value),
+            PC => self.emu.set_pc(value),
+            SP if value == current => {}
+            DT => self.emu.set_delay_timer(value as u8),
+            ST => self.emu.set_sound_timer(value as u8),
+            _ => return None,
+        }
+        Some("OK".to_string())
+    }
+
+    /// `reg=value`
+    fn write_register(&mut self, args: &str) -> Option<String> {
+        let (reg, value) = args.split_once('=')?;
+        self.set_register(parse_hex(reg)?, &decode_hex(value)?)
+    }
+
+    fn write_registers(&mut self, args: &str) -> Option<String> {
+        let bytes = decode_hex(args)?;
+        let sizes: Vec<usize> = (0..REGISTERS)
+            .map(|reg| self.register(reg).map(|(_, size)| size))
+            .collect::<Option<_>>()?;
+        if bytes.len() != sizes.iter().sum() {
+            return None;
+        }
+
+        let mut offset = 0;
+        for (reg, size) in sizes.into_iter().enumerate() {
+            self.set_register(reg, &bytes[offset..offset + size])?;
+            offset += size;
+        }
+        Some("OK".to_string())
+    }
+
+    /// `addr,length`, replying with as much as is in memory
+    fn read_memory(&self, args: &str) -> Option<String> {
+        let (addr, len) = args.split_once(',')?;
+        let (addr, len) = (parse_hex(addr)?, parse_hex(len)?);
+        let memory = self.emu.memory();
+        if addr >= memory.len() {
+            return None;
+        }
+        let end = addr.saturating_add(len).min(memory.len());
+        Some(encode_hex(&memory[addr..end]))
+    }
+
+    /// `addr,length:bytes`
+    fn write_memory(&mut self, args: &str) -> Option<String> {
+        let (range, data) = args.split_once(':')?;
+        let (addr, len) = range.split_once(',')?;
+        let (addr, len, data) = (parse_hex(addr)?, parse_hex(len)?, decode_hex(data)?);
+        if data.len() != len {
+            return None;
+        }
+        self.emu
+            .memory_mut()
+            .get_mut(addr..addr.checked_add(len)?)?
+            .copy_from_slice(&data);
+        Some("OK".to_string())
+    }
+
+    /// `type,addr,kind` for `Z` and `z`, where the kind of a watchpoint is its length
+    fn breakpoint(&mut self, args: &str, insert: bool) -> Option<String> {
+        let mut fields = args.split(',');
+        let kind = fields.next()?;
+        let addr = parse_hex(fields.next()?)? as u16;
+        let len = parse_hex(fields.next()?)? as u16;
+
+        let watchpoint = match kind {
+            // Software and hardware breakpoints are the same thing to an emulator
+            "0" | "1" => {
+                if insert {
+                    self.debugger.add_breakpoint(addr);
+                } else {
+                    self.debugger.remove_breakpoint(addr);
+                }
+                return Some("OK".to_string());
+            }
+            "2" => Watchpoint::write(addr, len),
+            "3" => Watchpoint::read(addr, len),
+            "4" => Watchpoint::access(addr, len),
+            _ => return Some(String::new()),
+        };
+
+        if insert {
+            self.debugger.add_watchpoint(watchpoint);
+        } else {
+            let watchpoints = self.debugger.watchpoints();
+            let idx = watchpoints.iter().position(|w| *w == watchpoint)?;
+            self.debugger.remove_watchpoint(idx);
+        }
+        Some("OK".to_string())
+    }
+}
+
+/// Reply to a `q` packet, or `None` for an error
+fn query(args: &str) -> Option<String> {
+    let reply = match args {
+        supported if supported.starts_with("Supported") => {
+            "PacketSize=1000;qXfer:features:read+;swbreak+;QStartNoAckMode+"
+        }
+        "Attached" => "1",
+        "C" => "QC1",
+        "fThreadInfo" => "m1",
+        "sThreadInfo" => "l",
+        xfer => match xfer.strip_prefix("Xfer:features:read:target.xml:") {
+            Some(range) => return read_target_xml(range),
+            None => "",
+        },
+    };
+    Some(reply.to_string())
+}
+
+/// `offset,length` of the target description, prefixed `l` for the last chunk
+fn read_target_xml(range: &str) -> Option<String> {
+    let (offset, len) = range.split_once(',')?;
+    let (offset, len) = (parse_hex(offset)?, parse_hex(len)?);
+    let start = offset.min(TARGET_XML.len());
+    let end = start.saturating_add(len).min(TARGET_XML.len());
+
+    // The description is ASCII, so any offset is on a character boundary
+    let marker = if end == TARGET_XML.len() { 'l' } else { 'm' };
+    Some(format!("{}{}", marker, &TARGET_XML[start..end]))
+}
+
+fn error() -> String {
+    "E01".to_string()
+}
+
+fn parse_hex(text: &str) -> Option<usize> {
+    usize::from_str_radix(text, 16).ok()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(text.get(idx..idx + 2)?, 16).ok())
+        .collect()
+}
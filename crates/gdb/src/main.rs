@@ -0,0 +1,88 @@
+use std::net::TcpListener;
+
+use emulator::quirks::Quirks;
+use emulator::Chip8;
+
+const USAGE: &str = "usage: chip8-gdb [options] romfile
+
+options:
+  --port n         port to listen on, 1234 by default
+  --quirks vip|chip48|schip|modern|xochip
+  --eti660         load the ROM at 0x600, for programs written for the ETI-660
+
+then connect with `target remote localhost:1234` in GDB";
+
+pub fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (port, quirks, memory_size, load_address, rom) = match parse(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            println!("{}", err);
+            println!("{}", USAGE);
+            return;
+        }
+    };
+
+    let mut emu = Chip8::headless(quirks);
+    emu.set_memory_size(memory_size);
+    let loaded = std::fs::read(&rom)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| {
+            emu.load_bytes_at(&bytes, load_address)
+                .map_err(|err| err.to_string())
+        });
+    if let Err(err) = loaded {
+        eprintln!("{}: {}", rom, err);
+        std::process::exit(1);
+    }
+
+    // Only local debuggers may attach, nothing here is fit to expose to a network
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("unable to listen on port {}: {}", port, err);
+            std::process::exit(1);
+        }
+    };
+    println!("waiting for GDB on 127.0.0.1:{}", port);
+
+    let served = listener
+        .accept()
+        .and_then(|(stream, _)| gdb::Stub::new(emu).serve(stream));
+    if let Err(err) = served {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn parse(args: &[String]) -> Result<(u16, Quirks, usize, u16, String), String> {
+    let mut port = 1234;
+    let mut quirks = Quirks::default();
+    let mut memory_size = emulator::MEMORY_SIZE;
+    let mut load_address = emulator::PROGRAM_START;
+    let mut rom = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                let value = args.next().ok_or("--port requires a number")?;
+                port = value
+                    .parse()
+                    .map_err(|_| format!("invalid port {}", value))?;
+            }
+            "--quirks" => {
+                let name = args.next().ok_or("--quirks requires a preset name")?;
+                quirks = name.parse().map_err(|err| format!("{}", err))?;
+                memory_size = Quirks::preset_memory_size(name);
+            }
+            "--eti660" => load_address = emulator::ETI_660_PROGRAM_START,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path if rom.is_none() => rom = Some(path.to_string()),
+            extra => return Err(format!("unexpected argument {}", extra)),
+        }
+    }
+
+    let rom = rom.ok_or("missing romfile")?;
+    Ok((port, quirks, memory_size, load_address, rom))
+}
@@ -0,0 +1,126 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::TcpStream;
+
+/// Byte GDB sends to interrupt a running target
+const INTERRUPT: u8 = 0x03;
+
+/// Packet framing over a TCP connection to GDB
+pub(crate) struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Set once GDB has agreed to `QStartNoAckMode`
+    pub(crate) no_ack: bool,
+}
+
+impl Connection {
+    pub(crate) fn new(stream: TcpStream) -> io::Result<Self> {
+        // Packets are small and each waits on a reply, so don't hold them back to batch
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            no_ack: false,
+        })
+    }
+
+    /// Read the next packet's payload, or `None` once GDB disconnects
+    pub(crate) fn read_packet(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            // Skip acks and stray interrupts until the start of a packet
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'$') => {}
+                Some(_) => continue,
+            }
+
+            let mut raw = Vec::new();
+            if self.reader.read_until(b'#', &mut raw)? == 0 || raw.pop() != Some(b'#') {
+                return Ok(None);
+            }
+            let mut checksum = [0; 2];
+            if let Err(err) = io::Read::read_exact(&mut self.reader, &mut checksum) {
+                return match err.kind() {
+                    ErrorKind::UnexpectedEof => Ok(None),
+                    _ => Err(err),
+                };
+            }
+
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                == Some(sum(&raw));
+            if !self.no_ack {
+                self.writer.write_all(if valid { b"+" } else { b"-" })?;
+            }
+            if valid {
+                return Ok(Some(unescape(&raw)));
+            }
+        }
+    }
+
+    /// Send a packet, escaping the characters that would end it early
+    pub(crate) fn send(&mut self, payload: &[u8]) -> io::Result<()> {
+        let mut escaped = Vec::with_capacity(payload.len());
+        for &byte in payload {
+            if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+                escaped.push(b'}');
+                escaped.push(byte ^ 0x20);
+            } else {
+                escaped.push(byte);
+            }
+        }
+
+        let mut packet = Vec::with_capacity(escaped.len() + 4);
+        packet.push(b'$');
+        packet.extend_from_slice(&escaped);
+        packet.extend_from_slice(format!("#{:02x}", sum(&escaped)).as_bytes());
+        self.writer.write_all(&packet)?;
+        self.writer.flush()
+    }
+
+    /// Check without blocking whether GDB has asked to interrupt the target
+    ///
+    /// Fails with `ErrorKind::UnexpectedEof` if GDB has disconnected.
+    pub(crate) fn interrupted(&mut self) -> io::Result<bool> {
+        self.reader.get_ref().set_nonblocking(true)?;
+        let pending = self.reader.fill_buf().map(|buf| buf.to_vec());
+        self.reader.get_ref().set_nonblocking(false)?;
+
+        match pending {
+            Ok(pending) if pending.is_empty() => Err(ErrorKind::UnexpectedEof.into()),
+            // Nothing but an interrupt should arrive while running, so the rest is dropped
+            Ok(pending) => {
+                self.reader.consume(pending.len());
+                Ok(pending.contains(&INTERRUPT))
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let buf = self.reader.fill_buf()?;
+        let Some(&byte) = buf.first() else {
+            return Ok(None);
+        };
+        self.reader.consume(1);
+        Ok(Some(byte))
+    }
+}
+
+fn sum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+/// Undo `}` escapes, which GDB uses for binary data
+fn unescape(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter();
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            b'}' => out.extend(bytes.next().map(|escaped| escaped ^ 0x20)),
+            byte => out.push(byte),
+        }
+    }
+    out
+}
@@ -0,0 +1,286 @@
+//! Drives `Stub` as GDB would, over a socket on localhost.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use emulator::headless::HeadlessTarget;
+use emulator::quirks::Quirks;
+use emulator::Chip8;
+use gdb::{Stub, TARGET_XML};
+
+/// Stores V0 and V1 at 0x300, then spins at 0x208
+const ROM: &[u8] = &[
+    0x60, 0x05, // 200: LD V0, #05
+    0x61, 0x07, // 202: LD V1, #07
+    0xA3, 0x00, // 204: LD I, #300
+    0xF1, 0x55, // 206: LD [I], V1
+    0x12, 0x08, // 208: JP #208
+];
+
+struct Client {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    no_ack: bool,
+}
+
+impl Client {
+    /// Send a packet and return the payload of the reply
+    fn send(&mut self, payload: &str) -> String {
+        self.send_raw(&packet(payload));
+        if !self.no_ack {
+            assert_eq!(self.read_byte(), b'+');
+        }
+        self.reply()
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) {
+        self.stream.write_all(bytes).unwrap();
+    }
+
+    fn reply(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+        let mut payload = Vec::new();
+        self.reader.read_until(b'#', &mut payload).unwrap();
+        payload.pop();
+        let mut checksum = [0; 2];
+        self.reader.read_exact(&mut checksum).unwrap();
+
+        let checksum = u8::from_str_radix(std::str::from_utf8(&checksum).unwrap(), 16).unwrap();
+        assert_eq!(checksum, sum(&payload));
+        if !self.no_ack {
+            self.send_raw(b"+");
+        }
+        String::from_utf8(payload).unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0];
+        self.reader.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+}
+
+fn sum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn packet(payload: &str) -> Vec<u8> {
+    format!("${}#{:02x}", payload, sum(payload.as_bytes())).into_bytes()
+}
+
+/// Start a stub for `ROM` on a free port and connect to it
+fn connect() -> (Client, JoinHandle<Chip8<HeadlessTarget>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let mut emu = Chip8::headless(Quirks::COSMAC_VIP);
+        emu.load_bytes(ROM).unwrap();
+        let mut stub = Stub::new(emu);
+        let (stream, _) = listener.accept().unwrap();
+        stub.serve(stream).unwrap();
+        stub.into_inner()
+    });
+
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_nodelay(true).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let client = Client {
+        reader: BufReader::new(stream.try_clone().unwrap()),
+        stream,
+        no_ack: false,
+    };
+    (client, server)
+}
+
+#[test]
+fn supported_features() {
+    let (mut gdb, _) = connect();
+    let reply = gdb.send("qSupported:multiprocess+;swbreak+;xmlRegisters=i386");
+    assert!(reply
+        .split(';')
+        .any(|feature| feature == "qXfer:features:read+"));
+    assert!(reply.split(';').any(|feature| feature == "swbreak+"));
+    assert_eq!(gdb.send("?"), "S05");
+    assert_eq!(gdb.send("vMustReplyEmpty"), "");
+}
+
+#[test]
+fn target_description() {
+    let (mut gdb, _) = connect();
+    let mut xml = String::new();
+    loop {
+        let reply = gdb.send(&format!(
+            "qXfer:features:read:target.xml:{:x},80",
+            xml.len()
+        ));
+        xml.push_str(&reply[1..]);
+        if reply.starts_with('l') {
+            break;
+        }
+        assert!(reply.starts_with('m'));
+    }
+    assert_eq!(xml, TARGET_XML);
+    for name in ["v0", "vf", "i", "pc", "sp", "dt", "st"] {
+        assert!(
+            xml.contains(&format!("name=\"{}\"", name)),
+            "{} missing",
+            name
+        );
+    }
+}
+
+#[test]
+fn read_registers() {
+    let (mut gdb, _) = connect();
+    // V0-VF, then I, PC little-endian, SP, DT and ST
+    let expected = format!("{}0000000200{}", "00".repeat(16), "0000");
+    assert_eq!(gdb.send("g"), expected);
+    assert_eq!(gdb.send("p11"), "0002");
+}
+
+#[test]
+fn write_registers() {
+    let (mut gdb, server) = connect();
+    assert_eq!(gdb.send("P3=2a"), "OK");
+    assert_eq!(gdb.send("P10=3412"), "OK");
+    assert_eq!(gdb.send("P13=3c"), "OK");
+    assert_eq!(gdb.send("p3"), "2a");
+    assert_eq!(gdb.send("p10"), "3412");
+    // SP can only be written with its current value
+    assert_eq!(gdb.send("P12=01"), "E01");
+    assert_eq!(gdb.send("P12=00"), "OK");
+    assert_eq!(gdb.send("P3=2a2a"), "E01");
+    assert_eq!(gdb.send("P15=00"), "E01");
+
+    let mut all = gdb.send("g");
+    all.replace_range(0..2, "ff");
+    assert_eq!(gdb.send(&format!("G{}", all)), "OK");
+    assert_eq!(gdb.send("D"), "OK");
+
+    let emu = server.join().unwrap();
+    assert_eq!(emu.registers()[0], 0xFF);
+    assert_eq!(emu.registers()[3], 0x2A);
+    assert_eq!(emu.i(), 0x1234);
+    assert_eq!(emu.delay_timer(), 0x3C);
+}
+
+#[test]
+fn memory() {
+    let (mut gdb, _) = connect();
+    assert_eq!(gdb.send("m200,4"), "60056107");
+    assert_eq!(gdb.send("M300,3:abcdef"), "OK");
+    assert_eq!(gdb.send("m300,3"), "abcdef");
+    // Reads past the end return what there is
+    assert_eq!(gdb.send("mfff,4"), "00");
+    assert_eq!(gdb.send("m1000,1"), "E01");
+    assert_eq!(gdb.send("Mfff,2:0000"), "E01");
+    assert_eq!(gdb.send("M300,2:ab"), "E01");
+}
+
+#[test]
+fn single_step() {
+    let (mut gdb, _) = connect();
+    assert_eq!(gdb.send("s"), "S05");
+    assert_eq!(gdb.send("p0"), "05");
+    assert_eq!(gdb.send("p11"), "0202");
+    assert_eq!(gdb.send("s206"), "S05");
+    assert_eq!(gdb.send("p11"), "0802");
+    assert_eq!(gdb.send("m300,2"), "0000");
+}
+
+#[test]
+fn breakpoint() {
+    let (mut gdb, _) = connect();
+    assert_eq!(gdb.send("Z0,204,2"), "OK");
+    assert_eq!(gdb.send("c"), "T05swbreak:;");
+    assert_eq!(gdb.send("p11"), "0402");
+    assert_eq!(gdb.send("p1"), "07");
+
+    // Continuing from a breakpoint runs the instruction under it
+    assert_eq!(gdb.send("Z0,202,2"), "OK");
+    assert_eq!(gdb.send("s202"), "S05");
+    assert_eq!(gdb.send("c"), "T05swbreak:;");
+    assert_eq!(gdb.send("p11"), "0402");
+
+    assert_eq!(gdb.send("z0,204,2"), "OK");
+    assert_eq!(gdb.send("Z0,208,2"), "OK");
+    assert_eq!(gdb.send("c"), "T05swbreak:;");
+    assert_eq!(gdb.send("p11"), "0802");
+}
+
+#[test]
+fn watchpoint() {
+    let (mut gdb, _) = connect();
+    assert_eq!(gdb.send("Z2,301,1"), "OK");
+    assert_eq!(gdb.send("c"), "T05watch:301;");
+    assert_eq!(gdb.send("p11"), "0802");
+    assert_eq!(gdb.send("m300,2"), "0507");
+
+    assert_eq!(gdb.send("z2,301,1"), "OK");
+    assert_eq!(gdb.send("z2,301,1"), "E01");
+    assert_eq!(gdb.send("Z3,200,2"), "OK");
+    assert_eq!(gdb.send("Z9,200,2"), "");
+}
+
+#[test]
+fn interrupt() {
+    let (mut gdb, _) = connect();
+    gdb.send_raw(&packet("c"));
+    assert_eq!(gdb.read_byte(), b'+');
+    thread::sleep(Duration::from_millis(50));
+    gdb.send_raw(&[0x03]);
+    assert_eq!(gdb.reply(), "S02");
+    assert_eq!(gdb.send("p11"), "0802");
+}
+
+#[test]
+fn no_ack_mode() {
+    let (mut gdb, _) = connect();
+    assert_eq!(gdb.send("QStartNoAckMode"), "OK");
+    gdb.no_ack = true;
+    assert_eq!(gdb.send("p0"), "00");
+    assert_eq!(gdb.send("s"), "S05");
+    assert_eq!(gdb.send("p0"), "05");
+}
+
+#[test]
+fn bad_checksum_is_rejected() {
+    let (mut gdb, _) = connect();
+    gdb.send_raw(b"$p0#00");
+    assert_eq!(gdb.read_byte(), b'-');
+    assert_eq!(gdb.send("p0"), "00");
+}
+
+#[test]
+fn kill_ends_session() {
+    let (mut gdb, server) = connect();
+    assert_eq!(gdb.send("s"), "S05");
+    gdb.send_raw(&packet("k"));
+    assert_eq!(gdb.read_byte(), b'+');
+
+    let emu = server.join().unwrap();
+    assert_eq!(emu.pc(), 0x202);
+}
+
+#[test]
+fn disconnect_while_running_ends_session() {
+    let (mut gdb, server) = connect();
+    gdb.send_raw(&packet("c"));
+    assert_eq!(gdb.read_byte(), b'+');
+    thread::sleep(Duration::from_millis(50));
+    drop(gdb);
+
+    // The ROM spins forever, so only noticing the disconnect ends the session
+    for _ in 0..100 {
+        if server.is_finished() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert!(server.is_finished(), "still serving after GDB disconnected");
+    assert_eq!(server.join().unwrap().pc(), 0x208);
+}